@@ -0,0 +1,90 @@
+use std::{
+    env,
+    error::Error,
+    process,
+    time::{Duration, Instant},
+};
+use vlfd_rs::{Board, IoConfig, IoFrame};
+
+fn main() {
+    if let Err(err) = real_main() {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn real_main() -> Result<(), Box<dyn Error>> {
+    let mut frames = 256usize;
+    let mut frame_words = 8usize;
+    let mut iterations = 100usize;
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--frames" => frames = args.next().ok_or("missing value for --frames")?.parse()?,
+            "--frame-words" => {
+                frame_words = args
+                    .next()
+                    .ok_or("missing value for --frame-words")?
+                    .parse()?
+            }
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .ok_or("missing value for --iterations")?
+                    .parse()?
+            }
+            other => return Err(format!("unknown flag `{other}`").into()),
+        }
+    }
+    if frame_words % 4 != 0 {
+        return Err("--frame-words must be a multiple of 4".into());
+    }
+
+    let mut board = Board::open()?;
+    let mut io = board.configure_io(&IoConfig::default())?;
+    let tx = vec![vec![0x1234u16; frame_words]; frames];
+    let mut rx = vec![vec![0u16; frame_words]; frames];
+
+    let per_frame = measure(iterations, || {
+        for (tx, rx) in tx.iter().zip(&mut rx) {
+            io.transfer(tx, rx)?;
+        }
+        Ok(())
+    })?;
+    let batched = measure(iterations, || {
+        let mut batch = tx
+            .iter()
+            .zip(&mut rx)
+            .map(|(tx, rx)| IoFrame::new(tx, rx))
+            .collect::<Vec<_>>();
+        io.transfer_batch(&mut batch)
+    })?;
+    io.finish()?;
+
+    println!("frames={frames} frame_words={frame_words} iterations={iterations}");
+    for (mode, best) in [("per-frame", per_frame), ("batch", batched)] {
+        println!(
+            "mode={mode} best={best:?} frames_per_sec={:.0}",
+            frames as f64 / best.as_secs_f64().max(f64::MIN_POSITIVE)
+        );
+    }
+    println!(
+        "speedup={:.2}",
+        per_frame.as_secs_f64() / batched.as_secs_f64().max(f64::MIN_POSITIVE)
+    );
+    Ok(())
+}
+
+fn measure<F>(iterations: usize, mut run: F) -> Result<Duration, Box<dyn Error>>
+where
+    F: FnMut() -> vlfd_rs::Result<()>,
+{
+    let mut best = Duration::MAX;
+    for _ in 0..iterations {
+        let started = Instant::now();
+        run()?;
+        best = best.min(started.elapsed());
+    }
+    Ok(best)
+}
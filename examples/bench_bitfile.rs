@@ -0,0 +1,96 @@
+use std::{
+    env,
+    error::Error,
+    io::Cursor,
+    process, thread,
+    time::{Duration, Instant},
+};
+use vlfd_rs::{load_bitfile_from_reader, parse_bitfile_parallel};
+
+fn main() {
+    if let Err(err) = real_main() {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn real_main() -> Result<(), Box<dyn Error>> {
+    let mut megabytes = 40usize;
+    let mut iterations = 3usize;
+    let mut threads = thread::available_parallelism().map_or(1, usize::from);
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--megabytes" => {
+                megabytes = args
+                    .next()
+                    .ok_or("missing value for --megabytes")?
+                    .parse()?
+            }
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .ok_or("missing value for --iterations")?
+                    .parse()?
+            }
+            "--threads" => threads = args.next().ok_or("missing value for --threads")?.parse()?,
+            other => return Err(format!("unknown flag `{other}`").into()),
+        }
+    }
+
+    let data = synthetic_bitfile(megabytes * 1024 * 1024);
+    println!(
+        "input_bytes={} iterations={iterations} threads={threads}",
+        data.len()
+    );
+
+    let sequential = measure(iterations, || {
+        load_bitfile_from_reader(Cursor::new(&data)).map(|words| words.len())
+    })?;
+    let parallel = measure(iterations, || {
+        parse_bitfile_parallel(&data, threads).map(|words| words.len())
+    })?;
+
+    print_result("sequential", data.len(), sequential);
+    print_result("parallel", data.len(), parallel);
+    println!(
+        "speedup={:.2}",
+        sequential.as_secs_f64() / parallel.as_secs_f64().max(f64::MIN_POSITIVE)
+    );
+    Ok(())
+}
+
+fn synthetic_bitfile(target_bytes: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(target_bytes + 64);
+    let mut word = 0u16;
+    while data.len() < target_bytes {
+        for index in 0..8 {
+            if index > 0 {
+                data.push(b'_');
+            }
+            data.extend_from_slice(format!("{word:04x}").as_bytes());
+            word = word.wrapping_mul(31).wrapping_add(7);
+        }
+        data.push(b'\n');
+    }
+    data
+}
+
+fn measure<F>(iterations: usize, mut parse: F) -> Result<Duration, Box<dyn Error>>
+where
+    F: FnMut() -> vlfd_rs::Result<usize>,
+{
+    let mut best = Duration::MAX;
+    for _ in 0..iterations.max(1) {
+        let started = Instant::now();
+        parse()?;
+        best = best.min(started.elapsed());
+    }
+    Ok(best)
+}
+
+fn print_result(mode: &str, bytes: usize, elapsed: Duration) {
+    let mib_per_sec = bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!("mode={mode} best={elapsed:?} mib_per_sec={mib_per_sec:.1}");
+}
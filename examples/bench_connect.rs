@@ -0,0 +1,61 @@
+use std::{
+    env,
+    error::Error,
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use vlfd_rs::Board;
+
+fn main() {
+    if let Err(err) = real_main() {
+        eprintln!("error: {err}");
+        process::exit(1);
+    }
+}
+
+fn real_main() -> Result<(), Box<dyn Error>> {
+    let mut iterations = 10usize;
+    let mut cache = env::temp_dir().join("vlfd-bench-connect.cache");
+
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--iterations" => {
+                iterations = args
+                    .next()
+                    .ok_or("missing value for --iterations")?
+                    .parse()?
+            }
+            "--cache" => cache = PathBuf::from(args.next().ok_or("missing value for --cache")?),
+            other => return Err(format!("unknown flag `{other}`").into()),
+        }
+    }
+
+    let cold = measure(iterations, || Board::open()?.close())?;
+    // Prime the cache once so every measured iteration takes the warm path.
+    Board::open_cached(&cache)?.close()?;
+    let warm = measure(iterations, || Board::open_cached(&cache)?.close())?;
+
+    println!("iterations={iterations} cache={}", cache.display());
+    println!("mode=cold best={:?}", cold);
+    println!("mode=warm best={:?}", warm);
+    println!(
+        "speedup={:.2}",
+        cold.as_secs_f64() / warm.as_secs_f64().max(f64::MIN_POSITIVE)
+    );
+    Ok(())
+}
+
+fn measure<F>(iterations: usize, mut connect: F) -> Result<Duration, Box<dyn Error>>
+where
+    F: FnMut() -> vlfd_rs::Result<()>,
+{
+    let mut best = Duration::MAX;
+    for _ in 0..iterations.max(1) {
+        let started = Instant::now();
+        connect()?;
+        best = best.min(started.elapsed());
+    }
+    Ok(best)
+}
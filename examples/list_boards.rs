@@ -0,0 +1,46 @@
+use std::process;
+use vlfd_rs::{ScanEntry, scan};
+
+fn main() {
+    let entries = match scan() {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("error: {err}");
+            process::exit(1);
+        }
+    };
+
+    if entries.is_empty() {
+        println!("no boards found");
+    }
+    for entry in entries {
+        let location = entry.location();
+        let port = format!("{}-{:?}", location.bus_number, location.port_numbers);
+        match entry {
+            ScanEntry::Ready(board) => {
+                let config = &board.config;
+                println!(
+                    "{port} serial={} smims={}.{}.{} fifo_words={} flash_blocks={} programmed={} ping={}",
+                    board.serial_number.as_deref().unwrap_or("-"),
+                    config.smims_major_version(),
+                    config.smims_sub_version(),
+                    config.smims_patch_version(),
+                    config.fifo_size_words(),
+                    config.flash_total_block(),
+                    config.is_programmed(),
+                    if board.ping_ok { "ok" } else { "failed" },
+                );
+            }
+            ScanEntry::Failed {
+                serial_number,
+                failure,
+                ..
+            } => {
+                println!(
+                    "{port} serial={} unavailable: {failure:?}",
+                    serial_number.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+    }
+}
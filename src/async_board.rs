@@ -0,0 +1,361 @@
+use crate::cancel::CancelSource;
+use crate::error::{Error, Result};
+use crate::program::program_bitfile;
+use crate::session::{Board, BoardMode, IoConfig, IoSession};
+use crate::transport::Transport;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+/// A [`Board`] served by a dedicated worker thread, with async methods that
+/// work on any executor.
+///
+/// Requests run one at a time in submission order. The worker always runs
+/// a request to completion: dropping the future of a
+/// [`transfer`](Self::transfer) only discards its result, so the FIFO
+/// transaction is never left half done. Dropping the future of a
+/// [`program`](Self::program) cancels it within one bitstream chunk and
+/// returns the board to control mode.
+///
+/// Dropping the `AsyncBoard` lets the worker finish its queue, end any IO
+/// session and release the device in the background; use
+/// [`close`](Self::close) to wait for that.
+pub struct AsyncBoard {
+    commands: Sender<Command>,
+    worker: Option<JoinHandle<()>>,
+}
+
+enum Command {
+    ConfigureIo(IoConfig, ReplySender<()>),
+    Transfer {
+        tx: Vec<u16>,
+        rx_len: usize,
+        reply: ReplySender<Vec<u16>>,
+    },
+    FinishIo(ReplySender<()>),
+    Program(PathBuf, CancelSource, ReplySender<()>),
+    Close(ReplySender<()>),
+}
+
+impl AsyncBoard {
+    /// Opens the default board on a new worker thread.
+    pub async fn open() -> Result<Self> {
+        Self::spawn(Board::open)?.await
+    }
+
+    /// Moves an opened board, over any transport, onto a worker thread.
+    pub fn from_board<T: Transport + Send + 'static>(board: Board<T>) -> Result<Self> {
+        let (commands, receiver) = mpsc::channel();
+        let worker = thread::Builder::new()
+            .name("vlfd-board".into())
+            .spawn(move || run_board(board, receiver))?;
+        Ok(Self {
+            commands,
+            worker: Some(worker),
+        })
+    }
+
+    fn spawn<T: Transport + Send + 'static>(
+        open: impl FnOnce() -> Result<Board<T>> + Send + 'static,
+    ) -> Result<impl Future<Output = Result<Self>>> {
+        let (commands, receiver) = mpsc::channel();
+        let (ready, opened) = reply();
+        let worker =
+            thread::Builder::new()
+                .name("vlfd-board".into())
+                .spawn(move || match open() {
+                    Ok(board) => {
+                        ready.send(Ok(()));
+                        run_board(board, receiver);
+                    }
+                    Err(err) => ready.send(Err(err)),
+                })?;
+        Ok(async move {
+            opened.await?;
+            Ok(Self {
+                commands,
+                worker: Some(worker),
+            })
+        })
+    }
+
+    /// Async [`Board::configure_io`]. The worker keeps the IO session open
+    /// until [`finish_io`](Self::finish_io) or [`close`](Self::close).
+    pub async fn configure_io(&self, settings: &IoConfig) -> Result<()> {
+        let settings = settings.clone();
+        self.request(|reply| Command::ConfigureIo(settings, reply))
+            .await
+    }
+
+    /// Async [`IoSession::transfer`].
+    pub async fn transfer(&self, tx: &[u16], rx: &mut [u16]) -> Result<()> {
+        let tx = tx.to_vec();
+        let rx_len = rx.len();
+        let received = self
+            .request(|reply| Command::Transfer { tx, rx_len, reply })
+            .await?;
+        rx.copy_from_slice(&received);
+        Ok(())
+    }
+
+    /// Async [`IoSession::finish`].
+    pub async fn finish_io(&self) -> Result<()> {
+        self.request(Command::FinishIo).await
+    }
+
+    /// Async [`crate::Programmer::program`]; the bitfile is parsed on the
+    /// worker thread.
+    pub async fn program(&self, bitfile: impl AsRef<Path>) -> Result<()> {
+        let bitfile = bitfile.as_ref().to_path_buf();
+        let cancel = CancelOnDrop(CancelSource::new());
+        let source = cancel.0.clone();
+        self.request(|reply| Command::Program(bitfile, source, reply))
+            .await
+    }
+
+    /// Ends any IO session, closes the board and waits for the worker to
+    /// exit.
+    pub async fn close(mut self) -> Result<()> {
+        let result = self.request(Command::Close).await;
+        if let Some(worker) = self.worker.take() {
+            // The worker exits right after answering.
+            let _ = worker.join();
+        }
+        result
+    }
+
+    async fn request<R>(&self, command: impl FnOnce(ReplySender<R>) -> Command) -> Result<R> {
+        let (sender, reply) = reply();
+        // A stopped worker drops the command, which fails the reply.
+        let _ = self.commands.send(command(sender));
+        reply.await
+    }
+}
+
+/// Async counterpart of [`crate::Programmer`].
+pub struct AsyncProgrammer {
+    board: AsyncBoard,
+}
+
+impl AsyncProgrammer {
+    pub async fn open() -> Result<Self> {
+        Ok(Self {
+            board: AsyncBoard::open().await?,
+        })
+    }
+
+    pub fn board(&self) -> &AsyncBoard {
+        &self.board
+    }
+
+    /// See [`AsyncBoard::program`].
+    pub async fn program(&mut self, bitfile: impl AsRef<Path>) -> Result<()> {
+        self.board.program(bitfile).await
+    }
+
+    pub async fn close(self) -> Result<()> {
+        self.board.close().await
+    }
+}
+
+struct CancelOnDrop(CancelSource);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+fn run_board<T: Transport>(mut board: Board<T>, commands: Receiver<Command>) {
+    while let Ok(command) = commands.recv() {
+        match command {
+            Command::ConfigureIo(settings, reply) => {
+                let exit = match board.configure_io(&settings) {
+                    Ok(io) => {
+                        reply.send(Ok(()));
+                        run_io(io, &commands)
+                    }
+                    Err(err) => {
+                        reply.send(Err(err));
+                        IoExit::Finished
+                    }
+                };
+                match exit {
+                    IoExit::Finished => {}
+                    IoExit::Close(reply, finished) => {
+                        let closed = board.close();
+                        reply.send(finished.and(closed));
+                        return;
+                    }
+                    IoExit::Disconnected => return,
+                }
+            }
+            Command::Transfer { reply, .. } => reply.send(Err(not_in_io_mode(&board))),
+            Command::FinishIo(reply) => reply.send(Err(not_in_io_mode(&board))),
+            Command::Program(bitfile, cancel, reply) => {
+                // The request's own source stands in for the board's while
+                // it runs, so dropping its future cancels only this upload.
+                let previous = board.replace_cancel_source(cancel);
+                let result = program_bitfile(&mut board, &bitfile);
+                board.replace_cancel_source(previous);
+                reply.send(result);
+            }
+            Command::Close(reply) => {
+                reply.send(board.close());
+                return;
+            }
+        }
+    }
+}
+
+enum IoExit {
+    Finished,
+    Close(ReplySender<()>, Result<()>),
+    Disconnected,
+}
+
+/// Serves requests while `io` is open. Dropping `io` on disconnect runs the
+/// session's own cleanup.
+fn run_io<T: Transport>(mut io: IoSession<'_, T>, commands: &Receiver<Command>) -> IoExit {
+    while let Ok(command) = commands.recv() {
+        match command {
+            Command::Transfer { tx, rx_len, reply } => {
+                let mut rx = vec![0u16; rx_len];
+                reply.send(io.transfer(&tx, &mut rx).map(|()| rx));
+            }
+            Command::FinishIo(reply) => {
+                reply.send(io.finish());
+                return IoExit::Finished;
+            }
+            Command::ConfigureIo(_, reply) | Command::Program(_, _, reply) => {
+                reply.send(Err(Error::Busy {
+                    current_operation: "vericomm",
+                }));
+            }
+            Command::Close(reply) => return IoExit::Close(reply, io.finish()),
+        }
+    }
+    IoExit::Disconnected
+}
+
+fn not_in_io_mode<T: Transport>(board: &Board<T>) -> Error {
+    Error::InvalidMode {
+        expected: BoardMode::VeriComm.as_str(),
+        actual: board.mode().as_str(),
+    }
+}
+
+struct ReplySlot<R> {
+    value: Option<Result<R>>,
+    waker: Option<Waker>,
+}
+
+/// Worker side of a one-shot reply. Dropping it unanswered resolves the
+/// reply with [`Error::WorkerStopped`].
+struct ReplySender<R>(Arc<Mutex<ReplySlot<R>>>);
+
+/// Future side of a one-shot reply.
+struct Reply<R>(Arc<Mutex<ReplySlot<R>>>);
+
+fn reply<R>() -> (ReplySender<R>, Reply<R>) {
+    let slot = Arc::new(Mutex::new(ReplySlot {
+        value: None,
+        waker: None,
+    }));
+    (ReplySender(Arc::clone(&slot)), Reply(slot))
+}
+
+fn lock_slot<R>(slot: &Mutex<ReplySlot<R>>) -> MutexGuard<'_, ReplySlot<R>> {
+    slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<R> ReplySender<R> {
+    fn send(self, value: Result<R>) {
+        self.resolve(value);
+    }
+
+    fn resolve(&self, value: Result<R>) {
+        let waker = {
+            let mut slot = lock_slot(&self.0);
+            if slot.value.is_some() {
+                return;
+            }
+            slot.value = Some(value);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<R> Drop for ReplySender<R> {
+    fn drop(&mut self) {
+        self.resolve(Err(Error::WorkerStopped));
+    }
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = lock_slot(&self.0);
+        match slot.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncBoard;
+    use crate::{Board, ErrorKind, IoConfig};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn async_board_serves_an_io_session_from_its_worker() {
+        let board = AsyncBoard::from_board(Board::open_mock().unwrap()).unwrap();
+        block_on(async {
+            let mut rx = [0u16; 8];
+            let err = board.transfer(&[1; 8], &mut rx).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidMode);
+
+            board.configure_io(&IoConfig::default()).await.unwrap();
+            board.transfer(&[0x1234; 8], &mut rx).await.unwrap();
+            assert_eq!(rx, [0x1234; 8]);
+            board.close().await.unwrap();
+        });
+    }
+}
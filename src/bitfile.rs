@@ -0,0 +1,288 @@
+use crate::error::Result;
+use crate::program::{BitfileFormat, MAX_BITFILE_LINE_BYTES, open_bitfile};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Words per line written by [`convert`], as in the vendor's files.
+pub const DEFAULT_WORDS_PER_LINE: usize = 8;
+
+/// Most words that fit on one line without exceeding
+/// [`MAX_BITFILE_LINE_BYTES`]: four digits plus a separator each.
+const MAX_WORDS_PER_LINE: usize = MAX_BITFILE_LINE_BYTES / 5;
+
+/// Data bytes per Intel HEX record.
+const HEX_RECORD_BYTES: usize = 16;
+
+/// Writes `words` in SMIMS text format: lowercase four-digit hex words
+/// joined by underscores, `words_per_line` to a line.
+///
+/// `words_per_line` is clamped to the range the parser accepts, so the
+/// output always reads back as the same words.
+pub fn write_bitfile(words: &[u16], mut out: impl Write, words_per_line: usize) -> Result<()> {
+    let mut encoder = Encoder::new(BitfileFormat::SmimsText, words_per_line);
+    encoder.write(words, &mut out)?;
+    encoder.finish(&mut out)
+}
+
+/// Re-encodes the bitfile at `input` as `format` into `output`.
+///
+/// The input format is detected as by [`crate::Programmer::program`],
+/// including gzip decompression; binary input is never detected, so it
+/// cannot be converted from. The conversion streams, so neither file is
+/// held in memory, and `output` must not be `input`: that fails with an
+/// [`io::ErrorKind::InvalidInput`] error before either file is touched.
+/// Text output uses [`DEFAULT_WORDS_PER_LINE`].
+pub fn convert(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    format: BitfileFormat,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    if let Ok(existing) = output.canonicalize() {
+        if input.canonicalize()? == existing {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bitfile conversion would overwrite its own input",
+            )
+            .into());
+        }
+    }
+    let mut reader = open_bitfile(input, None)?;
+    let mut out = BufWriter::new(File::create(output)?);
+    let mut encoder = Encoder::new(format, DEFAULT_WORDS_PER_LINE);
+    while let Some(chunk) = reader.next_chunk()? {
+        encoder.write(chunk, &mut out)?;
+    }
+    encoder.finish(&mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Incremental writer for one bitfile format.
+enum Encoder {
+    Text {
+        words_per_line: usize,
+        column: usize,
+    },
+    IntelHex {
+        address: u32,
+        pending: Vec<u8>,
+    },
+    Binary,
+}
+
+impl Encoder {
+    fn new(format: BitfileFormat, words_per_line: usize) -> Self {
+        match format {
+            BitfileFormat::SmimsText => Self::Text {
+                words_per_line: words_per_line.clamp(1, MAX_WORDS_PER_LINE),
+                column: 0,
+            },
+            BitfileFormat::IntelHex => Self::IntelHex {
+                address: 0,
+                pending: Vec::with_capacity(HEX_RECORD_BYTES),
+            },
+            BitfileFormat::Binary => Self::Binary,
+        }
+    }
+
+    fn write(&mut self, words: &[u16], out: &mut impl Write) -> Result<()> {
+        match self {
+            Self::Text {
+                words_per_line,
+                column,
+            } => {
+                for word in words {
+                    if *column > 0 {
+                        out.write_all(b"_")?;
+                    }
+                    write!(out, "{word:04x}")?;
+                    *column += 1;
+                    if column == words_per_line {
+                        out.write_all(b"\n")?;
+                        *column = 0;
+                    }
+                }
+            }
+            Self::IntelHex { address, pending } => {
+                for word in words {
+                    pending.extend_from_slice(&word.to_le_bytes());
+                    if pending.len() == HEX_RECORD_BYTES {
+                        write_hex_data(out, address, pending)?;
+                    }
+                }
+            }
+            Self::Binary => {
+                for word in words {
+                    out.write_all(&word.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, out: &mut impl Write) -> Result<()> {
+        match &mut self {
+            Self::Text { column, .. } if *column > 0 => out.write_all(b"\n")?,
+            Self::IntelHex { address, pending } => {
+                if !pending.is_empty() {
+                    write_hex_data(out, address, pending)?;
+                }
+                write_hex_record(out, 0x01, 0, &[])?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Writes `pending` as one data record at `address`, preceded by an
+/// extended linear address record whenever the upper 16 bits change.
+/// Records are aligned, so none crosses a 64 KiB boundary.
+fn write_hex_data(out: &mut impl Write, address: &mut u32, pending: &mut Vec<u8>) -> Result<()> {
+    let [upper_high, upper_low, high, low] = address.to_be_bytes();
+    if *address > 0 && [high, low] == [0, 0] {
+        write_hex_record(out, 0x04, 0, &[upper_high, upper_low])?;
+    }
+    write_hex_record(out, 0x00, u16::from_be_bytes([high, low]), pending)?;
+    *address += pending.len() as u32;
+    pending.clear();
+    Ok(())
+}
+
+fn write_hex_record(out: &mut impl Write, kind: u8, address: u16, data: &[u8]) -> Result<()> {
+    let [high, low] = address.to_be_bytes();
+    let header = [data.len() as u8, high, low, kind];
+    let sum = header
+        .iter()
+        .chain(data)
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    out.write_all(b":")?;
+    for byte in header.iter().chain(data) {
+        write!(out, "{byte:02X}")?;
+    }
+    writeln!(out, "{:02X}", sum.wrapping_neg())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert, write_bitfile};
+    use crate::{BitfileFormat, BitfileReader, Error, load_bitfile, load_bitfile_from_reader};
+    use std::{fs, path::PathBuf};
+
+    /// Deterministic xorshift words, so failures reproduce.
+    fn random_words(seed: u64, len: usize) -> Vec<u16> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u16
+            })
+            .collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vlfd-bitfile-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn written_bitfiles_parse_back_to_the_same_words() {
+        for (seed, len) in [(1, 1), (2, 7), (3, 1024), (4, 4099), (5, 20_000)] {
+            let words = random_words(seed, len);
+            for words_per_line in [0, 1, 3, 8, 1000, usize::MAX] {
+                let mut out = Vec::new();
+                write_bitfile(&words, &mut out, words_per_line).unwrap();
+                assert_eq!(
+                    load_bitfile_from_reader(out.as_slice()).unwrap(),
+                    words,
+                    "seed {seed}, {words_per_line} words per line"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn text_output_matches_the_vendor_layout() {
+        let mut out = Vec::new();
+        write_bitfile(&[0x1234, 0xabcd, 0x0001], &mut out, 2).unwrap();
+        assert_eq!(out, b"1234_abcd\n0001\n");
+    }
+
+    #[test]
+    fn sample_file_converts_between_every_format() {
+        // Vendor bitfiles cannot be redistributed here, so this stands in
+        // for one: CRLF line ends and trailing comments, as the vendor tool
+        // writes them.
+        let sample = "3d1c_0000_ffff_ffff_aa99_5566 sync\r\n\
+                      3001_2001_0000_0000_3001_8001\r\n\
+                      0000_0007_3001_6001\r\n";
+        let input = temp_path("sample.txt");
+        fs::write(&input, sample).unwrap();
+        let expected = load_bitfile(&input).unwrap();
+
+        // Large enough for the Intel HEX writer to cross a 64 KiB segment.
+        let large = temp_path("large.txt");
+        let large_words = random_words(6, 40_000);
+        let mut text = Vec::new();
+        write_bitfile(&large_words, &mut text, 8).unwrap();
+        fs::write(&large, text).unwrap();
+
+        for (source, words) in [(&input, &expected), (&large, &large_words)] {
+            for format in [
+                BitfileFormat::SmimsText,
+                BitfileFormat::IntelHex,
+                BitfileFormat::Binary,
+            ] {
+                let output = temp_path("converted");
+                convert(source, &output, format).unwrap();
+                let file = fs::File::open(&output)
+                    .map(std::io::BufReader::new)
+                    .unwrap();
+                // Binary is never detected, only forced.
+                let mut reader = match format {
+                    BitfileFormat::Binary => BitfileReader::with_format(file, format),
+                    _ => BitfileReader::new(file),
+                };
+                let mut parsed = Vec::new();
+                while let Some(chunk) = reader.next_chunk().unwrap() {
+                    parsed.extend_from_slice(chunk);
+                }
+                assert_eq!(reader.format(), Some(format));
+                assert_eq!(&parsed, words, "{format:?}");
+                fs::remove_file(output).unwrap();
+            }
+        }
+        fs::remove_file(input).unwrap();
+        fs::remove_file(large).unwrap();
+    }
+
+    #[test]
+    fn converting_a_file_onto_itself_leaves_it_intact() {
+        let path = temp_path("in-place.txt");
+        let words = random_words(7, 100);
+        let mut text = Vec::new();
+        write_bitfile(&words, &mut text, 8).unwrap();
+        fs::write(&path, &text).unwrap();
+
+        let aliased = path
+            .parent()
+            .unwrap()
+            .join(".")
+            .join(path.file_name().unwrap());
+        for output in [&path, &aliased] {
+            let err = convert(&path, output, BitfileFormat::IntelHex).unwrap_err();
+            assert!(
+                matches!(&err, Error::Io(err) if err.kind() == std::io::ErrorKind::InvalidInput),
+                "{err:?}"
+            );
+        }
+        assert_eq!(fs::read(&path).unwrap(), text);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use crate::error::{Error, Result};
+use crate::session::{Board, IoConfig};
+use std::time::{Duration, Instant};
+
+/// Number of 16-bit words moved per VeriComm IO clock cycle (64 bits).
+pub const WORDS_PER_IO_CYCLE: usize = 4;
+
+const CALIBRATION_TRIALS: usize = 8;
+const SMALL_TRANSFER_WORDS: usize = 16;
+/// Samples further than this many median absolute deviations from the
+/// median are treated as outliers (scheduler hiccups, USB retries).
+const OUTLIER_MADS: f64 = 3.0;
+
+/// Measured VeriComm throughput and the IO clock it implies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockCalibration {
+    pub trials: usize,
+    pub discarded: usize,
+    pub words_per_second: f64,
+    pub words_per_second_variance: f64,
+    pub io_clock_hz: f64,
+    pub io_clock_hz_variance: f64,
+    /// IO clock the calibrated settings' delays should produce, per
+    /// [`IoConfig::effective_frequency`].
+    pub nominal_hz: f64,
+    /// Fixed per-transfer USB cost that was factored out of the estimate.
+    pub transfer_overhead: Duration,
+}
+
+impl ClockCalibration {
+    /// Ratio of the measured clock to `nominal_hz`, for correcting the
+    /// theoretical delay-to-frequency formula on a particular board; pass
+    /// [`nominal_hz`](Self::nominal_hz) for the calibrated settings, as
+    /// [`IoConfig::set_frequency_calibrated`] does.
+    pub fn correction_factor(&self, nominal_hz: f64) -> f64 {
+        if nominal_hz <= 0.0 {
+            return 1.0;
+        }
+        self.io_clock_hz / nominal_hz
+    }
+}
+
+/// Timing of one calibration trial: a small and a large transfer issued
+/// back to back with the same settings.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TrialSample {
+    pub(crate) small: Duration,
+    pub(crate) large: Duration,
+}
+
+impl Board {
+    /// Measures the effective VeriComm word rate under `settings`.
+    ///
+    /// Each trial times a small and a FIFO-sized transfer. Taking the slope
+    /// between the two cancels the fixed USB submission cost, so the result
+    /// reflects the IO clock rather than host batching.
+    pub fn calibrate_io_clock(&mut self, settings: &IoConfig) -> Result<ClockCalibration> {
+        let large_words = usize::from(self.config().fifo_size_words())
+            .min(crate::constants::MAX_TRANSFER_WORDS)
+            / WORDS_PER_IO_CYCLE
+            * WORDS_PER_IO_CYCLE;
+        if large_words <= SMALL_TRANSFER_WORDS {
+            return Err(Error::UnexpectedResponse(
+                "FIFO too small for clock calibration",
+            ));
+        }
+
+        let mut io = self.configure_io(settings)?;
+        let tx = vec![0u16; large_words];
+        let mut rx = vec![0u16; large_words];
+        let mut samples = Vec::with_capacity(CALIBRATION_TRIALS);
+        for _ in 0..CALIBRATION_TRIALS {
+            let started = Instant::now();
+            io.transfer(&tx[..SMALL_TRANSFER_WORDS], &mut rx[..SMALL_TRANSFER_WORDS])?;
+            let small = started.elapsed();
+
+            let started = Instant::now();
+            io.transfer(&tx, &mut rx)?;
+            let large = started.elapsed();
+            samples.push(TrialSample { small, large });
+        }
+        io.finish()?;
+
+        let nominal_hz = f64::from(settings.effective_frequency());
+        summarize_trials(SMALL_TRANSFER_WORDS, large_words, nominal_hz, &samples).ok_or(
+            Error::UnexpectedResponse("clock calibration produced no usable samples"),
+        )
+    }
+}
+
+pub(crate) fn summarize_trials(
+    small_words: usize,
+    large_words: usize,
+    nominal_hz: f64,
+    samples: &[TrialSample],
+) -> Option<ClockCalibration> {
+    let extra_words = large_words.checked_sub(small_words)? as f64;
+    let rates = samples
+        .iter()
+        .filter_map(|sample| {
+            let extra = sample.large.checked_sub(sample.small)?.as_secs_f64();
+            (extra > 0.0).then_some((extra_words / extra, *sample))
+        })
+        .collect::<Vec<_>>();
+    if rates.is_empty() {
+        return None;
+    }
+
+    let median = median(rates.iter().map(|(rate, _)| *rate).collect());
+    let mad = median_abs_deviation(rates.iter().map(|(rate, _)| *rate), median);
+    let kept = rates
+        .iter()
+        .filter(|(rate, _)| mad == 0.0 || (rate - median).abs() <= OUTLIER_MADS * mad)
+        .collect::<Vec<_>>();
+
+    let count = kept.len() as f64;
+    let mean = kept.iter().map(|(rate, _)| rate).sum::<f64>() / count;
+    let variance = kept
+        .iter()
+        .map(|(rate, _)| (rate - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let overhead_secs = kept
+        .iter()
+        .map(|(rate, sample)| (sample.small.as_secs_f64() - small_words as f64 / rate).max(0.0))
+        .sum::<f64>()
+        / count;
+
+    let cycle_words = WORDS_PER_IO_CYCLE as f64;
+    Some(ClockCalibration {
+        trials: kept.len(),
+        discarded: rates.len() - kept.len(),
+        words_per_second: mean,
+        words_per_second_variance: variance,
+        io_clock_hz: mean / cycle_words,
+        io_clock_hz_variance: variance / (cycle_words * cycle_words),
+        nominal_hz,
+        transfer_overhead: Duration::from_secs_f64(overhead_secs),
+    })
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_abs_deviation(values: impl Iterator<Item = f64>, median_value: f64) -> f64 {
+    median(values.map(|value| (value - median_value).abs()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TrialSample, summarize_trials};
+    use std::time::Duration;
+
+    /// Simulates a board that moves `rate` words per second after a fixed
+    /// USB submission overhead.
+    fn simulated(rate: f64, overhead: Duration, small: usize, large: usize) -> TrialSample {
+        TrialSample {
+            small: overhead + Duration::from_secs_f64(small as f64 / rate),
+            large: overhead + Duration::from_secs_f64(large as f64 / rate),
+        }
+    }
+
+    #[test]
+    fn slope_cancels_fixed_usb_overhead() {
+        let overhead = Duration::from_micros(250);
+        let samples = vec![simulated(4_000_000.0, overhead, 16, 4096); 6];
+        let calibration = summarize_trials(16, 4096, 1_000_000.0, &samples).unwrap();
+        assert!((calibration.words_per_second - 4_000_000.0).abs() < 1.0);
+        assert!((calibration.io_clock_hz - 1_000_000.0).abs() < 1.0);
+        assert!(calibration.words_per_second_variance < 1e-3);
+        let overhead_error = calibration.transfer_overhead.abs_diff(overhead);
+        assert!(overhead_error < Duration::from_micros(1));
+    }
+
+    #[test]
+    fn outliers_are_discarded_before_averaging() {
+        let overhead = Duration::from_micros(100);
+        let mut samples = vec![
+            simulated(2_000_000.0, overhead, 16, 2048),
+            simulated(2_010_000.0, overhead, 16, 2048),
+            simulated(1_990_000.0, overhead, 16, 2048),
+            simulated(2_000_000.0, overhead, 16, 2048),
+        ];
+        // A trial stalled by the scheduler looks like a much slower clock.
+        samples.push(simulated(200_000.0, overhead, 16, 2048));
+
+        let calibration = summarize_trials(16, 2048, 500_000.0, &samples).unwrap();
+        assert_eq!(calibration.trials, 4);
+        assert_eq!(calibration.discarded, 1);
+        assert!((calibration.words_per_second - 2_000_000.0).abs() < 5_000.0);
+        assert!(calibration.words_per_second_variance > 0.0);
+        assert!((calibration.correction_factor(calibration.nominal_hz) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn unusable_samples_produce_no_estimate() {
+        let sample = TrialSample {
+            small: Duration::from_millis(2),
+            large: Duration::from_millis(1),
+        };
+        assert!(summarize_trials(16, 2048, 0.0, &[sample]).is_none());
+        assert!(summarize_trials(2048, 16, 0.0, &[sample]).is_none());
+    }
+}
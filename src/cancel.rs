@@ -0,0 +1,251 @@
+use crate::error::{Error, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+struct Shared {
+    cancelled: AtomicBool,
+    callbacks: Mutex<Vec<Callback>>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn callbacks(&self) -> MutexGuard<'_, Vec<Callback>> {
+        self.callbacks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Owning side of a cancellation signal.
+///
+/// Hand out [`CancelToken`]s with [`CancelSource::token`] and call
+/// [`CancelSource::cancel`] to request that every operation observing them
+/// stops at its next cancellation point.
+#[derive(Clone)]
+pub struct CancelSource {
+    shared: Arc<Shared>,
+}
+
+/// Observing side of a cancellation signal; cheap to clone and share
+/// between threads.
+#[derive(Clone)]
+pub struct CancelToken {
+    shared: Arc<Shared>,
+}
+
+impl CancelSource {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                cancelled: AtomicBool::new(false),
+                callbacks: Mutex::new(Vec::new()),
+                wake: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn token(&self) -> CancelToken {
+        CancelToken {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Requests cancellation, runs registered callbacks on the calling
+    /// thread and wakes every thread blocked in [`CancelToken::wait`].
+    ///
+    /// Only the first call has any effect.
+    pub fn cancel(&self) {
+        let callbacks = {
+            let mut callbacks = self.shared.callbacks();
+            if self.shared.cancelled.swap(true, Ordering::AcqRel) {
+                return;
+            }
+            self.shared.wake.notify_all();
+            std::mem::take(&mut *callbacks)
+        };
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Clears a previous cancellation so the source and its tokens can
+    /// guard another operation.
+    ///
+    /// Callbacks that already ran are not registered again.
+    pub fn reset(&self) {
+        let _callbacks = self.shared.callbacks();
+        self.shared.cancelled.store(false, Ordering::Release);
+    }
+}
+
+impl Default for CancelSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns [`Error::Cancelled`] for `operation` once cancellation has
+    /// been requested.
+    pub fn check(&self, operation: &'static str) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled(operation));
+        }
+        Ok(())
+    }
+
+    /// Registers `callback` to run once when cancellation is requested.
+    ///
+    /// If the token is already cancelled the callback runs immediately on
+    /// the calling thread.
+    pub fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        {
+            let mut callbacks = self.shared.callbacks();
+            if !self.is_cancelled() {
+                callbacks.push(Box::new(callback));
+                return;
+            }
+        }
+        callback();
+    }
+
+    /// Parks the calling thread until cancellation is requested.
+    pub fn wait(&self) {
+        let mut callbacks = self.shared.callbacks();
+        while !self.is_cancelled() {
+            callbacks = self
+                .shared
+                .wake
+                .wait(callbacks)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Parks the calling thread until cancellation is requested or
+    /// `timeout` elapses. Returns whether the token was cancelled.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut callbacks = self.shared.callbacks();
+        while !self.is_cancelled() {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return false;
+            };
+            callbacks = self
+                .shared
+                .wake
+                .wait_timeout(callbacks, remaining)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        true
+    }
+}
+
+impl fmt::Debug for CancelSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelSource")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancelSource;
+    use crate::error::{Error, ErrorKind};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn cancellation_is_visible_through_every_clone() {
+        let source = CancelSource::new();
+        let token = source.token();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        assert!(clone.check("test").is_ok());
+
+        source.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(clone.check("test"), Err(Error::Cancelled("test"))));
+        assert_eq!(
+            clone.check("test").unwrap_err().kind(),
+            ErrorKind::Cancelled
+        );
+    }
+
+    #[test]
+    fn callbacks_run_exactly_once() {
+        let source = CancelSource::new();
+        let token = source.token();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&calls);
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        source.cancel();
+        source.cancel();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Late registrations fire immediately.
+        let counter = Arc::clone(&calls);
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn waiting_threads_are_woken_by_cancel() {
+        let source = CancelSource::new();
+        let token = source.token();
+        assert!(!token.wait_timeout(Duration::from_millis(5)));
+
+        let waiter = thread::spawn(move || token.wait());
+        thread::sleep(Duration::from_millis(10));
+        source.cancel();
+        waiter.join().unwrap();
+        assert!(source.token().wait_timeout(Duration::ZERO));
+    }
+
+    #[test]
+    fn reset_rearms_every_token() {
+        let source = CancelSource::new();
+        let token = source.token();
+        source.cancel();
+        source.reset();
+        assert!(!token.is_cancelled());
+        assert!(!token.wait_timeout(Duration::from_millis(1)));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        source.clone().cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
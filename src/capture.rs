@@ -0,0 +1,137 @@
+use crate::error::Result;
+use crate::session::IoSession;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+/// Host-side timing attached to a received frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureStamp {
+    /// When the USB completion for the frame was observed, before
+    /// decryption. Never earlier than the previous frame's stamp.
+    pub host_monotonic: Instant,
+    /// Frame number within the window, starting at 0 with no gaps.
+    pub seq: u64,
+    /// Words received before this frame.
+    pub words_since_start: u64,
+    /// Time from submitting the frame to observing its completion. The
+    /// frame's data was latched somewhere inside this window, so it bounds
+    /// the uncertainty of `host_monotonic`.
+    pub transfer_duration: Duration,
+}
+
+/// Submission and completion instants of one pipelined read.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReadTiming {
+    pub(crate) submitted: Instant,
+    pub(crate) completed: Instant,
+}
+
+/// Assigns sequence numbers and monotonic stamps to retired frames.
+#[derive(Debug, Default)]
+pub(crate) struct StampClock {
+    last: Option<Instant>,
+    seq: u64,
+    words: u64,
+}
+
+impl StampClock {
+    pub(crate) fn stamp(&mut self, timing: ReadTiming, frame_words: usize) -> CaptureStamp {
+        let host_monotonic = self
+            .last
+            .map_or(timing.completed, |last| last.max(timing.completed));
+        let stamp = CaptureStamp {
+            host_monotonic,
+            seq: self.seq,
+            words_since_start: self.words,
+            transfer_duration: timing.completed.saturating_duration_since(timing.submitted),
+        };
+        self.last = Some(host_monotonic);
+        self.seq += 1;
+        self.words += frame_words as u64;
+        stamp
+    }
+}
+
+impl IoSession<'_> {
+    /// Streams `frame_words`-word reads with `depth` transfers in flight,
+    /// clocking out idle (zero) words, and hands each decrypted frame to
+    /// `on_frame` with its [`CaptureStamp`].
+    ///
+    /// Stops when `on_frame` returns [`ControlFlow::Break`]; transfers still
+    /// in flight are cancelled. Returns the number of frames delivered.
+    pub fn stream_read_timestamped<F>(
+        &mut self,
+        frame_words: usize,
+        depth: usize,
+        mut on_frame: F,
+    ) -> Result<u64>
+    where
+        F: FnMut(&[u16], CaptureStamp) -> ControlFlow<()>,
+    {
+        let idle = vec![0u16; frame_words];
+        let mut frame = vec![0u16; frame_words];
+        let mut window = self.transfer_window(frame_words, depth)?;
+        while !window.is_full() {
+            window.submit(&idle)?;
+        }
+
+        let mut delivered = 0u64;
+        loop {
+            let stamp = window.receive_into_stamped(&mut frame)?;
+            delivered += 1;
+            if on_frame(&frame, stamp).is_break() {
+                return Ok(delivered);
+            }
+            window.submit(&idle)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReadTiming, StampClock};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn stamps_are_monotonic_and_contiguous() {
+        let start = Instant::now();
+        let at = |micros: u64| start + Duration::from_micros(micros);
+        let mut clock = StampClock::default();
+
+        // The second completion was observed before the first one was
+        // retired (it was stored while waiting for the oldest read).
+        let timings = [(at(0), at(500)), (at(100), at(400)), (at(200), at(900))];
+        let stamps = timings
+            .iter()
+            .map(|&(submitted, completed)| {
+                clock.stamp(
+                    ReadTiming {
+                        submitted,
+                        completed,
+                    },
+                    64,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            stamps.iter().map(|stamp| stamp.seq).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            stamps
+                .iter()
+                .map(|stamp| stamp.words_since_start)
+                .collect::<Vec<_>>(),
+            [0, 64, 128]
+        );
+        assert!(
+            stamps
+                .windows(2)
+                .all(|pair| pair[0].host_monotonic <= pair[1].host_monotonic)
+        );
+        assert_eq!(stamps[1].host_monotonic, at(500));
+        assert_eq!(stamps[1].transfer_duration, Duration::from_micros(300));
+        assert_eq!(stamps[2].transfer_duration, Duration::from_micros(700));
+    }
+}
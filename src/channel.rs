@@ -0,0 +1,539 @@
+use crate::calibration::WORDS_PER_IO_CYCLE;
+use crate::error::{Error, Result};
+use crate::session::IoSession;
+use crate::transport::Transport;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What happens when a send does not fit in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Reject the new send with [`TrySendError::Full`]. On the receive side
+    /// the writer waits for the consumer instead of dropping data.
+    Reject,
+    /// Drop whole queued sends, oldest first, until the new one fits. On
+    /// the receive side the oldest received words are dropped.
+    DropOldest,
+}
+
+/// What happens to queued words once the channel body returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    Flush,
+    Discard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelOptions {
+    /// Maximum number of words queued in each direction.
+    pub capacity_words: usize,
+    pub overflow: OverflowPolicy,
+    pub shutdown: ShutdownMode,
+}
+
+impl Default for ChannelOptions {
+    fn default() -> Self {
+        Self {
+            capacity_words: 64 * 1024,
+            overflow: OverflowPolicy::Reject,
+            shutdown: ShutdownMode::Flush,
+        }
+    }
+}
+
+/// Counters reported when a channel shuts down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelTotals {
+    pub sent_words: u64,
+    pub rejected_sends: u64,
+    /// Queued words dropped by [`OverflowPolicy::DropOldest`].
+    pub dropped_words: u64,
+    pub transferred_words: u64,
+    /// Queued words thrown away by [`ShutdownMode::Discard`].
+    pub discarded_words: u64,
+    /// Received words dropped because the receive queue was full or the
+    /// receiver was gone.
+    pub rx_dropped_words: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError {
+    /// The queue is full and the policy is [`OverflowPolicy::Reject`].
+    Full,
+    /// The channel is shutting down.
+    Closed,
+    /// Sends must be a whole number of 64-bit IO cycles and fit in the
+    /// queue.
+    InvalidLength { words: usize },
+}
+
+/// Queue state shared between the sender, receiver and writer. Kept free
+/// of locking so the overflow rules can be tested directly.
+#[derive(Debug, Default)]
+struct ChannelQueue {
+    tx: VecDeque<u16>,
+    /// Lengths of the queued sends (the front one possibly partially
+    /// taken), so drops never split a send.
+    tx_sends: VecDeque<usize>,
+    rx: VecDeque<u16>,
+    closed: bool,
+    receiver_dropped: bool,
+    totals: ChannelTotals,
+}
+
+impl ChannelQueue {
+    fn push_tx(
+        &mut self,
+        words: &[u16],
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> std::result::Result<(), TrySendError> {
+        if self.closed {
+            return Err(TrySendError::Closed);
+        }
+        if words.is_empty() || words.len() % WORDS_PER_IO_CYCLE != 0 || words.len() > capacity {
+            return Err(TrySendError::InvalidLength { words: words.len() });
+        }
+        if self.tx.len() + words.len() > capacity {
+            match policy {
+                OverflowPolicy::Reject => {
+                    self.totals.rejected_sends += 1;
+                    return Err(TrySendError::Full);
+                }
+                OverflowPolicy::DropOldest => {
+                    while self.tx.len() + words.len() > capacity {
+                        let Some(len) = self.tx_sends.pop_front() else {
+                            break;
+                        };
+                        self.tx.drain(..len);
+                        self.totals.dropped_words += len as u64;
+                    }
+                }
+            }
+        }
+        self.tx.extend(words);
+        self.tx_sends.push_back(words.len());
+        self.totals.sent_words += words.len() as u64;
+        Ok(())
+    }
+
+    /// Takes up to `max_words` queued words for one transfer.
+    fn take_tx(&mut self, max_words: usize) -> Vec<u16> {
+        let take = self.tx.len().min(max_words);
+        let mut remaining = take;
+        while remaining > 0 {
+            let front = self
+                .tx_sends
+                .front_mut()
+                .expect("queued words always belong to a send");
+            if *front <= remaining {
+                remaining -= *front;
+                self.tx_sends.pop_front();
+            } else {
+                *front -= remaining;
+                remaining = 0;
+            }
+        }
+        self.tx.drain(..take).collect()
+    }
+
+    fn discard_tx(&mut self) {
+        self.totals.discarded_words += self.tx.len() as u64;
+        self.tx.clear();
+        self.tx_sends.clear();
+    }
+
+    /// Queues received words. Returns `false` when the words must wait for
+    /// the consumer to make room.
+    fn push_rx(&mut self, words: &[u16], capacity: usize, policy: OverflowPolicy) -> bool {
+        if self.receiver_dropped {
+            self.totals.rx_dropped_words += words.len() as u64;
+            return true;
+        }
+        let overflow = (self.rx.len() + words.len()).saturating_sub(capacity);
+        if overflow > 0 {
+            match policy {
+                OverflowPolicy::Reject => return false,
+                OverflowPolicy::DropOldest => {
+                    let from_queue = overflow.min(self.rx.len());
+                    self.rx.drain(..from_queue);
+                    self.totals.rx_dropped_words += overflow as u64;
+                    self.rx.extend(&words[overflow - from_queue..]);
+                    return true;
+                }
+            }
+        }
+        self.rx.extend(words);
+        true
+    }
+}
+
+struct Shared {
+    queue: Mutex<ChannelQueue>,
+    changed: Condvar,
+    options: ChannelOptions,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, ChannelQueue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Producer side of an IO channel; see [`IoSession::run_channel`].
+#[derive(Clone)]
+pub struct IoSender {
+    shared: Arc<Shared>,
+}
+
+/// Consumer side of an IO channel; see [`IoSession::run_channel`].
+pub struct IoReceiver {
+    shared: Arc<Shared>,
+}
+
+impl IoSender {
+    /// Queues `words` without waiting on USB.
+    ///
+    /// The send is queued whole or not at all; under
+    /// [`OverflowPolicy::DropOldest`] whole older sends are dropped to make
+    /// room. Only a short, uncontended lock is taken.
+    pub fn try_send(&self, words: &[u16]) -> std::result::Result<(), TrySendError> {
+        let options = self.shared.options;
+        let result = self
+            .shared
+            .lock()
+            .push_tx(words, options.capacity_words, options.overflow);
+        if result.is_ok() {
+            self.shared.changed.notify_all();
+        }
+        result
+    }
+
+    pub fn queued_words(&self) -> usize {
+        self.shared.lock().tx.len()
+    }
+}
+
+impl IoReceiver {
+    /// Appends every received word to `out` without waiting.
+    pub fn try_recv_into(&self, out: &mut Vec<u16>) -> usize {
+        let mut queue = self.shared.lock();
+        let words = queue.rx.len();
+        out.extend(queue.rx.drain(..));
+        drop(queue);
+        self.shared.changed.notify_all();
+        words
+    }
+
+    /// Waits up to `timeout` for received words and appends them to `out`.
+    pub fn recv_into(&self, out: &mut Vec<u16>, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut queue = self.shared.lock();
+        while queue.rx.is_empty() {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return 0;
+            };
+            queue = self
+                .shared
+                .changed
+                .wait_timeout(queue, remaining)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        let words = queue.rx.len();
+        out.extend(queue.rx.drain(..));
+        drop(queue);
+        self.shared.changed.notify_all();
+        words
+    }
+}
+
+impl Drop for IoReceiver {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.receiver_dropped = true;
+        queue.totals.rx_dropped_words += queue.rx.len() as u64;
+        queue.rx.clear();
+        drop(queue);
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Marks the channel closed when the body returns or unwinds.
+struct CloseOnDrop<'a>(&'a Shared);
+
+impl Drop for CloseOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.lock().closed = true;
+        self.0.changed.notify_all();
+    }
+}
+
+impl<T: Transport> IoSession<'_, T> {
+    /// Decouples producers and consumers from USB through bounded queues.
+    ///
+    /// `body` runs on a scoped thread with the channel's [`IoSender`] and
+    /// [`IoReceiver`]; senders may be cloned into further threads spawned by
+    /// `body`. Meanwhile the calling thread drains the send queue through
+    /// the encrypted FIFO path in FIFO-sized transfers and queues the words
+    /// read back. When `body` returns the queue is flushed or discarded per
+    /// [`ChannelOptions::shutdown`], and the body's result is returned with
+    /// the channel totals.
+    ///
+    /// With [`OverflowPolicy::Reject`] a full receive queue stalls the
+    /// writer until the receiver drains it; drop the receiver if received
+    /// words are not needed.
+    pub fn run_channel<R, F>(
+        &mut self,
+        options: ChannelOptions,
+        body: F,
+    ) -> Result<(R, ChannelTotals)>
+    where
+        F: FnOnce(IoSender, IoReceiver) -> R + Send,
+        R: Send,
+    {
+        let capacity_words = options.capacity_words / WORDS_PER_IO_CYCLE * WORDS_PER_IO_CYCLE;
+        if capacity_words == 0 {
+            return Err(Error::InvalidBufferLength {
+                context: "io channel capacity",
+                expected: WORDS_PER_IO_CYCLE,
+                actual: options.capacity_words,
+            });
+        }
+        let chunk_words = self.checked_chunk_words()?.min(capacity_words);
+
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(ChannelQueue::default()),
+            changed: Condvar::new(),
+            options,
+        });
+        let sender = IoSender {
+            shared: Arc::clone(&shared),
+        };
+        let receiver = IoReceiver {
+            shared: Arc::clone(&shared),
+        };
+
+        thread::scope(|scope| {
+            let body_shared = Arc::clone(&shared);
+            let handle = scope.spawn(move || {
+                let _close = CloseOnDrop(&body_shared);
+                body(sender, receiver)
+            });
+            let drained = self.drain_channel(&shared, chunk_words);
+            if drained.is_err() {
+                // Unblock producers waiting on a writer that is gone.
+                shared.lock().closed = true;
+                shared.changed.notify_all();
+            }
+            let output = match handle.join() {
+                Ok(output) => output,
+                Err(panic) => std::panic::resume_unwind(panic),
+            };
+            drained?;
+            Ok((output, shared.lock().totals))
+        })
+    }
+
+    fn drain_channel(&mut self, shared: &Shared, chunk_words: usize) -> Result<()> {
+        let options = shared.options;
+        let mut rx = Vec::with_capacity(chunk_words);
+        loop {
+            let tx = {
+                let mut queue = shared.lock();
+                while queue.tx.is_empty() && !queue.closed {
+                    queue = shared
+                        .changed
+                        .wait(queue)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                if queue.closed && options.shutdown == ShutdownMode::Discard {
+                    queue.discard_tx();
+                }
+                if queue.tx.is_empty() {
+                    return Ok(());
+                }
+                queue.take_tx(chunk_words)
+            };
+
+            rx.clear();
+            rx.resize(tx.len(), 0);
+            self.transfer(&tx, &mut rx)?;
+
+            let mut queue = shared.lock();
+            queue.totals.transferred_words += tx.len() as u64;
+            while !queue.push_rx(&rx, options.capacity_words, options.overflow) {
+                queue = shared
+                    .changed
+                    .wait(queue)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            drop(queue);
+            shared.changed.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelOptions, ChannelQueue, OverflowPolicy, ShutdownMode, TrySendError};
+    use crate::mock::MockTransport;
+    use crate::{Board, IoConfig, IoSender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn reject_policy_refuses_sends_that_do_not_fit() {
+        let mut queue = ChannelQueue::default();
+        queue.push_tx(&[1; 8], 12, OverflowPolicy::Reject).unwrap();
+        assert_eq!(
+            queue.push_tx(&[2; 8], 12, OverflowPolicy::Reject),
+            Err(TrySendError::Full)
+        );
+        queue.push_tx(&[3; 4], 12, OverflowPolicy::Reject).unwrap();
+        assert_eq!(queue.totals.rejected_sends, 1);
+        assert_eq!(queue.totals.sent_words, 12);
+        assert_eq!(
+            queue.push_tx(&[1; 6], 12, OverflowPolicy::Reject),
+            Err(TrySendError::InvalidLength { words: 6 })
+        );
+    }
+
+    #[test]
+    fn drop_oldest_discards_whole_sends_even_when_partially_taken() {
+        let mut queue = ChannelQueue::default();
+        queue
+            .push_tx(&[1; 8], 14, OverflowPolicy::DropOldest)
+            .unwrap();
+        queue
+            .push_tx(&[2; 4], 14, OverflowPolicy::DropOldest)
+            .unwrap();
+        assert_eq!(queue.take_tx(4), vec![1; 4]);
+
+        // The rest of send 1 is dropped as a unit; send 2 survives.
+        queue
+            .push_tx(&[3; 8], 14, OverflowPolicy::DropOldest)
+            .unwrap();
+        assert_eq!(queue.totals.dropped_words, 4);
+        let mut drained = queue.take_tx(16);
+        assert_eq!(drained.len(), 12);
+        assert_eq!(drained.drain(..4).collect::<Vec<_>>(), vec![2; 4]);
+        assert!(drained.iter().all(|&word| word == 3));
+        assert!(queue.tx_sends.is_empty());
+    }
+
+    #[test]
+    fn shutdown_while_full_flushes_or_discards() {
+        let mut queue = ChannelQueue::default();
+        queue.push_tx(&[1; 8], 8, OverflowPolicy::Reject).unwrap();
+        queue.closed = true;
+        assert_eq!(
+            queue.push_tx(&[1; 4], 8, OverflowPolicy::Reject),
+            Err(TrySendError::Closed)
+        );
+        // Flushing still hands out everything that was queued.
+        assert_eq!(queue.take_tx(4).len(), 4);
+        queue.discard_tx();
+        assert_eq!(queue.totals.discarded_words, 4);
+        assert!(queue.tx.is_empty());
+    }
+
+    #[test]
+    fn channels_flush_or_discard_what_is_queued_at_shutdown() {
+        for (shutdown, transferred, discarded) in
+            [(ShutdownMode::Flush, 24, 0), (ShutdownMode::Discard, 8, 16)]
+        {
+            // Holds the first transfer until the channel has closed, so
+            // two sends are still queued at shutdown.
+            let probe = Arc::new(Mutex::new(None::<IoSender>));
+            let design_probe = Arc::clone(&probe);
+            let transport = MockTransport::with_design(move |_| {
+                if let Some(probe) = design_probe.lock().unwrap().take() {
+                    while probe.try_send(&[]) != Err(TrySendError::Closed) {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+            });
+            let mut board = Board::from_transport(transport).unwrap();
+            let mut io = board.configure_io(&IoConfig::default()).unwrap();
+            let options = ChannelOptions {
+                shutdown,
+                ..ChannelOptions::default()
+            };
+            let (receiver, totals) = io
+                .run_channel(options, |sender, receiver| {
+                    *probe.lock().unwrap() = Some(sender.clone());
+                    sender.try_send(&[1; 8]).unwrap();
+                    while sender.queued_words() > 0 {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    sender.try_send(&[2; 8]).unwrap();
+                    sender.try_send(&[3; 8]).unwrap();
+                    receiver
+                })
+                .unwrap();
+            assert_eq!(totals.sent_words, 24, "{shutdown:?}");
+            assert_eq!(totals.transferred_words, transferred, "{shutdown:?}");
+            assert_eq!(totals.discarded_words, discarded, "{shutdown:?}");
+
+            let mut received = Vec::new();
+            receiver.try_recv_into(&mut received);
+            let expected = [[1; 8], [2; 8], [3; 8]].concat();
+            assert_eq!(received, expected[..transferred as usize], "{shutdown:?}");
+            io.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn a_stalled_receiver_holds_the_writer_until_it_drains() {
+        let mut board = Board::open_mock().unwrap();
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let options = ChannelOptions {
+            capacity_words: 8,
+            overflow: OverflowPolicy::Reject,
+            shutdown: ShutdownMode::Flush,
+        };
+        let sent = (0..24).collect::<Vec<u16>>();
+        let (received, totals) = io
+            .run_channel(options, |sender, receiver| {
+                let mut received = Vec::new();
+                // The first send fills the receive queue, the writer stalls
+                // with the second and the third waits in the send queue.
+                for send in sent.chunks(8) {
+                    while sender.try_send(send) == Err(TrySendError::Full) {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+                assert_eq!(sender.queued_words(), 8);
+                assert_eq!(receiver.try_recv_into(&mut received), 8);
+                while received.len() < sent.len() {
+                    receiver.recv_into(&mut received, Duration::from_secs(5));
+                }
+                received
+            })
+            .unwrap();
+        assert_eq!(received, sent);
+        assert_eq!(totals.transferred_words, 24);
+        assert_eq!(totals.rx_dropped_words, 0);
+        io.finish().unwrap();
+    }
+
+    #[test]
+    fn receive_overflow_follows_the_policy() {
+        let mut queue = ChannelQueue::default();
+        assert!(queue.push_rx(&[1, 2, 3, 4], 6, OverflowPolicy::Reject));
+        assert!(!queue.push_rx(&[5, 6, 7, 8], 6, OverflowPolicy::Reject));
+        assert!(queue.push_rx(&[5, 6, 7, 8], 6, OverflowPolicy::DropOldest));
+        assert_eq!(queue.rx, [3, 4, 5, 6, 7, 8]);
+        assert_eq!(queue.totals.rx_dropped_words, 2);
+
+        queue.receiver_dropped = true;
+        assert!(queue.push_rx(&[9; 4], 6, OverflowPolicy::Reject));
+        assert_eq!(queue.totals.rx_dropped_words, 6);
+    }
+}
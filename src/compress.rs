@@ -0,0 +1,131 @@
+use crate::error::{Error, Result};
+use std::io::{self, BufRead};
+
+/// First two bytes of every gzip member.
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub(crate) fn is_gzip(head: &[u8]) -> bool {
+    head.starts_with(&GZIP_MAGIC)
+}
+
+/// Returns `reader`, decompressing it on the fly when it starts with the
+/// gzip magic. Nothing is buffered beyond the decoder's own window.
+pub(crate) fn decompressed<'a>(mut reader: impl BufRead + 'a) -> Result<Box<dyn BufRead + 'a>> {
+    if !is_gzip(reader.fill_buf()?) {
+        return Ok(Box::new(reader));
+    }
+    gzip_decoder(reader)
+}
+
+#[cfg(feature = "compress")]
+fn gzip_decoder<'a>(reader: impl BufRead + 'a) -> Result<Box<dyn BufRead + 'a>> {
+    Ok(Box::new(io::BufReader::new(GzipStream(
+        flate2::bufread::MultiGzDecoder::new(reader),
+    ))))
+}
+
+#[cfg(not(feature = "compress"))]
+fn gzip_decoder<'a>(_reader: impl BufRead + 'a) -> Result<Box<dyn BufRead + 'a>> {
+    Err(Error::FeatureUnavailable("compress"))
+}
+
+/// Tags decoder errors so they can be told apart from errors reading the
+/// compressed file itself.
+#[cfg(feature = "compress")]
+struct GzipStream<R>(flate2::bufread::MultiGzDecoder<R>);
+
+#[cfg(feature = "compress")]
+impl<R: BufRead> io::Read for GzipStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0
+            .read(buf)
+            .map_err(|err| io::Error::new(err.kind(), DamagedGzip(err)))
+    }
+}
+
+#[derive(Debug)]
+struct DamagedGzip(io::Error);
+
+impl std::fmt::Display for DamagedGzip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for DamagedGzip {}
+
+/// Converts an error from reading a possibly decompressed bitfile,
+/// reporting a damaged gzip stream as [`Error::CorruptArchive`].
+pub(crate) fn read_error(err: io::Error) -> Error {
+    if !err.get_ref().is_some_and(|inner| inner.is::<DamagedGzip>()) {
+        return Error::Io(err);
+    }
+    let inner = err.into_inner().expect("checked inner error");
+    match inner.downcast::<DamagedGzip>() {
+        Ok(damaged) => Error::CorruptArchive(damaged.0),
+        Err(other) => Error::Io(io::Error::other(other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, load_bitfile_from_reader, parse_bitfile};
+
+    #[cfg(feature = "compress")]
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn gzipped_bitfiles_parse_like_plain_ones() {
+        let text = "1234_abcd\n".repeat(5000);
+        let compressed = gzip(text.as_bytes());
+        assert!(compressed.len() < text.len() / 4);
+        assert_eq!(
+            parse_bitfile(&compressed).unwrap(),
+            parse_bitfile(text.as_bytes()).unwrap()
+        );
+        assert_eq!(
+            load_bitfile_from_reader(compressed.as_slice())
+                .unwrap()
+                .len(),
+            10_000
+        );
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn damaged_archives_are_told_apart_from_bad_content() {
+        let mut damaged = gzip("1234_abcd\n".repeat(5000).as_bytes());
+        let middle = damaged.len() / 2;
+        damaged[middle..middle + 8].fill(0xff);
+        assert!(matches!(
+            parse_bitfile(&damaged),
+            Err(Error::CorruptArchive(_))
+        ));
+
+        let bad_content = gzip(b"1234_zzzz\n");
+        assert!(matches!(
+            parse_bitfile(&bad_content),
+            Err(Error::InvalidBitfileLine { line: 1, .. })
+        ));
+    }
+
+    #[cfg(not(feature = "compress"))]
+    #[test]
+    fn gzipped_bitfiles_need_the_compress_feature() {
+        assert!(matches!(
+            load_bitfile_from_reader(&[0x1f, 0x8b, 0x08, 0x00][..]),
+            Err(Error::FeatureUnavailable("compress"))
+        ));
+        assert!(matches!(
+            parse_bitfile(&[0x1f, 0x8b]),
+            Err(Error::FeatureUnavailable("compress"))
+        ));
+    }
+}
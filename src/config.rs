@@ -1,4 +1,8 @@
-mod word {
+use crate::error::Error;
+use crate::session::BoardMode;
+use std::{fmt, sync::Arc};
+
+pub(crate) mod word {
     pub const VERICOMM_CLOCK_HIGH_DELAY: usize = 0;
     pub const VERICOMM_CLOCK_LOW_DELAY: usize = 1;
     pub const VERICOMM_MISC: usize = 2;
@@ -7,6 +11,12 @@ mod word {
     pub const FLASH_BEGIN_CLUSTER: usize = 5;
     pub const FLASH_READ_END_BLOCK: usize = 6;
     pub const FLASH_READ_END_CLUSTER: usize = 7;
+    /// Low half of the bitstream CRC. No known firmware use; this crate
+    /// stores the CRC here. Retention across power cycles is unconfirmed
+    /// on hardware.
+    pub const BITSTREAM_CRC_LOW: usize = 29;
+    /// High half of the bitstream CRC; see [`BITSTREAM_CRC_LOW`].
+    pub const BITSTREAM_CRC_HIGH: usize = 30;
     pub const LICENCE_AND_SECURITY_KEY: usize = 31;
     pub const SMIMS_VERSION: usize = 32;
     pub const FIFO_SIZE_WORDS: usize = 33;
@@ -16,8 +26,445 @@ mod word {
     pub const ABILITY_FLAGS: usize = 37;
     pub const PROGRAM_STATE: usize = 48;
     pub const CLOCK_STATE: usize = 49;
+
+    /// Fields stored in word `index`, for diagnostics.
+    pub fn name(index: usize) -> Option<&'static str> {
+        Some(match index {
+            VERICOMM_CLOCK_HIGH_DELAY => "vericomm clock high delay",
+            VERICOMM_CLOCK_LOW_DELAY => "vericomm clock low delay",
+            VERICOMM_MISC => "vericomm isv/clock_check",
+            MODE_AND_CHANNEL => "mode selector/sdk channel",
+            FLASH_BEGIN_BLOCK => "flash begin block",
+            FLASH_BEGIN_CLUSTER => "flash begin cluster",
+            FLASH_READ_END_BLOCK => "flash read end block",
+            FLASH_READ_END_CLUSTER => "flash read end cluster",
+            BITSTREAM_CRC_LOW => "bitstream crc (low)",
+            BITSTREAM_CRC_HIGH => "bitstream crc (high)",
+            LICENCE_AND_SECURITY_KEY => "licence/security key",
+            SMIMS_VERSION => "smims version",
+            FIFO_SIZE_WORDS => "fifo size",
+            FLASH_TOTAL_BLOCK => "flash total blocks",
+            FLASH_BLOCK_SIZE => "flash block size",
+            FLASH_CLUSTER_SIZE => "flash cluster size",
+            ABILITY_FLAGS => "ability flags",
+            PROGRAM_STATE => "programmed/pcb status",
+            CLOCK_STATE => "clock status",
+            _ => return None,
+        })
+    }
+}
+
+/// Security key reported by the board in configuration word 31.
+///
+/// Word 31 is an overlay: reads return the board's security key, writes set
+/// the licence key that unlocks VeriComm. The value read back is therefore
+/// never a valid licence to write, which is why this type is read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SecurityKey(u16);
+
+impl SecurityKey {
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+/// Licence key written to configuration word 31 to unlock VeriComm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LicenceKey(u16);
+
+impl LicenceKey {
+    /// Licence accepted by the stock VeriComm firmware.
+    pub const DEFAULT: Self = Self(0xff40);
+
+    pub const fn new(value: u16) -> Self {
+        Self(value)
+    }
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl From<u16> for LicenceKey {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+/// Computes a licence key from a board's security key, for
+/// [`crate::IoConfig::licence_derivation`].
+///
+/// Wraps any closure, so a customer ID chosen at run time can be captured.
+/// This crate does not ship the vendor's derivation.
+#[derive(Clone)]
+pub struct LicenceDerivation(Arc<dyn Fn(SecurityKey) -> LicenceKey + Send + Sync>);
+
+impl LicenceDerivation {
+    pub fn new(derive: impl Fn(SecurityKey) -> LicenceKey + Send + Sync + 'static) -> Self {
+        Self(Arc::new(derive))
+    }
+
+    pub fn derive(&self, key: SecurityKey) -> LicenceKey {
+        (self.0)(key)
+    }
+}
+
+impl fmt::Debug for LicenceDerivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LicenceDerivation(..)")
+    }
+}
+
+/// Serialized as a hex string such as `"0xff40"`; deserializes from that
+/// or from a plain integer.
+#[cfg(feature = "serde")]
+impl serde::Serialize for LicenceKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{:#06x}", self.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LicenceKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = LicenceKey;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a 16-bit licence key as an integer or a \"0x\" hex string")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<LicenceKey, E> {
+                u16::try_from(value)
+                    .map(LicenceKey)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(value), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<LicenceKey, E> {
+                u16::try_from(value)
+                    .map(LicenceKey)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<LicenceKey, E> {
+                value
+                    .strip_prefix("0x")
+                    .or_else(|| value.strip_prefix("0X"))
+                    .and_then(|digits| u16::from_str_radix(digits, 16).ok())
+                    .map(LicenceKey)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Firmware version from configuration word 32: one byte of major
+/// version, then a nibble each of sub version and patch. Versions order
+/// and display as `major.sub.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SmimsVersion {
+    pub major: u8,
+    pub sub: u8,
+    pub patch: u8,
+}
+
+impl From<u16> for SmimsVersion {
+    fn from(raw: u16) -> Self {
+        Self {
+            major: (raw >> 8) as u8,
+            sub: ((raw >> 4) & 0x000f) as u8,
+            patch: (raw & 0x000f) as u8,
+        }
+    }
+}
+
+impl From<SmimsVersion> for u16 {
+    fn from(version: SmimsVersion) -> Self {
+        (u16::from(version.major) << 8)
+            | (u16::from(version.sub & 0x0f) << 4)
+            | u16::from(version.patch & 0x0f)
+    }
+}
+
+impl fmt::Display for SmimsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.sub, self.patch)
+    }
+}
+
+/// On-board flash layout from configuration words 34 to 36: blocks of
+/// clusters of 16-bit words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlashGeometry {
+    pub total_blocks: u16,
+    /// Clusters per block.
+    pub block_size: u16,
+    /// Words per cluster.
+    pub cluster_size: u16,
+}
+
+impl FlashGeometry {
+    pub fn capacity_bytes(&self) -> u64 {
+        u64::from(self.total_blocks) * u64::from(self.block_size) * u64::from(self.cluster_size) * 2
+    }
+
+    pub fn capacity_words(&self) -> usize {
+        usize::from(self.total_blocks) * self.block_words()
+    }
+
+    pub fn block_words(&self) -> usize {
+        usize::from(self.block_size) * usize::from(self.cluster_size)
+    }
+
+    pub fn contains(&self, address: FlashAddress) -> bool {
+        address.block < self.total_blocks && address.cluster < self.block_size
+    }
+
+    /// Word offset of the first word of `address` from the start of flash.
+    pub fn offset_of(&self, address: FlashAddress) -> crate::Result<usize> {
+        if !self.contains(address) {
+            return Err(Error::FlashAddressOutOfRange {
+                block: address.block,
+                cluster: address.cluster,
+            });
+        }
+        Ok(self.cluster_index(address) * usize::from(self.cluster_size))
+    }
+
+    /// The cluster holding word `words` of flash, counted from zero.
+    pub fn address_for_offset(&self, words: usize) -> crate::Result<FlashAddress> {
+        let capacity = self.capacity_words();
+        if words >= capacity {
+            return Err(Error::FlashCapacityExceeded {
+                words: words + 1,
+                available: capacity,
+            });
+        }
+        Ok(self.address_at(words / usize::from(self.cluster_size)))
+    }
+
+    /// The clusters from `start` on that hold `words` words; the last one
+    /// may be partly used.
+    pub fn range_for_len(&self, start: FlashAddress, words: usize) -> crate::Result<FlashRange> {
+        let offset = self.offset_of(start)?;
+        let available = self.capacity_words() - offset;
+        if words > available {
+            return Err(Error::FlashCapacityExceeded { words, available });
+        }
+        if words == 0 {
+            return Err(Error::InvalidBufferLength {
+                context: "flash range",
+                expected: 1,
+                actual: 0,
+            });
+        }
+        let clusters = words.div_ceil(usize::from(self.cluster_size));
+        Ok(FlashRange {
+            start,
+            end: self.address_at(self.cluster_index(start) + clusters - 1),
+        })
+    }
+
+    /// Words in `range`, both ends included, after checking it against
+    /// this geometry.
+    pub fn range_words(&self, range: &FlashRange) -> crate::Result<usize> {
+        let start = self.offset_of(range.start)?;
+        let end = self.offset_of(range.end)?;
+        if end < start {
+            return Err(Error::InvalidConfig {
+                word: word::FLASH_READ_END_BLOCK,
+                reason: "flash read end precedes flash begin",
+            });
+        }
+        Ok(end - start + usize::from(self.cluster_size))
+    }
+
+    /// Position of `address` counted in clusters from the start of flash.
+    fn cluster_index(&self, address: FlashAddress) -> usize {
+        usize::from(address.block) * usize::from(self.block_size) + usize::from(address.cluster)
+    }
+
+    fn address_at(&self, cluster_index: usize) -> FlashAddress {
+        let block_size = usize::from(self.block_size);
+        FlashAddress {
+            block: (cluster_index / block_size) as u16,
+            cluster: (cluster_index % block_size) as u16,
+        }
+    }
+}
+
+/// Position in on-board flash: a cluster within a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlashAddress {
+    pub block: u16,
+    pub cluster: u16,
+}
+
+/// Flash clusters from `start` to `end`, both included, as held by
+/// configuration words 4 to 7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlashRange {
+    pub start: FlashAddress,
+    pub end: FlashAddress,
+}
+
+/// Ability flags from configuration word 37. Bits without a name are kept
+/// as read.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Abilities(u16);
+
+impl Abilities {
+    pub const VERICOMM: Self = Self(0x0001);
+    pub const VERI_INSTRUMENT: Self = Self(0x0002);
+    pub const VERI_LINK: Self = Self(0x0004);
+    pub const VERI_SOC: Self = Self(0x0008);
+    pub const VERICOMM_PRO: Self = Self(0x0010);
+    pub const VERI_SDK: Self = Self(0x0100);
+
+    const NAMED: [(Self, &'static str); 6] = [
+        (Self::VERICOMM, "VERICOMM"),
+        (Self::VERI_INSTRUMENT, "VERI_INSTRUMENT"),
+        (Self::VERI_LINK, "VERI_LINK"),
+        (Self::VERI_SOC, "VERI_SOC"),
+        (Self::VERICOMM_PRO, "VERICOMM_PRO"),
+        (Self::VERI_SDK, "VERI_SDK"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits_retain(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The flag that must be set before the engine may enter `mode`, or
+    /// `None` for modes every board supports.
+    pub fn required_for(mode: BoardMode) -> Option<Self> {
+        match mode {
+            BoardMode::VeriComm => Some(Self::VERICOMM),
+            BoardMode::VeriInstrument => Some(Self::VERI_INSTRUMENT),
+            BoardMode::VeriLink => Some(Self::VERI_LINK),
+            BoardMode::VeriSoc => Some(Self::VERI_SOC),
+            BoardMode::VeriCommPro => Some(Self::VERICOMM_PRO),
+            BoardMode::VeriSdk => Some(Self::VERI_SDK),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::BitOr for Abilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for Abilities {
+    /// Set flags by name joined with ` | `, then any unnamed bits in hex.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.0;
+        let mut first = true;
+        let mut separator = |f: &mut fmt::Formatter<'_>| {
+            let result = if first { Ok(()) } else { f.write_str(" | ") };
+            first = false;
+            result
+        };
+        for (flag, name) in Self::NAMED {
+            if self.contains(flag) {
+                separator(f)?;
+                f.write_str(name)?;
+                rest &= !flag.0;
+            }
+        }
+        if rest != 0 {
+            separator(f)?;
+            write!(f, "{rest:#06x}")?;
+        }
+        if first {
+            f.write_str("(empty)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Abilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Abilities({self})")
+    }
+}
+
+/// One word that differs between two configs; see [`Config::diff`].
+///
+/// `Debug` and `Display` both read like
+/// `word 2 (vericomm isv/clock_check) 0x0041 -> 0x0040`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ConfigDiff {
+    pub word: usize,
+    pub before: u16,
+    pub after: u16,
+    /// Named field(s) stored in the word, `None` for reserved words.
+    pub field: Option<&'static str>,
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "word {}", self.word)?;
+        if let Some(field) = self.field {
+            write!(f, " ({field})")?;
+        }
+        write!(f, " {:#06x} -> {:#06x}", self.before, self.after)
+    }
+}
+
+impl fmt::Debug for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
+/// The 64-word configuration block.
+///
+/// The legacy ProgramVLFD headers document words 0-7 (VeriComm timing,
+/// mode and channel selectors, flash read addressing), 31 (licence and
+/// security key), 32-37 (version, FIFO size, flash geometry, abilities)
+/// and 48-49 (program and clock status), and every one of them has an
+/// accessor here. Words 29-30 are undocumented; this crate keeps the CRC of
+/// the last verified bitstream there. The other words have no documented
+/// meaning; reach them through [`words`](Self::words) and
+/// [`words_mut`](Self::words_mut).
+#[cfg_attr(
+    feature = "serde",
+    doc = "",
+    doc = "A config serializes as its raw `words` together with the decoded",
+    doc = "`named` fields, and deserializes through [`Config::from_named`]."
+)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Config {
     words: [u16; Self::WORD_COUNT],
@@ -46,6 +493,10 @@ impl Config {
         &self.words
     }
 
+    pub fn words_mut(&mut self) -> &mut [u16; Self::WORD_COUNT] {
+        &mut self.words
+    }
+
     pub fn vericomm_clock_high_delay(&self) -> u16 {
         self.words[word::VERICOMM_CLOCK_HIGH_DELAY]
     }
@@ -126,6 +577,36 @@ impl Config {
         self.words[word::FLASH_READ_END_BLOCK] = addr;
     }
 
+    pub fn flash_begin(&self) -> FlashAddress {
+        FlashAddress {
+            block: self.flash_begin_block_addr(),
+            cluster: self.flash_begin_cluster_addr(),
+        }
+    }
+
+    pub fn set_flash_begin(&mut self, address: FlashAddress) {
+        self.set_flash_begin_block_addr(address.block);
+        self.set_flash_begin_cluster_addr(address.cluster);
+    }
+
+    pub fn flash_read_end(&self) -> FlashAddress {
+        FlashAddress {
+            block: self.flash_read_end_block_addr(),
+            cluster: self.flash_read_end_cluster_addr(),
+        }
+    }
+
+    pub fn set_flash_read_end(&mut self, address: FlashAddress) {
+        self.set_flash_read_end_block_addr(address.block);
+        self.set_flash_read_end_cluster_addr(address.cluster);
+    }
+
+    /// Sets the begin and read end words from `range`.
+    pub fn set_flash_read_range(&mut self, range: FlashRange) {
+        self.set_flash_begin(range.start);
+        self.set_flash_read_end(range.end);
+    }
+
     pub fn flash_read_end_cluster_addr(&self) -> u16 {
         self.words[word::FLASH_READ_END_CLUSTER]
     }
@@ -134,24 +615,48 @@ impl Config {
         self.words[word::FLASH_READ_END_CLUSTER] = addr;
     }
 
+    /// CRC recorded by a verified programming run, or zero when none is;
+    /// see [`crate::bitstream_crc32`].
+    pub fn bitstream_crc(&self) -> u32 {
+        u32::from(self.words[word::BITSTREAM_CRC_HIGH]) << 16
+            | u32::from(self.words[word::BITSTREAM_CRC_LOW])
+    }
+
+    pub fn set_bitstream_crc(&mut self, crc: u32) {
+        self.words[word::BITSTREAM_CRC_LOW] = crc as u16;
+        self.words[word::BITSTREAM_CRC_HIGH] = (crc >> 16) as u16;
+    }
+
+    #[deprecated(
+        note = "word 31 reads back the security key, not the licence; use `security_key()`"
+    )]
     pub fn licence_key(&self) -> u16 {
         self.words[word::LICENCE_AND_SECURITY_KEY]
     }
 
-    /// The hardware exposes the security key through the same configuration
-    /// word used for the active license key.
-    pub fn security_key(&self) -> u16 {
-        self.words[word::LICENCE_AND_SECURITY_KEY]
+    /// Security key overlaid on the licence key word; see [`SecurityKey`].
+    pub fn security_key(&self) -> SecurityKey {
+        SecurityKey(self.words[word::LICENCE_AND_SECURITY_KEY])
     }
 
-    pub fn set_licence_key(&mut self, key: u16) {
-        self.words[word::LICENCE_AND_SECURITY_KEY] = key;
+    pub fn set_licence_key(&mut self, key: LicenceKey) {
+        self.words[word::LICENCE_AND_SECURITY_KEY] = key.value();
+    }
+
+    /// Whether `index` is the security/licence key overlay, which must never
+    /// be snapshotted and written back.
+    pub(crate) fn is_key_overlay_word(index: usize) -> bool {
+        index == word::LICENCE_AND_SECURITY_KEY
     }
 
     pub fn smims_version_raw(&self) -> u16 {
         self.words[word::SMIMS_VERSION]
     }
 
+    pub fn smims_version(&self) -> SmimsVersion {
+        SmimsVersion::from(self.words[word::SMIMS_VERSION])
+    }
+
     pub fn smims_major_version(&self) -> u8 {
         (self.words[word::SMIMS_VERSION] >> 8) as u8
     }
@@ -189,28 +694,51 @@ impl Config {
         self.words[word::FLASH_CLUSTER_SIZE]
     }
 
+    pub fn flash_geometry(&self) -> FlashGeometry {
+        FlashGeometry {
+            total_blocks: self.flash_total_block(),
+            block_size: self.flash_block_size(),
+            cluster_size: self.flash_cluster_size(),
+        }
+    }
+
+    pub fn abilities(&self) -> Abilities {
+        Abilities::from_bits_retain(self.words[word::ABILITY_FLAGS])
+    }
+
     pub fn vericomm_ability(&self) -> bool {
-        self.has_state_flag(0x0001)
+        self.abilities().contains(Abilities::VERICOMM)
     }
 
     pub fn veri_instrument_ability(&self) -> bool {
-        self.has_state_flag(0x0002)
+        self.abilities().contains(Abilities::VERI_INSTRUMENT)
     }
 
     pub fn veri_link_ability(&self) -> bool {
-        self.has_state_flag(0x0004)
+        self.abilities().contains(Abilities::VERI_LINK)
     }
 
     pub fn veri_soc_ability(&self) -> bool {
-        self.has_state_flag(0x0008)
+        self.abilities().contains(Abilities::VERI_SOC)
     }
 
     pub fn vericomm_pro_ability(&self) -> bool {
-        self.has_state_flag(0x0010)
+        self.abilities().contains(Abilities::VERICOMM_PRO)
     }
 
     pub fn veri_sdk_ability(&self) -> bool {
-        self.has_state_flag(0x0100)
+        self.abilities().contains(Abilities::VERI_SDK)
+    }
+
+    /// Whether the ability flags allow switching into `mode`. Modes
+    /// without an ability bit are always allowed; `Closed` and `Unknown`
+    /// never are.
+    pub fn supports_mode(&self, mode: BoardMode) -> bool {
+        match mode {
+            BoardMode::Closed | BoardMode::Unknown => false,
+            mode => Abilities::required_for(mode)
+                .is_none_or(|required| self.abilities().contains(required)),
+        }
     }
 
     pub fn is_programmed(&self) -> bool {
@@ -225,14 +753,523 @@ impl Config {
         self.words[word::CLOCK_STATE] & 0x0001 == 0
     }
 
-    fn has_state_flag(&self, mask: u16) -> bool {
-        self.words[word::ABILITY_FLAGS] & mask != 0
+    /// Checks the documented words for values the firmware cannot act on:
+    /// a flash read range that ends before it begins, bits set outside the
+    /// ISV and clock check fields of word 2, and a zero clock delay while
+    /// clock checking is on. [`crate::Board::write_config`] runs this
+    /// before sending anything; the board's own writes do not, since the
+    /// other words are whatever the device reported.
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.flash_read_end() < self.flash_begin() {
+            return Err(Error::InvalidConfig {
+                word: word::FLASH_READ_END_BLOCK,
+                reason: "flash read end precedes flash begin",
+            });
+        }
+        if self.words[word::VERICOMM_MISC] & !0x00f1 != 0 {
+            return Err(Error::InvalidConfig {
+                word: word::VERICOMM_MISC,
+                reason: "reserved bits set beside the vericomm isv and clock check",
+            });
+        }
+        if self.vericomm_clock_check_enabled() {
+            for index in [
+                word::VERICOMM_CLOCK_HIGH_DELAY,
+                word::VERICOMM_CLOCK_LOW_DELAY,
+            ] {
+                if self.words[index] == 0 {
+                    return Err(Error::InvalidConfig {
+                        word: index,
+                        reason: "clock delay is zero with clock check enabled",
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Words that differ from `self` to `other`, in index order.
+    pub fn diff(&self, other: &Config) -> Vec<ConfigDiff> {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (&before, &after))| ConfigDiff {
+                word: index,
+                before,
+                after,
+                field: word::name(index),
+            })
+            .collect()
+    }
+
+    /// Multi-line, one-field-per-line rendering of the decoded words for bug
+    /// reports. The security key in word 31 is only shown with
+    /// `show_security_key`; reserved words are left to [`hexdump`](Self::hexdump).
+    pub fn summary(&self, show_security_key: bool) -> String {
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+        let flash = self.flash_geometry();
+        let security_key = if show_security_key {
+            format!("{:#06x}", self.security_key().value())
+        } else {
+            "<redacted>".to_owned()
+        };
+        format!(
+            "SMIMS version:   {}\n\
+             FIFO depth:      {} words\n\
+             Flash:           {} blocks x {} clusters x {} words ({} bytes)\n\
+             Abilities:       {}\n\
+             Programmed:      {}\n\
+             PCB connected:   {}\n\
+             Clock running:   {}\n\
+             Clock delays:    high {}, low {}\n\
+             VeriComm ISV:    {}\n\
+             Clock check:     {}\n\
+             Mode selector:   {:#04x}\n\
+             SDK channel:     {:#04x}\n\
+             Flash begin:     block {}, cluster {}\n\
+             Flash read end:  block {}, cluster {}\n\
+             Security key:    {}",
+            self.smims_version(),
+            self.fifo_size_words(),
+            flash.total_blocks,
+            flash.block_size,
+            flash.cluster_size,
+            flash.capacity_bytes(),
+            self.abilities(),
+            yes_no(self.is_programmed()),
+            yes_no(self.is_pcb_connected()),
+            yes_no(self.vericomm_clock_continues()),
+            self.vericomm_clock_high_delay(),
+            self.vericomm_clock_low_delay(),
+            self.vericomm_isv(),
+            if self.vericomm_clock_check_enabled() {
+                "on"
+            } else {
+                "off"
+            },
+            self.mode_selector(),
+            self.veri_sdk_channel_selector(),
+            self.flash_begin_block_addr(),
+            self.flash_begin_cluster_addr(),
+            self.flash_read_end_block_addr(),
+            self.flash_read_end_cluster_addr(),
+            security_key,
+        )
+    }
+
+    /// All 64 words in hex, eight per line, each line prefixed with the
+    /// index of its first word.
+    pub fn hexdump(&self) -> String {
+        self.words
+            .chunks(8)
+            .enumerate()
+            .map(|(row, words)| {
+                let words = words
+                    .iter()
+                    .map(|word| format!("{word:04x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{:02}: {words}", row * 8)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Deserializes a config from its serialized form: the raw `words`
+    /// first, then every field present under `named` written over them.
+    /// Words and bits no named field covers keep their raw values, so
+    /// reserved words survive a round trip; without `words` they start
+    /// zeroed.
+    #[cfg(feature = "serde")]
+    pub fn from_named<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::Deserialize;
+
+        let repr = named::ConfigRepr::deserialize(deserializer)?;
+        let mut config = Self::new();
+        if let Some(words) = repr.words {
+            let len = words.len();
+            config.words = words
+                .try_into()
+                .map_err(|_| serde::de::Error::invalid_length(len, &"64 configuration words"))?;
+        }
+        if let Some(named) = repr.named {
+            named.apply(&mut config.words);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod named {
+    use super::{Abilities, Config, FlashGeometry, SmimsVersion, word};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub(super) struct ConfigRepr {
+        pub(super) words: Option<Vec<u16>>,
+        pub(super) named: Option<NamedFields>,
+    }
+
+    /// Decoded view of the words the crate knows about. Every field is
+    /// optional on input so a hand-edited file can change just a few.
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub(super) struct NamedFields {
+        vericomm_clock_high_delay: Option<u16>,
+        vericomm_clock_low_delay: Option<u16>,
+        vericomm_isv: Option<u8>,
+        vericomm_clock_check_enabled: Option<bool>,
+        mode_selector: Option<u8>,
+        veri_sdk_channel_selector: Option<u8>,
+        flash_begin_block_addr: Option<u16>,
+        flash_begin_cluster_addr: Option<u16>,
+        flash_read_end_block_addr: Option<u16>,
+        flash_read_end_cluster_addr: Option<u16>,
+        smims_version: Option<SmimsVersion>,
+        fifo_size_words: Option<u16>,
+        flash: Option<FlashGeometry>,
+        abilities: Option<Abilities>,
+        programmed: Option<bool>,
+        pcb_connected: Option<bool>,
+        clock_continues: Option<bool>,
+    }
+
+    impl NamedFields {
+        fn from_config(config: &Config) -> Self {
+            Self {
+                vericomm_clock_high_delay: Some(config.vericomm_clock_high_delay()),
+                vericomm_clock_low_delay: Some(config.vericomm_clock_low_delay()),
+                vericomm_isv: Some(config.vericomm_isv()),
+                vericomm_clock_check_enabled: Some(config.vericomm_clock_check_enabled()),
+                mode_selector: Some(config.mode_selector()),
+                veri_sdk_channel_selector: Some(config.veri_sdk_channel_selector()),
+                flash_begin_block_addr: Some(config.flash_begin_block_addr()),
+                flash_begin_cluster_addr: Some(config.flash_begin_cluster_addr()),
+                flash_read_end_block_addr: Some(config.flash_read_end_block_addr()),
+                flash_read_end_cluster_addr: Some(config.flash_read_end_cluster_addr()),
+                smims_version: Some(config.smims_version()),
+                fifo_size_words: Some(config.fifo_size_words()),
+                flash: Some(config.flash_geometry()),
+                abilities: Some(config.abilities()),
+                programmed: Some(config.is_programmed()),
+                pcb_connected: Some(config.is_pcb_connected()),
+                clock_continues: Some(config.vericomm_clock_continues()),
+            }
+        }
+
+        /// Writes each present field into its bits of `words`, leaving
+        /// every other bit alone.
+        pub(super) fn apply(&self, words: &mut [u16; Config::WORD_COUNT]) {
+            let mut set = |index: usize, mask: u16, value: u16| {
+                words[index] = (words[index] & !mask) | (value & mask);
+            };
+            let word_fields = [
+                (
+                    word::VERICOMM_CLOCK_HIGH_DELAY,
+                    self.vericomm_clock_high_delay,
+                ),
+                (
+                    word::VERICOMM_CLOCK_LOW_DELAY,
+                    self.vericomm_clock_low_delay,
+                ),
+                (word::FLASH_BEGIN_BLOCK, self.flash_begin_block_addr),
+                (word::FLASH_BEGIN_CLUSTER, self.flash_begin_cluster_addr),
+                (word::FLASH_READ_END_BLOCK, self.flash_read_end_block_addr),
+                (
+                    word::FLASH_READ_END_CLUSTER,
+                    self.flash_read_end_cluster_addr,
+                ),
+                (word::SMIMS_VERSION, self.smims_version.map(u16::from)),
+                (word::FIFO_SIZE_WORDS, self.fifo_size_words),
+                (word::FLASH_TOTAL_BLOCK, self.flash.map(|f| f.total_blocks)),
+                (word::FLASH_BLOCK_SIZE, self.flash.map(|f| f.block_size)),
+                (word::FLASH_CLUSTER_SIZE, self.flash.map(|f| f.cluster_size)),
+                (word::ABILITY_FLAGS, self.abilities.map(Abilities::bits)),
+            ];
+            for (index, value) in word_fields {
+                if let Some(value) = value {
+                    set(index, 0xffff, value);
+                }
+            }
+            if let Some(isv) = self.vericomm_isv {
+                set(word::VERICOMM_MISC, 0x00f0, u16::from(isv) << 4);
+            }
+            if let Some(enabled) = self.vericomm_clock_check_enabled {
+                set(word::VERICOMM_MISC, 0x0001, u16::from(enabled));
+            }
+            if let Some(mode) = self.mode_selector {
+                set(word::MODE_AND_CHANNEL, 0xff00, u16::from(mode) << 8);
+            }
+            if let Some(channel) = self.veri_sdk_channel_selector {
+                set(word::MODE_AND_CHANNEL, 0x00ff, u16::from(channel));
+            }
+            if let Some(programmed) = self.programmed {
+                set(word::PROGRAM_STATE, 0x0001, u16::from(programmed));
+            }
+            if let Some(connected) = self.pcb_connected {
+                set(word::PROGRAM_STATE, 0x0100, u16::from(!connected) << 8);
+            }
+            if let Some(continues) = self.clock_continues {
+                set(word::CLOCK_STATE, 0x0001, u16::from(!continues));
+            }
+        }
+    }
+
+    impl Serialize for Config {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ConfigRepr {
+                words: Some(self.words.to_vec()),
+                named: Some(NamedFields::from_config(self)),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Config {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Config::from_named(deserializer)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Config;
+    use super::{
+        Abilities, Config, FlashAddress, FlashGeometry, FlashRange, LicenceKey, SmimsVersion,
+    };
+    use crate::BoardMode;
+
+    #[test]
+    fn smims_versions_order_display_and_round_trip() {
+        let old = SmimsVersion::from(0x0193);
+        let new = SmimsVersion::from(0x0210);
+        assert_eq!(old.to_string(), "1.9.3");
+        assert_eq!(new.to_string(), "2.1.0");
+        assert!(old < new);
+        assert!(SmimsVersion::from(0x020f) < new);
+        assert_eq!(u16::from(old), 0x0193);
+    }
+
+    #[test]
+    fn abilities_name_known_flags_and_keep_reserved_bits() {
+        let mut words = [0u16; Config::WORD_COUNT];
+        words[37] = 0x0211;
+        let config = Config::from_words(words);
+        let abilities = config.abilities();
+        assert_eq!(abilities.bits(), 0x0211);
+        assert!(abilities.contains(Abilities::VERICOMM | Abilities::VERICOMM_PRO));
+        assert!(!abilities.intersects(Abilities::VERI_LINK | Abilities::VERI_SOC));
+        assert_eq!(abilities.to_string(), "VERICOMM | VERICOMM_PRO | 0x0200");
+        assert_eq!(Abilities::empty().to_string(), "(empty)");
+
+        assert!(config.supports_mode(BoardMode::VeriCommPro));
+        assert!(config.supports_mode(BoardMode::FpgaProgrammer));
+        assert!(!config.supports_mode(BoardMode::VeriSdk));
+        assert!(!config.supports_mode(BoardMode::Unknown));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn configs_round_trip_through_named_json_keeping_reserved_words() {
+        let mut words = [0u16; Config::WORD_COUNT];
+        for (index, word) in words.iter_mut().enumerate() {
+            *word = (index as u16).wrapping_mul(0x9e37) ^ 0x5a5a;
+        }
+        let config = Config::from_words(words);
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["named"]["vericomm_isv"], config.vericomm_isv());
+        assert_eq!(json["named"]["programmed"], config.is_programmed());
+        assert_eq!(
+            serde_json::from_value::<Config>(json.clone()).unwrap(),
+            config
+        );
+
+        // Named fields win over the raw words they decode.
+        let mut edited = json;
+        edited["named"]["vericomm_isv"] = 3.into();
+        edited["named"]["pcb_connected"] = (!config.is_pcb_connected()).into();
+        let rebuilt: Config = serde_json::from_value(edited).unwrap();
+        assert_eq!(rebuilt.vericomm_isv(), 3);
+        assert_eq!(rebuilt.is_pcb_connected(), !config.is_pcb_connected());
+        for (index, (old, new)) in words.iter().zip(rebuilt.words()).enumerate() {
+            let changed = match index {
+                2 => 0x00f0,
+                48 => 0x0100,
+                _ => 0,
+            };
+            assert_eq!(old & !changed, new & !changed, "word {index}");
+        }
+
+        // Named fields alone build on a zeroed block.
+        let sparse: Config =
+            serde_json::from_str(r#"{"named": {"fifo_size_words": 1024}}"#).unwrap();
+        assert_eq!(sparse.fifo_size_words(), 1024);
+        assert_eq!(sparse.words().iter().filter(|word| **word != 0).count(), 1);
+    }
+
+    fn invalid_word(config: &Config) -> Option<usize> {
+        match config.validate() {
+            Ok(()) => None,
+            Err(crate::Error::InvalidConfig { word, .. }) => Some(word),
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_flash_ranges_ending_before_they_begin() {
+        let mut config = Config::new();
+        config.set_flash_begin_block_addr(4);
+        config.set_flash_begin_cluster_addr(8);
+        config.set_flash_read_end_block_addr(4);
+        config.set_flash_read_end_cluster_addr(8);
+        assert_eq!(invalid_word(&config), None);
+
+        config.set_flash_read_end_cluster_addr(7);
+        assert_eq!(invalid_word(&config), Some(6));
+        config.set_flash_read_end_block_addr(5);
+        assert_eq!(invalid_word(&config), None);
+        config.set_flash_read_end_block_addr(3);
+        assert_eq!(invalid_word(&config), Some(6));
+    }
+
+    #[test]
+    fn flash_geometry_maps_offsets_to_clusters() {
+        let geometry = FlashGeometry {
+            total_blocks: 4,
+            block_size: 8,
+            cluster_size: 16,
+        };
+        let at = |block, cluster| FlashAddress { block, cluster };
+        assert_eq!(geometry.capacity_words(), 512);
+        assert_eq!(geometry.offset_of(at(1, 2)).unwrap(), 160);
+        assert_eq!(geometry.address_for_offset(0).unwrap(), at(0, 0));
+        assert_eq!(geometry.address_for_offset(143).unwrap(), at(1, 0));
+        assert_eq!(geometry.address_for_offset(144).unwrap(), at(1, 1));
+        assert_eq!(geometry.address_for_offset(511).unwrap(), at(3, 7));
+        assert!(matches!(
+            geometry.address_for_offset(512),
+            Err(crate::Error::FlashCapacityExceeded {
+                words: 513,
+                available: 512
+            })
+        ));
+        assert!(matches!(
+            geometry.offset_of(at(0, 8)),
+            Err(crate::Error::FlashAddressOutOfRange {
+                block: 0,
+                cluster: 8
+            })
+        ));
+
+        let range = geometry.range_for_len(at(0, 7), 17).unwrap();
+        assert_eq!(range.end, at(1, 0));
+        assert_eq!(geometry.range_words(&range).unwrap(), 32);
+        assert_eq!(geometry.range_for_len(at(3, 7), 16).unwrap().end, at(3, 7));
+        assert!(matches!(
+            geometry.range_for_len(at(3, 7), 17),
+            Err(crate::Error::FlashCapacityExceeded {
+                words: 17,
+                available: 16
+            })
+        ));
+        assert!(geometry.range_for_len(at(0, 0), 0).is_err());
+
+        let mut config = Config::new();
+        config.set_flash_read_range(range);
+        assert_eq!(config.flash_begin_cluster_addr(), 7);
+        assert_eq!(config.flash_read_end_block_addr(), 1);
+        assert_eq!(
+            FlashRange {
+                start: config.flash_begin(),
+                end: config.flash_read_end()
+            },
+            range
+        );
+    }
+
+    #[test]
+    fn validate_rejects_isv_overflowing_its_nibble() {
+        let mut config = Config::new();
+        config.set_vericomm_isv(15);
+        assert_eq!(invalid_word(&config), None);
+        config.words_mut()[2] = 200 << 4;
+        assert_eq!(invalid_word(&config), Some(2));
+    }
+
+    #[test]
+    fn validate_rejects_zero_delays_only_with_clock_check_enabled() {
+        let mut config = Config::new();
+        assert_eq!(invalid_word(&config), None);
+        config.set_vericomm_clock_check_enabled(true);
+        assert_eq!(invalid_word(&config), Some(0));
+        config.set_vericomm_clock_high_delay(1);
+        assert_eq!(invalid_word(&config), Some(1));
+        config.set_vericomm_clock_low_delay(1);
+        assert_eq!(invalid_word(&config), None);
+    }
+
+    #[test]
+    fn diff_lists_changed_words_with_their_fields() {
+        let mut before = Config::new();
+        before.words_mut()[2] = 0x0041;
+        before.words_mut()[40] = 0x0001;
+        let mut after = before.clone();
+        after.set_vericomm_clock_check_enabled(false);
+        after.words_mut()[40] = 0x0002;
+
+        assert!(before.diff(&before).is_empty());
+        let diff = before.diff(&after);
+        assert_eq!(
+            format!("{diff:?}"),
+            "[word 2 (vericomm isv/clock_check) 0x0041 -> 0x0040, word 40 0x0001 -> 0x0002]"
+        );
+        assert_eq!(diff[1].field, None);
+    }
+
+    #[test]
+    fn summary_decodes_fields_and_hexdump_shows_every_word() {
+        let mut words = [0u16; Config::WORD_COUNT];
+        words[0] = 11;
+        words[1] = 12;
+        words[2] = 0x0031;
+        words[3] = 0x0102;
+        words[31] = 0xbeef;
+        words[32] = 0x0200;
+        words[33] = 1024;
+        words[37] = 0x0001;
+        words[48] = 0x0001;
+        words[63] = 0xabcd;
+        let config = Config::from_words(words);
+
+        let summary = config.summary(false);
+        assert_eq!(summary.lines().count(), 15);
+        for line in [
+            "SMIMS version:   2.0.0",
+            "FIFO depth:      1024 words",
+            "Abilities:       VERICOMM",
+            "Programmed:      yes",
+            "Clock delays:    high 11, low 12",
+            "VeriComm ISV:    3",
+            "Clock check:     on",
+            "Mode selector:   0x01",
+            "SDK channel:     0x02",
+            "Security key:    <redacted>",
+        ] {
+            assert!(summary.lines().any(|l| l == line), "missing {line:?}");
+        }
+        assert!(config.summary(true).ends_with("Security key:    0xbeef"));
+
+        let dump = config.hexdump();
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "00: 000b 000c 0031 0102 0000 0000 0000 0000");
+        assert_eq!(lines[3], "24: 0000 0000 0000 0000 0000 0000 0000 beef");
+        assert_eq!(lines[7], "56: 0000 0000 0000 0000 0000 0000 0000 abcd");
+    }
 
     #[test]
     fn mode_and_channel_share_the_same_word_without_clobbering_each_other() {
@@ -256,8 +1293,9 @@ mod tests {
     #[test]
     fn licence_and_security_key_share_the_same_backing_word() {
         let mut config = Config::new();
-        config.set_licence_key(0x55aa);
-        assert_eq!(config.licence_key(), 0x55aa);
-        assert_eq!(config.security_key(), 0x55aa);
+        config.set_licence_key(LicenceKey::new(0x55aa));
+        assert_eq!(config.security_key().value(), 0x55aa);
+        assert!(Config::is_key_overlay_word(31));
+        assert!(!Config::is_key_overlay_word(32));
     }
 }
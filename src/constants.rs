@@ -5,3 +5,24 @@ pub const DW_PID: u16 = 0x2008u16;
 
 // Similar to a "product activation key".
 pub const SERIAL_NO: &str = "F4UP-G2NH-Y0M0-AC05-F805-A478";
+
+// Largest FIFO transfer, in words, addressable by the firmware's transfer
+// length field. The firmware only has 16 bits for the word count and wedges
+// after returning the first 64Ki words of a larger request. No known release
+// widens the field.
+pub const MAX_TRANSFER_WORDS: usize = u16::MAX as usize;
+
+// Smallest programming FIFO, in words, accepted from the config block.
+// Only zero is known to be bogus: firmware reports it in a config block
+// read before it settled. No smallest real FIFO is documented, so every
+// other size is taken at its word.
+pub const MIN_PROGRAM_FIFO_WORDS: usize = 1;
+
+// Bulk packet size assumed when a transport cannot report its own: the
+// USB 2.0 high-speed maximum.
+pub const DEFAULT_MAX_PACKET_BYTES: usize = 512;
+
+// Clock the SMIMS engine derives the VeriComm IO clock from. Each IO clock
+// period is `clock_high_delay + clock_low_delay` cycles of this clock; see
+// `Board::calibrate_io_clock` for measuring a particular board.
+pub const VERICOMM_BASE_CLOCK_HZ: u32 = 48_000_000;
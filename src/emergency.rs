@@ -0,0 +1,337 @@
+use crate::protocol::Command;
+use crate::transport::EndpointOpener;
+use nusb::transfer::{Buffer, TransferError};
+use std::{fmt, thread, time::Duration};
+
+/// Upper bound on each emergency command write, regardless of the
+/// transport's configured USB timeout.
+pub const EMERGENCY_WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Time the firmware needs after an engine reset before it accepts the next
+/// command; matches the control-plane recovery path.
+const ENGINE_RESET_SETTLE: Duration = Duration::from_millis(2);
+
+/// Command endpoint writes issued by [`EmergencyHandle::execute`], in order.
+pub(crate) const EMERGENCY_COMMANDS: [(&str, &[u8]); 2] = [
+    ("engine_reset", Command::ResetEngine.encode()),
+    ("command_active", Command::CommandActive.encode()),
+];
+
+/// Step reported by [`EmergencyHandle::execute`] when the command endpoint
+/// cannot be claimed.
+pub const EMERGENCY_CLAIM_STEP: &str = "claim_command_endpoint";
+
+/// Pre-built shutdown sequence that returns the board to control mode.
+///
+/// Created by [`crate::Board::prepare_emergency_shutdown`]. The command
+/// buffers are built up front; the command endpoint is only claimed inside
+/// [`EmergencyHandle::execute`], so the board keeps working while the
+/// handle waits. Each write is waited for with at most
+/// [`EMERGENCY_WRITE_TIMEOUT`].
+///
+/// What it guarantees: no locking or logging inside this crate, and a
+/// worst-case run time of two write timeouts plus the engine reset settle
+/// time. What it cannot guarantee: opening the endpoint and submitting
+/// through the USB backend may take locks or allocate, so `execute` is not
+/// async-signal-safe. Call it from a thread woken by the signal handler
+/// (for example a self-pipe or `signal-hook` iterator), not from the
+/// handler itself. Endpoints are claimed exclusively, so `execute` fails
+/// at [`EMERGENCY_CLAIM_STEP`] if the board is writing a command at that
+/// very moment. It also skips the sync handshake, so a board that is
+/// mid-transfer may drop the commands; the engine reset makes that
+/// unlikely but not impossible.
+pub struct EmergencyHandle {
+    open: EndpointOpener,
+    commands: [Option<Buffer>; 2],
+    timeout: Duration,
+}
+
+/// Reason an emergency shutdown step did not complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmergencyFailure {
+    pub step: &'static str,
+    /// Transfer status reported by the backend, or `None` on timeout and
+    /// when the endpoint could not be claimed.
+    pub error: Option<TransferError>,
+}
+
+impl EmergencyHandle {
+    /// Prepares the sequence; `open` claims the command endpoint when it
+    /// runs.
+    pub(crate) fn new(open: EndpointOpener, timeout: Duration) -> Self {
+        let commands = EMERGENCY_COMMANDS.map(|(_, bytes)| {
+            let mut buffer = Buffer::new(bytes.len());
+            buffer.extend_from_slice(bytes);
+            Some(buffer)
+        });
+        Self {
+            open,
+            commands,
+            timeout: timeout.min(EMERGENCY_WRITE_TIMEOUT),
+        }
+    }
+
+    /// Whether the sequence has already been attempted.
+    pub fn is_spent(&self) -> bool {
+        self.commands.iter().all(Option::is_none)
+    }
+
+    /// Claims the command endpoint and sends the prepared engine reset and
+    /// control-mode commands, releasing the endpoint before returning.
+    ///
+    /// Each step runs at most once; calling `execute` again after a failure
+    /// retries only the steps that were never submitted.
+    pub fn execute(&mut self) -> std::result::Result<(), EmergencyFailure> {
+        if self.is_spent() {
+            return Ok(());
+        }
+        let Ok(mut endpoint) = (self.open)() else {
+            return Err(EmergencyFailure {
+                step: EMERGENCY_CLAIM_STEP,
+                error: None,
+            });
+        };
+        for (index, (step, _)) in EMERGENCY_COMMANDS.iter().enumerate() {
+            let Some(buffer) = self.commands[index].take() else {
+                continue;
+            };
+            endpoint.submit(buffer);
+            let Some(completion) = endpoint.wait_next_complete(self.timeout) else {
+                // The cancelled write still completes; retire it so it
+                // cannot be taken for the next step's result.
+                endpoint.cancel_all();
+                endpoint.wait_next_complete(self.timeout);
+                return Err(EmergencyFailure { step, error: None });
+            };
+            if let Err(error) = completion.status {
+                return Err(EmergencyFailure {
+                    step,
+                    error: Some(error),
+                });
+            }
+            if index == 0 {
+                thread::sleep(ENGINE_RESET_SETTLE);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for EmergencyHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmergencyHandle")
+            .field("timeout", &self.timeout)
+            .field("spent", &self.is_spent())
+            .finish()
+    }
+}
+
+impl fmt::Display for EmergencyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            Some(error) => write!(f, "emergency `{}` failed: {error}", self.step),
+            None => write!(f, "emergency `{}` timed out", self.step),
+        }
+    }
+}
+
+impl std::error::Error for EmergencyFailure {}
+
+#[cfg(test)]
+mod tests {
+    use super::{EMERGENCY_CLAIM_STEP, EMERGENCY_COMMANDS, EmergencyFailure, EmergencyHandle};
+    use crate::transport::{EndpointOpener, PipelineEndpoint, Transport};
+    use crate::usb::{Endpoint, UsbDevice};
+    use nusb::transfer::{Buffer, Completion, TransferError};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// What a [`ScriptedEndpoint`] does and saw, shared by every endpoint
+    /// its opener hands out.
+    #[derive(Default)]
+    struct Script {
+        /// Status of each write in turn; `None` leaves it running.
+        statuses: VecDeque<Option<Result<(), TransferError>>>,
+        /// Whether the next claim fails.
+        busy: bool,
+        sent: Vec<Vec<u8>>,
+        /// Transfers still queued when an endpoint was released.
+        abandoned: usize,
+    }
+
+    /// Command endpoint that completes each write with the next scripted
+    /// status; an exhausted script times out.
+    struct ScriptedEndpoint {
+        script: Arc<Mutex<Script>>,
+        pending: VecDeque<Buffer>,
+        cancelled: VecDeque<Buffer>,
+    }
+
+    impl PipelineEndpoint for ScriptedEndpoint {
+        fn max_packet_size(&self) -> usize {
+            512
+        }
+
+        fn pending(&self) -> usize {
+            self.pending.len() + self.cancelled.len()
+        }
+
+        fn allocate(&self, len: usize) -> Buffer {
+            Buffer::new(len)
+        }
+
+        fn submit(&mut self, buffer: Buffer) {
+            self.pending.push_back(buffer);
+        }
+
+        fn wait_next_complete(&mut self, _timeout: Duration) -> Option<Completion> {
+            if let Some(buffer) = self.cancelled.pop_front() {
+                return Some(Completion {
+                    buffer,
+                    actual_len: 0,
+                    status: Err(TransferError::Cancelled),
+                });
+            }
+            if self.pending.is_empty() {
+                return None;
+            }
+            let mut script = self.script.lock().unwrap();
+            let status = script.statuses.pop_front().flatten()?;
+            let buffer = self.pending.pop_front()?;
+            if status.is_ok() {
+                script.sent.push(buffer.to_vec());
+            }
+            Some(Completion {
+                actual_len: buffer.len(),
+                buffer,
+                status,
+            })
+        }
+
+        fn cancel_all(&mut self) {
+            self.cancelled.append(&mut self.pending);
+        }
+    }
+
+    impl Drop for ScriptedEndpoint {
+        fn drop(&mut self) {
+            self.script.lock().unwrap().abandoned += self.pending();
+        }
+    }
+
+    fn scripted(
+        statuses: impl IntoIterator<Item = Option<Result<(), TransferError>>>,
+    ) -> (EmergencyHandle, Arc<Mutex<Script>>) {
+        let script = Arc::new(Mutex::new(Script {
+            statuses: statuses.into_iter().collect(),
+            ..Script::default()
+        }));
+        let shared = Arc::clone(&script);
+        let open: EndpointOpener = Box::new(move || {
+            if shared.lock().unwrap().busy {
+                return Err(crate::Error::InterfaceClaimed { interface: 0 });
+            }
+            Ok(Box::new(ScriptedEndpoint {
+                script: Arc::clone(&shared),
+                pending: VecDeque::new(),
+                cancelled: VecDeque::new(),
+            }))
+        });
+        (EmergencyHandle::new(open, Duration::from_secs(1)), script)
+    }
+
+    #[test]
+    fn sequence_resets_the_engine_before_returning_to_control() {
+        let steps = EMERGENCY_COMMANDS.map(|(step, bytes)| (step, bytes.to_vec()));
+        assert_eq!(
+            steps,
+            [
+                ("engine_reset", vec![0x02]),
+                ("command_active", vec![0x01, 0x00]),
+            ]
+        );
+    }
+
+    #[test]
+    fn preparing_without_an_open_device_fails_up_front() {
+        let usb = UsbDevice::with_transport_config(Default::default()).unwrap();
+        assert!(matches!(
+            usb.out_endpoint_opener(Endpoint::Command),
+            Err(crate::Error::DeviceNotOpen)
+        ));
+    }
+
+    #[test]
+    fn execute_sends_both_commands_once() {
+        let (mut handle, script) = scripted([Some(Ok(())), Some(Ok(()))]);
+        assert!(!handle.is_spent());
+        handle.execute().unwrap();
+        assert!(handle.is_spent());
+        assert_eq!(script.lock().unwrap().sent, [vec![0x02], vec![0x01, 0x00]]);
+
+        // A spent handle sends nothing more.
+        handle.execute().unwrap();
+        assert_eq!(script.lock().unwrap().sent.len(), 2);
+    }
+
+    #[test]
+    fn a_vanished_device_stops_the_sequence_at_the_failing_step() {
+        let (mut handle, script) = scripted([Some(Err(TransferError::Disconnected))]);
+        assert_eq!(
+            handle.execute(),
+            Err(EmergencyFailure {
+                step: "engine_reset",
+                error: Some(TransferError::Disconnected),
+            })
+        );
+        assert!(script.lock().unwrap().sent.is_empty());
+
+        // Retrying skips the reset that was already submitted; the second
+        // command times out on the silent endpoint.
+        assert_eq!(
+            handle.execute(),
+            Err(EmergencyFailure {
+                step: "command_active",
+                error: None,
+            })
+        );
+        assert!(handle.is_spent());
+    }
+
+    #[test]
+    fn timed_out_writes_are_retired_before_the_endpoint_is_released() {
+        let (mut handle, script) = scripted([None, Some(Ok(()))]);
+        assert_eq!(
+            handle.execute(),
+            Err(EmergencyFailure {
+                step: "engine_reset",
+                error: None,
+            })
+        );
+        assert_eq!(script.lock().unwrap().abandoned, 0);
+
+        handle.execute().unwrap();
+        let script = script.lock().unwrap();
+        assert_eq!(script.sent, [vec![0x01, 0x00]]);
+        assert_eq!(script.abandoned, 0);
+    }
+
+    #[test]
+    fn a_claimed_endpoint_fails_without_spending_the_sequence() {
+        let (mut handle, script) = scripted([Some(Ok(())), Some(Ok(()))]);
+        script.lock().unwrap().busy = true;
+        assert_eq!(
+            handle.execute(),
+            Err(EmergencyFailure {
+                step: EMERGENCY_CLAIM_STEP,
+                error: None,
+            })
+        );
+        assert!(!handle.is_spent());
+
+        script.lock().unwrap().busy = false;
+        handle.execute().unwrap();
+        assert_eq!(script.lock().unwrap().sent.len(), 2);
+    }
+}
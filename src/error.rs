@@ -1,22 +1,141 @@
-use std::{error::Error as StdError, fmt};
+use crate::config::SmimsVersion;
+use crate::usb::Endpoint;
+use nusb::transfer::TransferError;
+use std::{error::Error as StdError, fmt, io, time::Duration};
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
+    Busy {
+        current_operation: &'static str,
+    },
+    Cancelled(&'static str),
+    ConfigRestore {
+        operation: Option<Box<Error>>,
+        restore: Box<Error>,
+    },
     DeviceNotOpen,
     DeviceNotFound {
         vid: u16,
         pid: u16,
     },
+    /// User data slot `slot` already holds data. Slots are write-once, as
+    /// this crate cannot erase flash.
+    UserDataSlotUsed {
+        slot: u8,
+    },
+    /// A flash write targets a word that is not erased, first at this
+    /// cluster. Nothing was written.
+    FlashNotErased {
+        block: u16,
+        cluster: u16,
+    },
+    /// The design never answered [`crate::IoSession::check_licence`] in a
+    /// session that wrote this licence key. The firmware does not report
+    /// licence checks, so this is inferred from `source`, the read timeout,
+    /// and may also mean a design that simply produced no reply.
+    LicenceRejected {
+        provided: u16,
+        source: Box<Error>,
+    },
+    /// User data slot `slot` does not exist; there are `slots`.
+    InvalidUserDataSlot {
+        slot: u8,
+        slots: u8,
+    },
+    /// User data slot `slot` holds a damaged header or fails its CRC.
+    CorruptUserData {
+        slot: u8,
+    },
+    /// A flash access of `words` words runs past the end of flash, where
+    /// only `available` remain from its start.
+    FlashCapacityExceeded {
+        words: usize,
+        available: usize,
+    },
+    /// Flash read back differently after a write, first at this cluster,
+    /// although the words were erased beforehand.
+    FlashWriteFailed {
+        block: u16,
+        cluster: u16,
+    },
+    /// A flash address outside the geometry the device reports.
+    FlashAddressOutOfRange {
+        block: u16,
+        cluster: u16,
+    },
+    /// The device reported a programming FIFO smaller than `minimum`
+    /// words, even after the configuration was read again.
+    ProgramFifoTooSmall {
+        words: usize,
+        minimum: usize,
+    },
+    /// A gzip-compressed bitfile could not be decompressed.
+    CorruptArchive(std::io::Error),
+    BitstreamMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    CallbackPanicked(&'static str),
+    /// The FPGA daughterboard is not seated on the SMIMS board.
+    PcbDisconnected,
+    /// [`crate::Config::validate`] rejected the local config before it was
+    /// written; `word` is the offending word index.
+    InvalidConfig {
+        word: usize,
+        reason: &'static str,
+    },
+    /// An IO settings file did not parse; the message comes from the
+    /// format's parser.
+    SettingsParse(String),
+    InvalidSettings(&'static str),
+    /// The device disappeared and was reopened under a
+    /// [`crate::ReconnectPolicy`]. The board is back in control mode; mode,
+    /// licence and session state must be re-established. `source` is the
+    /// failure that detected the loss; the failed operation was not retried.
+    SessionLost {
+        source: Box<Error>,
+    },
+    /// The background thread serving an async board exited, usually
+    /// because it panicked, before answering a request.
+    WorkerStopped,
+    /// `source` is the failure of the last of `attempts` tries. Classified
+    /// and contextualized like `source`.
+    Retried {
+        attempts: u32,
+        source: Box<Error>,
+    },
+    /// `source` ended a transfer split into FIFO-sized chunks at chunk
+    /// `chunk`, counted from zero. Classified and contextualized like
+    /// `source`.
+    ChunkFailed {
+        chunk: usize,
+        source: Box<Error>,
+    },
+    InterfaceClaimed {
+        interface: u8,
+    },
     BufferTooLarge {
         context: &'static str,
         max_words: usize,
         actual_words: usize,
     },
     FeatureUnavailable(&'static str),
+    FrameCrcMismatch {
+        frame_index: u64,
+    },
+    FrameSequenceMismatch {
+        frame_index: u64,
+        expected: u16,
+        actual: u16,
+    },
     InvalidBitfile(&'static str),
+    /// A malformed bitfile line. `line` and `column` are 1-based; `excerpt`
+    /// is the start of the offending token.
     InvalidBitfileLine {
         line: usize,
+        column: usize,
+        excerpt: String,
         reason: &'static str,
     },
     InvalidBufferLength {
@@ -28,20 +147,51 @@ pub enum Error {
         expected: &'static str,
         actual: &'static str,
     },
+    NoDeviceAtLocation(String),
+    NotABoard {
+        location: String,
+        vid: u16,
+        pid: u16,
+    },
+    NeedsReset {
+        operation: &'static str,
+    },
     PipelineEmpty,
     PipelineFull {
         capacity: usize,
     },
+    SerialNotFound(String),
     NotProgrammed,
     Timeout(&'static str),
+    /// The firmware never reported ready on the Sync endpoint. `waited`
+    /// and `polls` tell a dead board (few polls) from a slow one.
+    SyncTimeout {
+        waited: Duration,
+        polls: u32,
+    },
+    TransferTimeout {
+        context: &'static str,
+        endpoint: Endpoint,
+        timeout: Duration,
+    },
+    TransferTooLarge {
+        requested: usize,
+        max: usize,
+    },
+    TransformNotChunkSafe,
     UnexpectedResponse(&'static str),
+    /// The firmware is older than required, or differs from the version a
+    /// warm-start cache entry recorded.
     VersionMismatch {
-        expected: u16,
-        actual: u16,
+        expected: SmimsVersion,
+        actual: SmimsVersion,
     },
+    /// A transport failure. `transfer` says how far a bulk transfer got;
+    /// it is `None` for control requests and pipelined transfers.
     Usb {
         source: Box<dyn StdError + Send + Sync>,
         context: &'static str,
+        transfer: Option<UsbTransfer>,
     },
     Io(std::io::Error),
 }
@@ -49,6 +199,84 @@ pub enum Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Error::Busy { current_operation } => {
+                write!(f, "device is busy with `{current_operation}`")
+            }
+            Error::Cancelled(context) => write!(f, "operation `{context}` was cancelled"),
+            Error::ConfigRestore { operation, restore } => match operation {
+                Some(operation) => write!(
+                    f,
+                    "{operation}; additionally failed to restore configuration: {restore}"
+                ),
+                None => write!(f, "failed to restore configuration: {restore}"),
+            },
+            Error::InterfaceClaimed { interface } => write!(
+                f,
+                "USB interface {interface} is held by a kernel driver or another process"
+            ),
+            Error::Retried { attempts, source } => {
+                write!(f, "{source} (after {attempts} attempts)")
+            }
+            Error::ChunkFailed { chunk, source } => write!(f, "{source} (in chunk {chunk})"),
+            Error::WorkerStopped => write!(f, "board worker thread stopped"),
+            Error::SessionLost { source } => {
+                write!(
+                    f,
+                    "device was reconnected after `{source}`; session state was lost"
+                )
+            }
+            Error::InvalidSettings(reason) => write!(f, "invalid IO settings: {reason}"),
+            Error::SettingsParse(message) => write!(f, "failed to parse IO settings: {message}"),
+            Error::InvalidConfig { word, reason } => {
+                write!(f, "invalid config word {word}: {reason}")
+            }
+            Error::PcbDisconnected => write!(f, "FPGA daughterboard is not connected"),
+            Error::CallbackPanicked(callback) => {
+                write!(f, "{callback} callback panicked")
+            }
+            Error::BitstreamMismatch { expected, actual } => write!(
+                f,
+                "resident bitstream CRC {actual:#010x} does not match expected {expected:#010x}"
+            ),
+            Error::CorruptArchive(err) => write!(f, "gzip stream damaged: {err}"),
+            Error::ProgramFifoTooSmall { words, minimum } => write!(
+                f,
+                "device reported a {words}-word programming FIFO, below the {minimum}-word minimum"
+            ),
+            Error::FlashAddressOutOfRange { block, cluster } => write!(
+                f,
+                "flash block {block}, cluster {cluster} is outside the device's flash"
+            ),
+            Error::FlashCapacityExceeded { words, available } => write!(
+                f,
+                "{words} flash words requested where only {available} are available"
+            ),
+            Error::FlashWriteFailed { block, cluster } => write!(
+                f,
+                "flash write did not take at block {block}, cluster {cluster}"
+            ),
+            Error::InvalidUserDataSlot { slot, slots: 0 } => {
+                write!(f, "user data slot {slot} does not exist (no slots)")
+            }
+            Error::InvalidUserDataSlot { slot, slots } => {
+                write!(
+                    f,
+                    "user data slot {slot} does not exist (slots 0-{})",
+                    slots - 1
+                )
+            }
+            Error::CorruptUserData { slot } => write!(f, "user data slot {slot} is corrupted"),
+            Error::LicenceRejected { provided, source } => write!(
+                f,
+                "no reply to the licence check after writing licence key {provided:#06x}; the firmware may have rejected it ({source})"
+            ),
+            Error::FlashNotErased { block, cluster } => write!(
+                f,
+                "flash block {block} cluster {cluster} is not erased; erase it with the vendor tool before writing"
+            ),
+            Error::UserDataSlotUsed { slot } => {
+                write!(f, "user data slot {slot} is already in use")
+            }
             Error::DeviceNotOpen => write!(f, "device is not open"),
             Error::DeviceNotFound { vid, pid } => {
                 write!(f, "device {vid:#06x}:{pid:#06x} not found")
@@ -62,10 +290,27 @@ impl fmt::Display for Error {
                 "{context} exceeds FIFO capacity ({actual_words} words > {max_words} words)"
             ),
             Error::FeatureUnavailable(feature) => write!(f, "feature `{feature}` is unavailable"),
-            Error::InvalidBitfile(reason) => write!(f, "invalid bitfile: {reason}"),
-            Error::InvalidBitfileLine { line, reason } => {
-                write!(f, "invalid bitfile line {line}: {reason}")
+            Error::FrameCrcMismatch { frame_index } => {
+                write!(f, "frame {frame_index} failed CRC check")
             }
+            Error::FrameSequenceMismatch {
+                frame_index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "frame {frame_index} has sequence number {actual} (expected {expected})"
+            ),
+            Error::InvalidBitfile(reason) => write!(f, "invalid bitfile: {reason}"),
+            Error::InvalidBitfileLine {
+                line,
+                column,
+                excerpt,
+                reason,
+            } => write!(
+                f,
+                "invalid bitfile line {line}, column {column}: {reason} (near `{excerpt}`)"
+            ),
             Error::InvalidBufferLength {
                 context,
                 expected,
@@ -80,22 +325,71 @@ impl fmt::Display for Error {
                     "invalid device mode (expected `{expected}`, got `{actual}`)"
                 )
             }
+            Error::NoDeviceAtLocation(location) => write!(f, "no USB device at {location}"),
+            Error::NotABoard { location, vid, pid } => write!(
+                f,
+                "device {vid:#06x}:{pid:#06x} at {location} is not a VLFD board"
+            ),
+            Error::NeedsReset { operation } => write!(
+                f,
+                "device needs a reset after `{operation}` left it in an unknown state"
+            ),
             Error::PipelineEmpty => write!(f, "transfer pipeline has no pending transfers"),
             Error::PipelineFull { capacity } => write!(
                 f,
                 "transfer pipeline is full (capacity {capacity} outstanding transfers)"
             ),
+            Error::SerialNotFound(serial) => {
+                write!(f, "no board with serial number `{serial}` found")
+            }
             Error::NotProgrammed => write!(f, "FPGA is not programmed"),
             Error::Timeout(context) => write!(f, "operation `{context}` timed out"),
+            Error::SyncTimeout { waited, polls } => write!(
+                f,
+                "firmware did not report ready within {waited:?} ({polls} sync polls)"
+            ),
+            Error::TransferTimeout {
+                context,
+                endpoint,
+                timeout,
+            } => write!(
+                f,
+                "operation `{context}` on the {endpoint:?} endpoint timed out after {timeout:?}"
+            ),
+            Error::TransferTooLarge { requested, max } => write!(
+                f,
+                "transfer of {requested} words exceeds the firmware limit of {max} words"
+            ),
+            Error::TransformNotChunkSafe => write!(
+                f,
+                "IO transform is not word-local and cannot be used on pipelined transfers"
+            ),
             Error::UnexpectedResponse(context) => {
                 write!(f, "unexpected response during `{context}`")
             }
             Error::VersionMismatch { expected, actual } => write!(
                 f,
-                "SMIMS version mismatch (expected {expected:#06x}, found {actual:#06x})"
+                "SMIMS version mismatch: expected at least {expected}, device reports {actual}"
             ),
-            Error::Usb { source, context } => {
-                write!(f, "usb error {source} in `{context}`")
+            Error::Usb {
+                source,
+                context,
+                transfer,
+            } => {
+                write!(f, "usb error {source} in `{context}`")?;
+                let Some(transfer) = transfer else {
+                    return Ok(());
+                };
+                write!(
+                    f,
+                    " on the {:?} endpoint ({}) after {} of {} bytes",
+                    transfer.endpoint, transfer.direction, transfer.completed, transfer.requested
+                )?;
+                match transfer.word_aligned {
+                    Some(true) => write!(f, ", at a word boundary"),
+                    Some(false) => write!(f, ", mid-word"),
+                    None => Ok(()),
+                }
             }
             Error::Io(err) => err.fmt(f),
         }
@@ -105,15 +399,904 @@ impl fmt::Display for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            Error::ConfigRestore { restore, .. } => Some(restore.as_ref()),
+            Error::Retried { source, .. } | Error::ChunkFailed { source, .. } => {
+                Some(source.as_ref())
+            }
+            Error::SessionLost { source } | Error::LicenceRejected { source, .. } => {
+                Some(source.as_ref())
+            }
             Error::Usb { source, .. } => Some(source.as_ref()),
-            Error::Io(err) => Some(err),
+            Error::Io(err) | Error::CorruptArchive(err) => Some(err),
             _ => None,
         }
     }
 }
 
+/// Cheap, copyable classification of an [`Error`].
+///
+/// Every [`Error`] variant maps to exactly one kind, which makes the kind
+/// suitable for counters, maps and `match` statements where the full error
+/// cannot be stored or shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorKind {
+    Busy,
+    Cancelled,
+    ConfigRestore,
+    DeviceNotOpen,
+    DeviceNotFound,
+    UserDataSlotUsed,
+    FlashNotErased,
+    LicenceRejected,
+    InvalidUserDataSlot,
+    CorruptUserData,
+    FlashCapacityExceeded,
+    FlashWriteFailed,
+    FlashAddressOutOfRange,
+    ProgramFifoTooSmall,
+    CorruptArchive,
+    BitstreamMismatch,
+    CallbackPanicked,
+    PcbDisconnected,
+    InvalidConfig,
+    SettingsParse,
+    InvalidSettings,
+    SessionLost,
+    WorkerStopped,
+    InterfaceClaimed,
+    BufferTooLarge,
+    FeatureUnavailable,
+    FrameCrcMismatch,
+    FrameSequenceMismatch,
+    InvalidBitfile,
+    InvalidBitfileLine,
+    InvalidBufferLength,
+    InvalidMode,
+    NoDeviceAtLocation,
+    NotABoard,
+    NeedsReset,
+    PipelineEmpty,
+    PipelineFull,
+    SerialNotFound,
+    NotProgrammed,
+    Timeout,
+    TransferTooLarge,
+    TransformNotChunkSafe,
+    UnexpectedResponse,
+    VersionMismatch,
+    Usb(UsbErrorKind),
+    Io,
+}
+
+/// What a caller can do about an [`Error`], from [`Error::class`].
+///
+/// Every error falls in exactly one class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Likely to succeed if the same operation is tried again.
+    Transient,
+    /// The device was unplugged or reset; it must be reopened first.
+    DeviceLost,
+    /// Host and firmware disagree about the protocol state; reset the board
+    /// before trying again.
+    Protocol,
+    /// Retrying will not help without a change of input, setup or hardware.
+    Fatal,
+}
+
+/// Direction of a bulk transfer, as seen from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferDirection {
+    In,
+    Out,
+}
+
+impl fmt::Display for TransferDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransferDirection::In => "IN",
+            TransferDirection::Out => "OUT",
+        })
+    }
+}
+
+/// How far a bulk transfer got before it failed, carried by
+/// [`Error::Usb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsbTransfer {
+    pub endpoint: Endpoint,
+    pub direction: TransferDirection,
+    /// Bytes the transfer asked to move.
+    pub requested: usize,
+    /// Bytes received before the failure. For writes, bytes copied into
+    /// the endpoint writer's buffer, which can run ahead of what reached the
+    /// device: a failure in `nusb_bulk_flush` counts every byte although
+    /// the last of them may never have been sent.
+    pub completed: usize,
+    /// For word reads and writes, whether the failure fell between whole
+    /// words; `None` for byte transfers. A read that stops mid-word points
+    /// at the firmware rather than the cable.
+    pub word_aligned: Option<bool>,
+}
+
+/// Transport-level failure classes carried by [`Error::Usb`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UsbErrorKind {
+    Timeout,
+    Pipe,
+    NoDevice,
+    Busy,
+    Access,
+    NotFound,
+    Cancelled,
+    Unsupported,
+    Other,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Usb(kind) => write!(f, "usb_{}", kind.as_str()),
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+impl ErrorKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::Busy => "busy",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::ConfigRestore => "config_restore",
+            ErrorKind::DeviceNotOpen => "device_not_open",
+            ErrorKind::UserDataSlotUsed => "user_data_slot_used",
+            ErrorKind::FlashNotErased => "flash_not_erased",
+            ErrorKind::LicenceRejected => "licence_rejected",
+            ErrorKind::InvalidUserDataSlot => "invalid_user_data_slot",
+            ErrorKind::CorruptUserData => "corrupt_user_data",
+            ErrorKind::FlashCapacityExceeded => "flash_capacity_exceeded",
+            ErrorKind::FlashWriteFailed => "flash_write_failed",
+            ErrorKind::FlashAddressOutOfRange => "flash_address_out_of_range",
+            ErrorKind::ProgramFifoTooSmall => "program_fifo_too_small",
+            ErrorKind::CorruptArchive => "corrupt_archive",
+            ErrorKind::BitstreamMismatch => "bitstream_mismatch",
+            ErrorKind::CallbackPanicked => "callback_panicked",
+            ErrorKind::PcbDisconnected => "pcb_disconnected",
+            ErrorKind::InvalidConfig => "invalid_config",
+            ErrorKind::SettingsParse => "settings_parse",
+            ErrorKind::InvalidSettings => "invalid_settings",
+            ErrorKind::SessionLost => "session_lost",
+            ErrorKind::WorkerStopped => "worker_stopped",
+            ErrorKind::InterfaceClaimed => "interface_claimed",
+            ErrorKind::DeviceNotFound => "device_not_found",
+            ErrorKind::BufferTooLarge => "buffer_too_large",
+            ErrorKind::FeatureUnavailable => "feature_unavailable",
+            ErrorKind::FrameCrcMismatch => "frame_crc_mismatch",
+            ErrorKind::FrameSequenceMismatch => "frame_sequence_mismatch",
+            ErrorKind::InvalidBitfile => "invalid_bitfile",
+            ErrorKind::InvalidBitfileLine => "invalid_bitfile_line",
+            ErrorKind::InvalidBufferLength => "invalid_buffer_length",
+            ErrorKind::InvalidMode => "invalid_mode",
+            ErrorKind::NoDeviceAtLocation => "no_device_at_location",
+            ErrorKind::NotABoard => "not_a_board",
+            ErrorKind::NeedsReset => "needs_reset",
+            ErrorKind::PipelineEmpty => "pipeline_empty",
+            ErrorKind::PipelineFull => "pipeline_full",
+            ErrorKind::SerialNotFound => "serial_not_found",
+            ErrorKind::NotProgrammed => "not_programmed",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::TransferTooLarge => "transfer_too_large",
+            ErrorKind::TransformNotChunkSafe => "transform_not_chunk_safe",
+            ErrorKind::UnexpectedResponse => "unexpected_response",
+            ErrorKind::VersionMismatch => "version_mismatch",
+            ErrorKind::Usb(_) => "usb",
+            ErrorKind::Io => "io",
+        }
+    }
+}
+
+impl UsbErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UsbErrorKind::Timeout => "timeout",
+            UsbErrorKind::Pipe => "pipe",
+            UsbErrorKind::NoDevice => "no_device",
+            UsbErrorKind::Busy => "busy",
+            UsbErrorKind::Access => "access",
+            UsbErrorKind::NotFound => "not_found",
+            UsbErrorKind::Cancelled => "cancelled",
+            UsbErrorKind::Unsupported => "unsupported",
+            UsbErrorKind::Other => "other",
+        }
+    }
+
+    pub fn class(self) -> ErrorClass {
+        match self {
+            // Unrecognized transport failures are retried, as opening does.
+            UsbErrorKind::Timeout
+            | UsbErrorKind::Pipe
+            | UsbErrorKind::Busy
+            | UsbErrorKind::Cancelled
+            | UsbErrorKind::Other => ErrorClass::Transient,
+            UsbErrorKind::NoDevice => ErrorClass::DeviceLost,
+            UsbErrorKind::Access | UsbErrorKind::NotFound | UsbErrorKind::Unsupported => {
+                ErrorClass::Fatal
+            }
+        }
+    }
+
+    fn classify(source: &(dyn StdError + 'static)) -> Self {
+        if let Some(err) = source.downcast_ref::<TransferError>() {
+            return Self::from_transfer_error(*err);
+        }
+        if let Some(err) = source.downcast_ref::<nusb::Error>() {
+            return match err.kind() {
+                nusb::ErrorKind::Disconnected => Self::NoDevice,
+                nusb::ErrorKind::Busy => Self::Busy,
+                nusb::ErrorKind::PermissionDenied => Self::Access,
+                nusb::ErrorKind::NotFound => Self::NotFound,
+                nusb::ErrorKind::Unsupported => Self::Unsupported,
+                _ => Self::Other,
+            };
+        }
+        if let Some(err) = source.downcast_ref::<io::Error>() {
+            if let Some(inner) = err.get_ref() {
+                if let Some(err) = inner.downcast_ref::<TransferError>() {
+                    return Self::from_transfer_error(*err);
+                }
+            }
+            return match err.kind() {
+                io::ErrorKind::TimedOut => Self::Timeout,
+                io::ErrorKind::Interrupted => Self::Cancelled,
+                io::ErrorKind::PermissionDenied => Self::Access,
+                io::ErrorKind::NotFound => Self::NotFound,
+                io::ErrorKind::ConnectionReset => Self::Pipe,
+                io::ErrorKind::ConnectionAborted | io::ErrorKind::NotConnected => Self::NoDevice,
+                io::ErrorKind::Unsupported => Self::Unsupported,
+                _ => Self::Other,
+            };
+        }
+        Self::Other
+    }
+
+    fn from_transfer_error(err: TransferError) -> Self {
+        match err {
+            TransferError::Stall => Self::Pipe,
+            TransferError::Disconnected => Self::NoDevice,
+            TransferError::Cancelled => Self::Cancelled,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Owned, cloneable snapshot of an [`Error`] for storing or sharing across
+/// threads.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ErrorSummary {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub context: Option<&'static str>,
+}
+
+impl fmt::Display for ErrorSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.kind, self.message)
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Busy { .. } => ErrorKind::Busy,
+            Error::Cancelled(_) => ErrorKind::Cancelled,
+            Error::ConfigRestore { .. } => ErrorKind::ConfigRestore,
+            Error::DeviceNotOpen => ErrorKind::DeviceNotOpen,
+            Error::UserDataSlotUsed { .. } => ErrorKind::UserDataSlotUsed,
+            Error::FlashNotErased { .. } => ErrorKind::FlashNotErased,
+            Error::LicenceRejected { .. } => ErrorKind::LicenceRejected,
+            Error::InvalidUserDataSlot { .. } => ErrorKind::InvalidUserDataSlot,
+            Error::CorruptUserData { .. } => ErrorKind::CorruptUserData,
+            Error::FlashCapacityExceeded { .. } => ErrorKind::FlashCapacityExceeded,
+            Error::FlashWriteFailed { .. } => ErrorKind::FlashWriteFailed,
+            Error::FlashAddressOutOfRange { .. } => ErrorKind::FlashAddressOutOfRange,
+            Error::ProgramFifoTooSmall { .. } => ErrorKind::ProgramFifoTooSmall,
+            Error::CorruptArchive(_) => ErrorKind::CorruptArchive,
+            Error::BitstreamMismatch { .. } => ErrorKind::BitstreamMismatch,
+            Error::CallbackPanicked(_) => ErrorKind::CallbackPanicked,
+            Error::PcbDisconnected => ErrorKind::PcbDisconnected,
+            Error::InvalidConfig { .. } => ErrorKind::InvalidConfig,
+            Error::SettingsParse(_) => ErrorKind::SettingsParse,
+            Error::InvalidSettings(_) => ErrorKind::InvalidSettings,
+            Error::SessionLost { .. } => ErrorKind::SessionLost,
+            Error::WorkerStopped => ErrorKind::WorkerStopped,
+            Error::Retried { source, .. } | Error::ChunkFailed { source, .. } => source.kind(),
+            Error::InterfaceClaimed { .. } => ErrorKind::InterfaceClaimed,
+            Error::DeviceNotFound { .. } => ErrorKind::DeviceNotFound,
+            Error::BufferTooLarge { .. } => ErrorKind::BufferTooLarge,
+            Error::FeatureUnavailable(_) => ErrorKind::FeatureUnavailable,
+            Error::FrameCrcMismatch { .. } => ErrorKind::FrameCrcMismatch,
+            Error::FrameSequenceMismatch { .. } => ErrorKind::FrameSequenceMismatch,
+            Error::InvalidBitfile(_) => ErrorKind::InvalidBitfile,
+            Error::InvalidBitfileLine { .. } => ErrorKind::InvalidBitfileLine,
+            Error::InvalidBufferLength { .. } => ErrorKind::InvalidBufferLength,
+            Error::InvalidMode { .. } => ErrorKind::InvalidMode,
+            Error::NoDeviceAtLocation(_) => ErrorKind::NoDeviceAtLocation,
+            Error::NotABoard { .. } => ErrorKind::NotABoard,
+            Error::NeedsReset { .. } => ErrorKind::NeedsReset,
+            Error::PipelineEmpty => ErrorKind::PipelineEmpty,
+            Error::PipelineFull { .. } => ErrorKind::PipelineFull,
+            Error::SerialNotFound(_) => ErrorKind::SerialNotFound,
+            Error::NotProgrammed => ErrorKind::NotProgrammed,
+            Error::Timeout(_) | Error::SyncTimeout { .. } | Error::TransferTimeout { .. } => {
+                ErrorKind::Timeout
+            }
+            Error::TransferTooLarge { .. } => ErrorKind::TransferTooLarge,
+            Error::TransformNotChunkSafe => ErrorKind::TransformNotChunkSafe,
+            Error::UnexpectedResponse(_) => ErrorKind::UnexpectedResponse,
+            Error::VersionMismatch { .. } => ErrorKind::VersionMismatch,
+            Error::Usb { source, .. } => ErrorKind::Usb(UsbErrorKind::classify(source.as_ref())),
+            Error::Io(_) => ErrorKind::Io,
+        }
+    }
+
+    /// How a supervisor should react to the error.
+    ///
+    /// Wrappers such as [`Error::Retried`] and [`Error::ChunkFailed`] take
+    /// the class of their source, and [`Error::ConfigRestore`] that of the
+    /// failed restore. [`Error::Io`] is transient when interrupted, timed
+    /// out or would block, and fatal otherwise.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::Busy { .. }
+            | Error::PipelineFull { .. }
+            | Error::Timeout(_)
+            | Error::SyncTimeout { .. }
+            | Error::TransferTimeout { .. } => ErrorClass::Transient,
+            Error::SessionLost { .. } => ErrorClass::DeviceLost,
+            Error::ProgramFifoTooSmall { .. }
+            | Error::FrameCrcMismatch { .. }
+            | Error::FrameSequenceMismatch { .. }
+            | Error::NeedsReset { .. }
+            | Error::UnexpectedResponse(_) => ErrorClass::Protocol,
+            Error::Cancelled(_)
+            | Error::DeviceNotOpen
+            | Error::DeviceNotFound { .. }
+            | Error::LicenceRejected { .. }
+            | Error::InvalidUserDataSlot { .. }
+            | Error::CorruptUserData { .. }
+            | Error::UserDataSlotUsed { .. }
+            | Error::FlashCapacityExceeded { .. }
+            | Error::FlashWriteFailed { .. }
+            | Error::FlashNotErased { .. }
+            | Error::FlashAddressOutOfRange { .. }
+            | Error::CorruptArchive(_)
+            | Error::BitstreamMismatch { .. }
+            | Error::CallbackPanicked(_)
+            | Error::PcbDisconnected
+            | Error::InvalidConfig { .. }
+            | Error::SettingsParse(_)
+            | Error::InvalidSettings(_)
+            | Error::WorkerStopped
+            | Error::InterfaceClaimed { .. }
+            | Error::BufferTooLarge { .. }
+            | Error::FeatureUnavailable(_)
+            | Error::InvalidBitfile(_)
+            | Error::InvalidBitfileLine { .. }
+            | Error::InvalidBufferLength { .. }
+            | Error::InvalidMode { .. }
+            | Error::NoDeviceAtLocation(_)
+            | Error::NotABoard { .. }
+            | Error::PipelineEmpty
+            | Error::SerialNotFound(_)
+            | Error::NotProgrammed
+            | Error::TransferTooLarge { .. }
+            | Error::TransformNotChunkSafe
+            | Error::VersionMismatch { .. } => ErrorClass::Fatal,
+            Error::Io(err) => match err.kind() {
+                io::ErrorKind::Interrupted
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::WouldBlock => ErrorClass::Transient,
+                _ => ErrorClass::Fatal,
+            },
+            Error::Retried { source, .. } | Error::ChunkFailed { source, .. } => source.class(),
+            Error::ConfigRestore { restore, .. } => restore.class(),
+            Error::Usb { source, .. } => UsbErrorKind::classify(source.as_ref()).class(),
+        }
+    }
+
+    /// Whether trying the same operation again is likely to succeed.
+    pub fn is_transient(&self) -> bool {
+        self.class() == ErrorClass::Transient
+    }
+
+    /// Whether the device went away, so it must be reopened.
+    pub fn is_device_lost(&self) -> bool {
+        self.class() == ErrorClass::DeviceLost
+    }
+
+    /// Whether the board needs a reset before it can be used again.
+    pub fn is_protocol_error(&self) -> bool {
+        self.class() == ErrorClass::Protocol
+    }
+
+    /// Whether the error persists however often it is retried.
+    pub fn is_fatal(&self) -> bool {
+        self.class() == ErrorClass::Fatal
+    }
+
+    /// The static operation name attached to the error, if any.
+    pub fn context(&self) -> Option<&'static str> {
+        match self {
+            Error::BufferTooLarge { context, .. }
+            | Error::InvalidBufferLength { context, .. }
+            | Error::TransferTimeout { context, .. }
+            | Error::Usb { context, .. } => Some(context),
+            Error::Cancelled(context)
+            | Error::Timeout(context)
+            | Error::UnexpectedResponse(context) => Some(context),
+            Error::SyncTimeout { .. } => Some("sync_delay"),
+            Error::Retried { source, .. } | Error::ChunkFailed { source, .. } => source.context(),
+            _ => None,
+        }
+    }
+
+    pub fn to_owned_summary(&self) -> ErrorSummary {
+        ErrorSummary {
+            kind: self.kind(),
+            message: self.to_string(),
+            context: self.context(),
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Self::Io(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorClass, ErrorKind, TransferDirection, UsbErrorKind, UsbTransfer};
+    use crate::usb::Endpoint;
+    use nusb::transfer::TransferError;
+    use std::collections::HashMap;
+
+    #[test]
+    fn usb_sources_are_classified_into_sub_kinds() {
+        let stall = Error::Usb {
+            source: Box::new(TransferError::Stall),
+            context: "pipeline_write",
+            transfer: None,
+        };
+        assert_eq!(stall.kind(), ErrorKind::Usb(UsbErrorKind::Pipe));
+
+        let gone = Error::Usb {
+            source: Box::new(std::io::Error::from(TransferError::Disconnected)),
+            context: "nusb_bulk_read",
+            transfer: None,
+        };
+        assert_eq!(gone.kind(), ErrorKind::Usb(UsbErrorKind::NoDevice));
+
+        let opaque = Error::Usb {
+            source: Box::new(std::io::Error::other("boom")),
+            context: "nusb_bulk_read",
+            transfer: None,
+        };
+        assert_eq!(opaque.kind(), ErrorKind::Usb(UsbErrorKind::Other));
+    }
+
+    #[test]
+    fn usb_errors_describe_the_failed_transfer() {
+        let transfer = UsbTransfer {
+            endpoint: Endpoint::FifoRead,
+            direction: TransferDirection::In,
+            requested: 2048,
+            completed: 513,
+            word_aligned: Some(false),
+        };
+        let err = Error::Usb {
+            source: Box::new(TransferError::Stall),
+            context: "nusb_bulk_read",
+            transfer: Some(transfer),
+        };
+        assert_eq!(
+            err.to_string(),
+            "usb error Stall in `nusb_bulk_read` on the FifoRead endpoint (IN) \
+             after 513 of 2048 bytes, mid-word"
+        );
+    }
+
+    #[test]
+    fn errors_are_classified_for_supervision() {
+        let usb = |source: TransferError| Error::Usb {
+            source: Box::new(source),
+            context: "nusb_bulk_read",
+            transfer: None,
+        };
+        let cases = [
+            (
+                Error::SyncTimeout {
+                    waited: std::time::Duration::from_secs(1),
+                    polls: 3,
+                },
+                ErrorClass::Transient,
+            ),
+            (usb(TransferError::Stall), ErrorClass::Transient),
+            (usb(TransferError::Cancelled), ErrorClass::Transient),
+            (usb(TransferError::Disconnected), ErrorClass::DeviceLost),
+            (
+                Error::SessionLost {
+                    source: Box::new(usb(TransferError::Disconnected)),
+                },
+                ErrorClass::DeviceLost,
+            ),
+            (Error::UnexpectedResponse("sync"), ErrorClass::Protocol),
+            (
+                Error::FrameCrcMismatch { frame_index: 7 },
+                ErrorClass::Protocol,
+            ),
+            (
+                Error::DeviceNotFound {
+                    vid: 0x2200,
+                    pid: 0x2100,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::VersionMismatch {
+                    expected: 0x0210.into(),
+                    actual: 0x0193.into(),
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::InvalidBitfile("empty"), ErrorClass::Fatal),
+            (
+                Error::Retried {
+                    attempts: 3,
+                    source: Box::new(Error::Timeout("sync_delay")),
+                },
+                ErrorClass::Transient,
+            ),
+            (
+                Error::ConfigRestore {
+                    operation: Some(Box::new(Error::NotProgrammed)),
+                    restore: Box::new(usb(TransferError::Disconnected)),
+                },
+                ErrorClass::DeviceLost,
+            ),
+        ];
+        for (err, class) in cases {
+            assert_eq!(err.class(), class, "{err}");
+            let flags = [
+                err.is_transient(),
+                err.is_device_lost(),
+                err.is_protocol_error(),
+                err.is_fatal(),
+            ];
+            assert_eq!(flags.iter().filter(|flag| **flag).count(), 1, "{err}");
+        }
+    }
+
+    #[test]
+    fn every_variant_has_a_class() {
+        use std::{io, time::Duration};
+        let usb = |source: TransferError| Error::Usb {
+            source: Box::new(source),
+            context: "nusb_bulk_write",
+            transfer: None,
+        };
+        let io = |kind: io::ErrorKind| Error::Io(io::Error::from(kind));
+        let timeout = || Box::new(Error::Timeout("sync_delay"));
+        let cases = [
+            (
+                Error::Busy {
+                    current_operation: "program",
+                },
+                ErrorClass::Transient,
+            ),
+            (Error::PipelineFull { capacity: 4 }, ErrorClass::Transient),
+            (Error::Timeout("sync_delay"), ErrorClass::Transient),
+            (
+                Error::SyncTimeout {
+                    waited: Duration::from_secs(1),
+                    polls: 3,
+                },
+                ErrorClass::Transient,
+            ),
+            (
+                Error::TransferTimeout {
+                    context: "nusb_bulk_read",
+                    endpoint: Endpoint::FifoRead,
+                    timeout: Duration::from_secs(1),
+                },
+                ErrorClass::Transient,
+            ),
+            (io(io::ErrorKind::Interrupted), ErrorClass::Transient),
+            (io(io::ErrorKind::TimedOut), ErrorClass::Transient),
+            (io(io::ErrorKind::WouldBlock), ErrorClass::Transient),
+            (io(io::ErrorKind::NotFound), ErrorClass::Fatal),
+            (io(io::ErrorKind::InvalidData), ErrorClass::Fatal),
+            (usb(TransferError::Stall), ErrorClass::Transient),
+            (usb(TransferError::Disconnected), ErrorClass::DeviceLost),
+            (
+                Error::SessionLost { source: timeout() },
+                ErrorClass::DeviceLost,
+            ),
+            (
+                Error::ProgramFifoTooSmall {
+                    words: 0,
+                    minimum: 1,
+                },
+                ErrorClass::Protocol,
+            ),
+            (
+                Error::FrameCrcMismatch { frame_index: 1 },
+                ErrorClass::Protocol,
+            ),
+            (
+                Error::FrameSequenceMismatch {
+                    frame_index: 1,
+                    expected: 1,
+                    actual: 2,
+                },
+                ErrorClass::Protocol,
+            ),
+            (
+                Error::NeedsReset {
+                    operation: "program",
+                },
+                ErrorClass::Protocol,
+            ),
+            (Error::UnexpectedResponse("sync"), ErrorClass::Protocol),
+            (Error::Cancelled("program"), ErrorClass::Fatal),
+            (Error::DeviceNotOpen, ErrorClass::Fatal),
+            (
+                Error::DeviceNotFound {
+                    vid: 0x2200,
+                    pid: 0x2100,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::LicenceRejected {
+                    provided: 0x1234,
+                    source: timeout(),
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::InvalidUserDataSlot { slot: 9, slots: 8 },
+                ErrorClass::Fatal,
+            ),
+            (Error::CorruptUserData { slot: 0 }, ErrorClass::Fatal),
+            (Error::UserDataSlotUsed { slot: 0 }, ErrorClass::Fatal),
+            (
+                Error::FlashCapacityExceeded {
+                    words: 2,
+                    available: 1,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::FlashWriteFailed {
+                    block: 0,
+                    cluster: 0,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::FlashNotErased {
+                    block: 0,
+                    cluster: 0,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::FlashAddressOutOfRange {
+                    block: 16,
+                    cluster: 0,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::CorruptArchive(io::Error::from(io::ErrorKind::InvalidData)),
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::BitstreamMismatch {
+                    expected: 1,
+                    actual: 2,
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::CallbackPanicked("progress"), ErrorClass::Fatal),
+            (Error::PcbDisconnected, ErrorClass::Fatal),
+            (
+                Error::InvalidConfig {
+                    word: 2,
+                    reason: "reserved",
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::SettingsParse("line 1".to_owned()), ErrorClass::Fatal),
+            (Error::InvalidSettings("empty"), ErrorClass::Fatal),
+            (Error::WorkerStopped, ErrorClass::Fatal),
+            (Error::InterfaceClaimed { interface: 0 }, ErrorClass::Fatal),
+            (
+                Error::BufferTooLarge {
+                    context: "write",
+                    max_words: 1,
+                    actual_words: 2,
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::FeatureUnavailable("reconnect"), ErrorClass::Fatal),
+            (Error::InvalidBitfile("empty"), ErrorClass::Fatal),
+            (
+                Error::InvalidBitfileLine {
+                    line: 1,
+                    column: 1,
+                    excerpt: "zz".to_owned(),
+                    reason: "not hex",
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::InvalidBufferLength {
+                    context: "read",
+                    expected: 2,
+                    actual: 1,
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::InvalidMode {
+                    expected: "vericomm",
+                    actual: "control",
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::NoDeviceAtLocation("1-1".to_owned()),
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::NotABoard {
+                    location: "1-1".to_owned(),
+                    vid: 1,
+                    pid: 2,
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::PipelineEmpty, ErrorClass::Fatal),
+            (
+                Error::SerialNotFound("VLFD-0042".to_owned()),
+                ErrorClass::Fatal,
+            ),
+            (Error::NotProgrammed, ErrorClass::Fatal),
+            (
+                Error::TransferTooLarge {
+                    requested: 2,
+                    max: 1,
+                },
+                ErrorClass::Fatal,
+            ),
+            (Error::TransformNotChunkSafe, ErrorClass::Fatal),
+            (
+                Error::VersionMismatch {
+                    expected: 0x0210.into(),
+                    actual: 0x0193.into(),
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::Retried {
+                    attempts: 3,
+                    source: Box::new(Error::NotProgrammed),
+                },
+                ErrorClass::Fatal,
+            ),
+            (
+                Error::ChunkFailed {
+                    chunk: 2,
+                    source: Box::new(usb(TransferError::Disconnected)),
+                },
+                ErrorClass::DeviceLost,
+            ),
+            (
+                Error::ConfigRestore {
+                    operation: None,
+                    restore: timeout(),
+                },
+                ErrorClass::Transient,
+            ),
+        ];
+        for (err, class) in cases {
+            assert_eq!(err.class(), class, "{err:?}");
+        }
+    }
+
+    #[test]
+    fn usb_kinds_pick_a_class() {
+        use UsbErrorKind::*;
+        for (kind, class) in [
+            (Timeout, ErrorClass::Transient),
+            (Pipe, ErrorClass::Transient),
+            (Busy, ErrorClass::Transient),
+            (Cancelled, ErrorClass::Transient),
+            (Other, ErrorClass::Transient),
+            (NoDevice, ErrorClass::DeviceLost),
+            (Access, ErrorClass::Fatal),
+            (NotFound, ErrorClass::Fatal),
+            (Unsupported, ErrorClass::Fatal),
+        ] {
+            assert_eq!(kind.class(), class, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn kinds_can_be_used_as_counter_keys() {
+        let mut counts = HashMap::new();
+        for err in [
+            Error::Timeout("sync_delay"),
+            Error::Timeout("pipeline_read"),
+            Error::NotProgrammed,
+        ] {
+            *counts.entry(err.kind()).or_insert(0u32) += 1;
+        }
+        assert_eq!(counts[&ErrorKind::Timeout], 2);
+        assert_eq!(counts[&ErrorKind::NotProgrammed], 1);
+    }
+
+    #[test]
+    fn version_mismatch_prints_dotted_versions() {
+        let err = Error::VersionMismatch {
+            expected: 0x0210.into(),
+            actual: 0x0193.into(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "SMIMS version mismatch: expected at least 2.1.0, device reports 1.9.3"
+        );
+    }
+
+    #[test]
+    fn invalid_user_data_slots_name_the_valid_range() {
+        let err = Error::InvalidUserDataSlot { slot: 4, slots: 4 };
+        assert_eq!(
+            err.to_string(),
+            "user data slot 4 does not exist (slots 0-3)"
+        );
+        let err = Error::InvalidUserDataSlot { slot: 0, slots: 0 };
+        assert_eq!(
+            err.to_string(),
+            "user data slot 0 does not exist (no slots)"
+        );
+    }
+
+    #[test]
+    fn serial_not_found_names_the_requested_serial() {
+        let err = Error::SerialNotFound("VLFD-0042".to_owned());
+        assert_eq!(err.kind(), ErrorKind::SerialNotFound);
+        assert_eq!(
+            err.to_string(),
+            "no board with serial number `VLFD-0042` found"
+        );
+    }
+
+    #[test]
+    fn owned_summary_keeps_kind_message_and_context() {
+        let summary = Error::Timeout("sync_delay").to_owned_summary();
+        let shared = summary.clone();
+        assert_eq!(shared.kind, ErrorKind::Timeout);
+        assert_eq!(shared.context, Some("sync_delay"));
+        assert_eq!(shared.message, "operation `sync_delay` timed out");
+        assert_eq!(
+            shared.to_string(),
+            "[timeout] operation `sync_delay` timed out"
+        );
+    }
+}
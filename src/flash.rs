@@ -0,0 +1,455 @@
+use crate::config::{FlashAddress, FlashGeometry, FlashRange};
+use crate::error::{Error, Result};
+use crate::program::{ProgramPhase, Progress, bitstream_crc32};
+use crate::session::{Board, BoardMode};
+use crate::transport::Transport;
+
+/// User data slots, each one flash block taken from the end of flash so
+/// they stay clear of a bitstream stored from block 0.
+pub const USER_DATA_SLOTS: u8 = 4;
+
+/// First word of an occupied slot.
+const USER_DATA_MAGIC: u16 = 0x5544;
+/// Magic, byte length (two words) and CRC-32 of the payload (two words).
+const USER_DATA_HEADER_WORDS: usize = 5;
+
+impl<T: Transport> Board<T> {
+    /// Reads the flash clusters in `range`, both ends included.
+    ///
+    /// The range is set in the flash addressing words only for the read,
+    /// as by [`Board::with_config_override`], and the board is back in
+    /// control mode afterwards. Addresses outside
+    /// [`crate::Config::flash_geometry`] are rejected before anything is
+    /// sent.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn flash_read(&mut self, range: FlashRange) -> Result<Vec<u16>> {
+        self.ensure_ready()?;
+        let len = self.config().flash_geometry().range_words(&range)?;
+        let chunk_words = self.fifo_chunk_words()?;
+        self.with_config_override(
+            |config| config.set_flash_read_range(range),
+            |board| {
+                board.activate(BoardMode::FlashRead)?;
+                let mut words = vec![0u16; len];
+                let read = words
+                    .chunks_mut(chunk_words)
+                    .try_for_each(|chunk| board.fifo_read_words(chunk));
+                // Leave flash read mode even after a failed read, so the
+                // addressing words can be restored.
+                let leave = board.command_active();
+                read?;
+                leave?;
+                Ok(words)
+            },
+        )
+    }
+
+    /// Writes `data` to flash from `start` on, then reads it back.
+    ///
+    /// Flash programming only clears bits, so the words written must be
+    /// erased (all `0xffff`) beforehand. They are read first, and any that
+    /// is not fails the write with [`Error::FlashNotErased`] before flash is
+    /// touched. A final partial cluster is padded with `0xffff`, which
+    /// leaves the rest of it unchanged.
+    ///
+    /// The SMIMS command set has no erase command, so this crate cannot
+    /// erase flash: blocks must be erased with the vendor tool before they
+    /// are rewritten here.
+    ///
+    /// Data running past the end of flash is rejected with
+    /// [`Error::FlashCapacityExceeded`] before anything is sent. The board
+    /// is back in control mode afterwards, with its configuration re-read.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(words = data.len()), err)
+    )]
+    pub fn flash_write(&mut self, start: FlashAddress, data: &[u16]) -> Result<()> {
+        self.write_flash(start, data, &mut Progress::silent())
+    }
+
+    /// Body of [`Board::flash_write`], reporting each chunk to `progress`
+    /// and checking the board's cancel source before it is written.
+    pub(crate) fn write_flash(
+        &mut self,
+        start: FlashAddress,
+        data: &[u16],
+        progress: &mut Progress<'_>,
+    ) -> Result<()> {
+        self.ensure_ready()?;
+        let geometry = self.config().flash_geometry();
+        let first = geometry.offset_of(start)?;
+        if data.is_empty() {
+            return Ok(());
+        }
+        let range = geometry.range_for_len(start, data.len())?;
+        let chunk_words = self.fifo_chunk_words()?;
+
+        // Writing over data would leave the AND of old and new bits.
+        let current = self.flash_read(range)?;
+        if let Some(offset) = current[..data.len()]
+            .iter()
+            .position(|&word| word != 0xffff)
+        {
+            let used = geometry.address_for_offset(first + offset)?;
+            return Err(Error::FlashNotErased {
+                block: used.block,
+                cluster: used.cluster,
+            });
+        }
+
+        let mut padded = data.to_vec();
+        padded.resize(geometry.range_words(&range)?, 0xffff);
+        // The firmware writes from the begin address on; the read end is
+        // set to match so the range stays well-formed.
+        self.with_config_override(
+            |config| config.set_flash_read_range(range),
+            |board| {
+                board.activate(BoardMode::FlashWrite)?;
+                let mut sent = 0;
+                let written = padded.chunks_mut(chunk_words).try_for_each(|chunk| {
+                    board.check_cancelled("flash_write")?;
+                    board.encrypt_words(chunk);
+                    board.fifo_write_words(chunk)?;
+                    sent += chunk.len();
+                    progress.report(ProgramPhase::Uploading, sent.min(data.len()))
+                });
+                let leave = board.command_active();
+                written?;
+                leave?;
+                board.refresh_config().map(|_| ())
+            },
+        )?;
+
+        progress.report(ProgramPhase::Verifying, data.len())?;
+        let stored = self.flash_read(range)?;
+        match stored
+            .iter()
+            .zip(data)
+            .position(|(stored, data)| stored != data)
+        {
+            Some(offset) => {
+                let failed = geometry.address_for_offset(first + offset)?;
+                Err(Error::FlashWriteFailed {
+                    block: failed.block,
+                    cluster: failed.cluster,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Transport> Board<T> {
+    /// Stores `bytes` in user data slot `slot`, in the last
+    /// [`USER_DATA_SLOTS`] blocks of flash.
+    ///
+    /// A slot holds a header with the length and a CRC-32, then the bytes
+    /// packed two to a little-endian word; it has room for one flash block
+    /// less the header.
+    ///
+    /// A slot can be written once. There is no erase command, so storing
+    /// into a slot whose header is already written fails with
+    /// [`Error::UserDataSlotUsed`] before anything is written; only the
+    /// vendor tool can erase it again.
+    pub fn store_user_data(&mut self, slot: u8, bytes: &[u8]) -> Result<()> {
+        self.ensure_ready()?;
+        let geometry = self.config().flash_geometry();
+        let start = user_data_slot(&geometry, slot)?;
+        let payload: Vec<u16> = bytes
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0xff)]))
+            .collect();
+        let available = geometry.block_words();
+        if USER_DATA_HEADER_WORDS + payload.len() > available {
+            return Err(Error::FlashCapacityExceeded {
+                words: USER_DATA_HEADER_WORDS + payload.len(),
+                available,
+            });
+        }
+        let header = self.flash_read(FlashRange { start, end: start })?;
+        if header
+            .iter()
+            .take(USER_DATA_HEADER_WORDS)
+            .any(|&word| word != 0xffff)
+        {
+            return Err(Error::UserDataSlotUsed { slot });
+        }
+
+        let len = bytes.len() as u32;
+        let crc = bitstream_crc32(&payload);
+        let mut words = vec![
+            USER_DATA_MAGIC,
+            len as u16,
+            (len >> 16) as u16,
+            crc as u16,
+            (crc >> 16) as u16,
+        ];
+        words.extend_from_slice(&payload);
+        self.flash_write(start, &words)
+    }
+
+    /// Reads back what [`Board::store_user_data`] stored in `slot`, or
+    /// `None` when the slot is erased. A damaged slot fails with
+    /// [`Error::CorruptUserData`].
+    pub fn load_user_data(&mut self, slot: u8) -> Result<Option<Vec<u8>>> {
+        self.ensure_ready()?;
+        let geometry = self.config().flash_geometry();
+        let start = user_data_slot(&geometry, slot)?;
+        let mut words = self.flash_read(FlashRange { start, end: start })?;
+        match words.first() {
+            Some(&0xffff) => return Ok(None),
+            Some(&USER_DATA_MAGIC) if words.len() >= USER_DATA_HEADER_WORDS => {}
+            _ => return Err(Error::CorruptUserData { slot }),
+        }
+
+        let len = usize::from(words[1]) | usize::from(words[2]) << 16;
+        let total = USER_DATA_HEADER_WORDS + len.div_ceil(2);
+        if total > geometry.block_words() {
+            return Err(Error::CorruptUserData { slot });
+        }
+        if total > words.len() {
+            words = self.flash_read(geometry.range_for_len(start, total)?)?;
+        }
+        let payload = &words[USER_DATA_HEADER_WORDS..total];
+        let crc = u32::from(words[3]) | u32::from(words[4]) << 16;
+        if bitstream_crc32(payload) != crc {
+            return Err(Error::CorruptUserData { slot });
+        }
+        let mut bytes: Vec<u8> = payload.iter().flat_map(|word| word.to_le_bytes()).collect();
+        bytes.truncate(len);
+        Ok(Some(bytes))
+    }
+}
+
+/// Words a bitstream stored from block 0 may take: every block below the
+/// user data slots, or all of flash when it is too small to have them.
+pub(crate) fn bitstream_flash_words(geometry: &FlashGeometry) -> usize {
+    let blocks = match geometry
+        .total_blocks
+        .checked_sub(u16::from(USER_DATA_SLOTS))
+    {
+        Some(blocks) if blocks > 0 => blocks,
+        _ => geometry.total_blocks,
+    };
+    usize::from(blocks) * geometry.block_words()
+}
+
+/// First cluster of user data slot `slot`.
+fn user_data_slot(geometry: &FlashGeometry, slot: u8) -> Result<FlashAddress> {
+    if slot >= USER_DATA_SLOTS {
+        return Err(Error::InvalidUserDataSlot {
+            slot,
+            slots: USER_DATA_SLOTS,
+        });
+    }
+    // Keep at least one block below the slots for a bitstream.
+    if geometry.total_blocks <= u16::from(USER_DATA_SLOTS) {
+        return Err(Error::FeatureUnavailable("flash user data"));
+    }
+    Ok(FlashAddress {
+        block: geometry.total_blocks - u16::from(USER_DATA_SLOTS - slot),
+        cluster: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, Error, FlashAddress, FlashRange};
+
+    fn range(start: (u16, u16), end: (u16, u16)) -> FlashRange {
+        FlashRange {
+            start: FlashAddress {
+                block: start.0,
+                cluster: start.1,
+            },
+            end: FlashAddress {
+                block: end.0,
+                cluster: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn flash_reads_return_the_addressed_clusters() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        let cluster = usize::from(geometry.cluster_size);
+        let block = usize::from(geometry.block_size) * cluster;
+        let contents: Vec<u16> = (0..usize::from(geometry.total_blocks) * block)
+            .map(|index| index as u16 ^ 0x5a5a)
+            .collect();
+        board.mock().set_flash_words(0, &contents);
+
+        let words = board.flash_read(range((1, 2), (2, 0))).unwrap();
+        let start = block + 2 * cluster;
+        assert_eq!(words, contents[start..2 * block + cluster]);
+
+        let last = geometry.total_blocks - 1;
+        let all = board
+            .flash_read(range((0, 0), (last, geometry.block_size - 1)))
+            .unwrap();
+        assert_eq!(all, contents);
+
+        // The addressing words are restored and the board is idle again.
+        assert_eq!(board.config().flash_read_end_block_addr(), 0);
+        assert_eq!(
+            board.refresh_config().unwrap().flash_read_end_block_addr(),
+            0
+        );
+        assert_eq!(board.mode(), crate::BoardMode::Control);
+    }
+
+    #[test]
+    fn out_of_range_flash_reads_are_rejected_up_front() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        for bad in [
+            range((0, 0), (geometry.total_blocks, 0)),
+            range((0, geometry.block_size), (1, 0)),
+        ] {
+            assert!(matches!(
+                board.flash_read(bad),
+                Err(Error::FlashAddressOutOfRange { .. })
+            ));
+        }
+        assert!(matches!(
+            board.flash_read(range((2, 0), (1, 0))),
+            Err(Error::InvalidConfig { .. })
+        ));
+        assert!(board.flash_read(range((0, 0), (0, 0))).is_ok());
+    }
+
+    #[test]
+    fn flash_writes_land_at_the_start_address() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        let cluster = usize::from(geometry.cluster_size);
+        let start = FlashAddress {
+            block: 2,
+            cluster: geometry.block_size - 1,
+        };
+        // Crosses a block boundary and ends mid-cluster.
+        let data: Vec<u16> = (0..2 * cluster as u16 + 5).collect();
+        board.flash_write(start, &data).unwrap();
+        assert_eq!(board.mode(), crate::BoardMode::Control);
+        assert_eq!(board.config().flash_begin_block_addr(), 0);
+
+        let stored = board.flash_read(range((2, 3), (3, 2))).unwrap();
+        assert_eq!(stored[..data.len()], data);
+        assert!(stored[data.len()..].iter().all(|&word| word == 0xffff));
+        let before = board.flash_read(range((2, 2), (2, 2))).unwrap();
+        assert!(before.iter().all(|&word| word == 0xffff));
+    }
+
+    #[test]
+    fn flash_writes_check_capacity_and_erasure() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        let last = FlashAddress {
+            block: geometry.total_blocks - 1,
+            cluster: geometry.block_size - 1,
+        };
+        let cluster = usize::from(geometry.cluster_size);
+        assert!(matches!(
+            board.flash_write(last, &vec![0; cluster + 1]),
+            Err(Error::FlashCapacityExceeded { words, available })
+                if words == cluster + 1 && available == cluster
+        ));
+        board.flash_write(last, &vec![0x00ff; cluster]).unwrap();
+
+        // Rewriting without an erase is refused before anything is sent.
+        assert!(matches!(
+            board.flash_write(last, &vec![0xff00; cluster]),
+            Err(Error::FlashNotErased { block, cluster })
+                if block == last.block && cluster == last.cluster
+        ));
+        let range = FlashRange {
+            start: last,
+            end: last,
+        };
+        assert_eq!(board.flash_read(range).unwrap(), vec![0x00ff; cluster]);
+
+        // Words past the data in a padded cluster may already be in use.
+        let mut tail = vec![0xffff; cluster];
+        tail[cluster - 1] = 0x1234;
+        let start = FlashAddress {
+            block: 1,
+            cluster: 0,
+        };
+        board.mock().set_flash_words(geometry.block_words(), &tail);
+        board.flash_write(start, &[7, 8]).unwrap();
+        tail[..2].copy_from_slice(&[7, 8]);
+        assert_eq!(
+            board.flash_read(FlashRange { start, end: start }).unwrap(),
+            tail
+        );
+    }
+
+    #[test]
+    fn user_data_round_trips_through_its_slot() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        assert_eq!(board.load_user_data(0).unwrap(), None);
+
+        // Spans several clusters and has an odd length.
+        let table: Vec<u8> = (0..301u16).map(|byte| byte as u8).collect();
+        board.store_user_data(0, &table).unwrap();
+        board.store_user_data(3, b"fixture-17").unwrap();
+        assert_eq!(board.load_user_data(0).unwrap(), Some(table));
+        assert_eq!(
+            board.load_user_data(3).unwrap().as_deref(),
+            Some(&b"fixture-17"[..])
+        );
+        assert_eq!(board.load_user_data(1).unwrap(), None);
+
+        // Slots sit in the last blocks, clear of a bitstream at block 0.
+        let first_slot = usize::from(geometry.total_blocks - u16::from(super::USER_DATA_SLOTS))
+            * geometry.block_words();
+        board.mock().set_flash_words(first_slot + 7, &[0]);
+        assert!(matches!(
+            board.load_user_data(0),
+            Err(Error::CorruptUserData { slot: 0 })
+        ));
+    }
+
+    #[test]
+    fn user_data_slots_are_bounded() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        assert!(matches!(
+            board.store_user_data(super::USER_DATA_SLOTS, b"x"),
+            Err(Error::InvalidUserDataSlot { slot: 4, slots: 4 })
+        ));
+        let room = (geometry.block_words() - super::USER_DATA_HEADER_WORDS) * 2;
+        assert!(matches!(
+            board.store_user_data(2, &vec![0; room + 1]),
+            Err(Error::FlashCapacityExceeded { .. })
+        ));
+        board.store_user_data(2, &vec![0x42; room]).unwrap();
+        assert_eq!(board.load_user_data(2).unwrap(), Some(vec![0x42; room]));
+    }
+
+    #[test]
+    fn user_data_slots_are_write_once() {
+        let mut board = Board::open_mock().unwrap();
+        board.store_user_data(1, b"first").unwrap();
+        board.mock().take_commands();
+        assert!(matches!(
+            board.store_user_data(1, b"second"),
+            Err(Error::UserDataSlotUsed { slot: 1 })
+        ));
+        assert!(
+            !board
+                .mock()
+                .take_commands()
+                .contains(&crate::Command::ActivateFlashWrite)
+        );
+        assert_eq!(
+            board.load_user_data(1).unwrap().as_deref(),
+            Some(&b"first"[..])
+        );
+    }
+}
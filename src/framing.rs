@@ -0,0 +1,449 @@
+//! Optional CRC framing on top of VeriComm transfers.
+//!
+//! Every frame on the wire uses the same layout in both directions:
+//!
+//! | word            | contents                                           |
+//! |-----------------|----------------------------------------------------|
+//! | `0`             | [`FRAME_MAGIC`] (`0xa55a`)                         |
+//! | `1 ..= n`       | payload words                                      |
+//! | `n + 1`         | sequence number (only with `seq_numbers`)          |
+//! | last            | CRC16 over every preceding frame word (with a CRC) |
+//! | padding         | zero words up to the next multiple of 4 words      |
+//!
+//! The CRC is CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value
+//! `0xffff`, no reflection, no final XOR). Each 16-bit word is fed into the
+//! CRC most-significant bit first, which is the same as feeding the high byte
+//! followed by the low byte. Sequence numbers start at `0` when a
+//! [`FramedIo`] is created and wrap at `0xffff`; the FPGA is expected to echo
+//! its own counter with the same rules.
+//!
+//! Padding is required because VeriComm transfers must be a multiple of
+//! 8 bytes. Padding words are ignored by the decoder.
+
+use crate::error::{Error, Result};
+use crate::session::IoSession;
+use crate::transport::Transport;
+use crate::usb::UsbDevice;
+use std::collections::VecDeque;
+
+pub const FRAME_MAGIC: u16 = 0xa55a;
+
+const FRAME_ALIGN_WORDS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Crc16Mode {
+    None,
+    #[default]
+    CcittFalse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FramingOptions {
+    pub crc: Crc16Mode,
+    pub seq_numbers: bool,
+}
+
+impl FramingOptions {
+    /// Number of header and trailer words added around each payload.
+    pub fn overhead_words(&self) -> usize {
+        1 + usize::from(self.seq_numbers) + usize::from(self.crc != Crc16Mode::None)
+    }
+
+    /// Length of a frame on the wire, including alignment padding.
+    pub fn frame_words(&self, payload_words: usize) -> usize {
+        self.body_words(payload_words)
+            .next_multiple_of(FRAME_ALIGN_WORDS)
+    }
+
+    fn body_words(&self, payload_words: usize) -> usize {
+        payload_words + self.overhead_words()
+    }
+}
+
+pub fn crc16_ccitt_false(words: &[u16]) -> u16 {
+    let mut crc = 0xffffu16;
+    for word in words {
+        crc ^= word;
+        for _ in 0..16 {
+            // Process the word MSB first; shifting the whole word through the
+            // register is equivalent to feeding the high then the low byte.
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Encodes `payload` into `out`, replacing its previous contents.
+pub fn encode_frame(options: &FramingOptions, payload: &[u16], seq: u16, out: &mut Vec<u16>) {
+    out.clear();
+    out.push(FRAME_MAGIC);
+    out.extend_from_slice(payload);
+    if options.seq_numbers {
+        out.push(seq);
+    }
+    if options.crc == Crc16Mode::CcittFalse {
+        let crc = crc16_ccitt_false(out);
+        out.push(crc);
+    }
+    out.resize(options.frame_words(payload.len()), 0);
+}
+
+/// Streaming frame decoder with resynchronisation.
+///
+/// Words pushed into the decoder are treated as one continuous stream. When
+/// a frame fails validation its magic word is discarded and decoding resumes
+/// at the next [`FRAME_MAGIC`], so a corrupted frame costs at most the words
+/// up to the next intact header.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    options: FramingOptions,
+    payload_words: usize,
+    pending: VecDeque<u16>,
+    frame_index: u64,
+    expected_seq: u16,
+    skipped_words: u64,
+}
+
+impl FrameDecoder {
+    pub fn new(options: FramingOptions, payload_words: usize) -> Self {
+        Self {
+            options,
+            payload_words,
+            pending: VecDeque::new(),
+            frame_index: 0,
+            expected_seq: 0,
+            skipped_words: 0,
+        }
+    }
+
+    pub fn push(&mut self, words: &[u16]) {
+        self.pending.extend(words.iter().copied());
+    }
+
+    /// Discards every buffered word, counting them as skipped.
+    ///
+    /// For streams cut into whole frames, so that what is left of a
+    /// rejected frame is not decoded together with the next one.
+    pub fn clear(&mut self) {
+        self.skipped_words += self.pending.len() as u64;
+        self.pending.clear();
+    }
+
+    /// Number of frames decoded so far, including rejected ones.
+    pub fn frames_seen(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Number of words discarded while searching for a frame header.
+    pub fn skipped_words(&self) -> u64 {
+        self.skipped_words
+    }
+
+    /// Decodes the next frame into `payload`.
+    ///
+    /// Returns `None` when no complete frame is buffered yet.
+    pub fn next_frame(&mut self, payload: &mut [u16]) -> Option<Result<()>> {
+        if let Err(err) = validate_payload_len(self.payload_words, payload.len()) {
+            return Some(Err(err));
+        }
+
+        while self
+            .pending
+            .front()
+            .is_some_and(|word| *word != FRAME_MAGIC)
+        {
+            self.pending.pop_front();
+            self.skipped_words += 1;
+        }
+
+        let body_words = self.options.body_words(self.payload_words);
+        if self.pending.len() < body_words {
+            return None;
+        }
+
+        let body = self.pending.make_contiguous();
+        let frame_index = self.frame_index;
+        self.frame_index += 1;
+
+        if self.options.crc == Crc16Mode::CcittFalse {
+            let expected = crc16_ccitt_false(&body[..body_words - 1]);
+            if body[body_words - 1] != expected {
+                self.pending.pop_front();
+                self.skipped_words += 1;
+                return Some(Err(Error::FrameCrcMismatch { frame_index }));
+            }
+        }
+
+        if self.options.seq_numbers {
+            let seq = body[1 + self.payload_words];
+            if seq != self.expected_seq {
+                let expected = self.expected_seq;
+                self.expected_seq = seq.wrapping_add(1);
+                self.pending.drain(..body_words);
+                return Some(Err(Error::FrameSequenceMismatch {
+                    frame_index,
+                    expected,
+                    actual: seq,
+                }));
+            }
+            self.expected_seq = seq.wrapping_add(1);
+        }
+
+        payload.copy_from_slice(&body[1..=self.payload_words]);
+        self.pending.drain(..body_words);
+        Some(Ok(()))
+    }
+}
+
+/// Frames every transfer of an [`IoSession`] with a header, optional
+/// sequence number and CRC. See the module documentation for the wire layout.
+///
+/// Each [`transfer`](Self::transfer) carries exactly one frame over
+/// [`IoSession::transfer`], so a frame must fit the FIFO. Chunked and
+/// streamed transfers ([`IoSession::transfer_chunked`],
+/// [`IoSession::stream_read`]) are not framed.
+pub struct FramedIo<'io, 'board, T: Transport = UsbDevice> {
+    io: &'io mut IoSession<'board, T>,
+    options: FramingOptions,
+    tx_seq: u16,
+    decoder: Option<FrameDecoder>,
+    tx_frame: Vec<u16>,
+    rx_frame: Vec<u16>,
+}
+
+impl<'io, 'board, T: Transport> FramedIo<'io, 'board, T> {
+    pub fn new(io: &'io mut IoSession<'board, T>, options: FramingOptions) -> Self {
+        Self {
+            io,
+            options,
+            tx_seq: 0,
+            decoder: None,
+            tx_frame: Vec::new(),
+            rx_frame: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &FramingOptions {
+        &self.options
+    }
+
+    /// Sends `tx` as one frame and strips the frame received in exchange.
+    ///
+    /// All transfers on one `FramedIo` must use the same payload length.
+    /// A reply that fails its CRC is dropped whole, and its sequence number
+    /// counts as used, so the next call decodes only its own reply.
+    pub fn transfer(&mut self, tx: &[u16], rx: &mut [u16]) -> Result<()> {
+        if tx.len() != rx.len() {
+            return Err(Error::InvalidBufferLength {
+                context: "framed transfer",
+                expected: tx.len(),
+                actual: rx.len(),
+            });
+        }
+        let decoder = self
+            .decoder
+            .get_or_insert_with(|| FrameDecoder::new(self.options, tx.len()));
+        validate_payload_len(decoder.payload_words, tx.len())?;
+
+        encode_frame(&self.options, tx, self.tx_seq, &mut self.tx_frame);
+        self.tx_seq = self.tx_seq.wrapping_add(1);
+        self.rx_frame.clear();
+        self.rx_frame.resize(self.tx_frame.len(), 0);
+        self.io.transfer(&self.tx_frame, &mut self.rx_frame)?;
+
+        decoder.clear();
+        decoder.push(&self.rx_frame);
+        let result = decoder
+            .next_frame(rx)
+            .unwrap_or(Err(Error::UnexpectedResponse(
+                "framed transfer returned no complete frame",
+            )));
+        if matches!(result, Err(Error::FrameCrcMismatch { .. })) {
+            decoder.expected_seq = decoder.expected_seq.wrapping_add(1);
+        }
+        result
+    }
+}
+
+fn validate_payload_len(expected: usize, actual: usize) -> Result<()> {
+    if expected != actual {
+        return Err(Error::InvalidBufferLength {
+            context: "framed payload",
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Crc16Mode, FRAME_MAGIC, FrameDecoder, FramedIo, FramingOptions, crc16_ccitt_false,
+        encode_frame,
+    };
+    use crate::mock::MockTransport;
+    use crate::{Board, Error, IoConfig};
+
+    const CRC_AND_SEQ: FramingOptions = FramingOptions {
+        crc: Crc16Mode::CcittFalse,
+        seq_numbers: true,
+    };
+
+    #[test]
+    fn crc_matches_ccitt_false_reference_values() {
+        assert_eq!(crc16_ccitt_false(&[]), 0xffff);
+        // "12345678" packed big-endian into words.
+        assert_eq!(crc16_ccitt_false(&[0x3132, 0x3334, 0x3536, 0x3738]), 0xa12b);
+        assert_eq!(crc16_ccitt_false(&[FRAME_MAGIC, 0x1234, 0xabcd]), 0xd96a);
+    }
+
+    #[test]
+    fn frame_lengths_are_padded_to_vericomm_packets() {
+        let plain = FramingOptions {
+            crc: Crc16Mode::None,
+            seq_numbers: false,
+        };
+        assert_eq!(plain.frame_words(3), 4);
+        assert_eq!(FramingOptions::default().frame_words(2), 4);
+        assert_eq!(CRC_AND_SEQ.frame_words(2), 8);
+        assert_eq!(CRC_AND_SEQ.frame_words(5), 8);
+    }
+
+    #[test]
+    fn encoded_frame_layout_is_stable() {
+        let mut frame = Vec::new();
+        encode_frame(&CRC_AND_SEQ, &[0x1234, 0xabcd], 0, &mut frame);
+        assert_eq!(
+            frame,
+            vec![FRAME_MAGIC, 0x1234, 0xabcd, 0x0000, 0x6253, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn decoder_roundtrips_every_option_combination() {
+        for crc in [Crc16Mode::None, Crc16Mode::CcittFalse] {
+            for seq_numbers in [false, true] {
+                let options = FramingOptions { crc, seq_numbers };
+                let mut decoder = FrameDecoder::new(options, 3);
+                let mut frame = Vec::new();
+                for seq in 0..4u16 {
+                    encode_frame(&options, &[seq, !seq, FRAME_MAGIC], seq, &mut frame);
+                    decoder.push(&frame);
+                }
+                let mut out = [0u16; 3];
+                for seq in 0..4u16 {
+                    decoder.next_frame(&mut out).unwrap().unwrap();
+                    assert_eq!(out, [seq, !seq, FRAME_MAGIC]);
+                }
+                assert!(decoder.next_frame(&mut out).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_reports_crc_mismatch_and_resyncs_on_next_header() {
+        let mut decoder = FrameDecoder::new(CRC_AND_SEQ, 2);
+        let mut frame = Vec::new();
+        encode_frame(&CRC_AND_SEQ, &[1, 2], 0, &mut frame);
+        frame[1] ^= 0x0100;
+        decoder.push(&[0xdead, 0xbeef]);
+        decoder.push(&frame);
+        encode_frame(&CRC_AND_SEQ, &[3, 4], 1, &mut frame);
+        decoder.push(&frame);
+
+        let mut out = [0u16; 2];
+        match decoder.next_frame(&mut out) {
+            Some(Err(Error::FrameCrcMismatch { frame_index })) => assert_eq!(frame_index, 0),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        // Sequence number 0 was lost with the corrupted frame.
+        match decoder.next_frame(&mut out) {
+            Some(Err(Error::FrameSequenceMismatch {
+                frame_index,
+                expected,
+                actual,
+            })) => {
+                assert_eq!((frame_index, expected, actual), (1, 0, 1));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(decoder.skipped_words() >= 3);
+        assert!(decoder.next_frame(&mut out).is_none());
+    }
+
+    #[test]
+    fn decoder_waits_for_partial_frames() {
+        let mut decoder = FrameDecoder::new(CRC_AND_SEQ, 2);
+        let mut frame = Vec::new();
+        encode_frame(&CRC_AND_SEQ, &[7, 8], 0, &mut frame);
+        let mut out = [0u16; 2];
+        decoder.push(&frame[..3]);
+        assert!(decoder.next_frame(&mut out).is_none());
+        decoder.push(&frame[3..]);
+        decoder.next_frame(&mut out).unwrap().unwrap();
+        assert_eq!(out, [7, 8]);
+    }
+
+    #[test]
+    fn framed_transfers_round_trip_through_a_looped_back_design() {
+        let mut board = Board::open_mock().unwrap();
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let mut framed = FramedIo::new(&mut io, CRC_AND_SEQ);
+        let mut rx = [0u16; 3];
+        for seq in 0..3u16 {
+            framed.transfer(&[seq, FRAME_MAGIC, !seq], &mut rx).unwrap();
+            assert_eq!(rx, [seq, FRAME_MAGIC, !seq]);
+        }
+        assert!(matches!(
+            framed.transfer(&[1, 2], &mut [0; 2]),
+            Err(Error::InvalidBufferLength { .. })
+        ));
+        io.finish().unwrap();
+    }
+
+    #[test]
+    fn a_corrupted_reply_does_not_leak_into_the_next_transfer() {
+        // Corrupts the second reply's payload; everything else loops back.
+        let mut replies = 0;
+        let transport = MockTransport::with_design(move |words| {
+            replies += 1;
+            if replies == 2 {
+                words[2] ^= 0x0100;
+            }
+        });
+        let mut board = Board::from_transport(transport).unwrap();
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let mut framed = FramedIo::new(&mut io, CRC_AND_SEQ);
+
+        let mut rx = [0u16; 4];
+        framed
+            .transfer(&[FRAME_MAGIC, 1, FRAME_MAGIC, 1], &mut rx)
+            .unwrap();
+        assert_eq!(rx, [FRAME_MAGIC, 1, FRAME_MAGIC, 1]);
+        assert!(matches!(
+            framed.transfer(&[FRAME_MAGIC, 2, FRAME_MAGIC, 2], &mut rx),
+            Err(Error::FrameCrcMismatch { frame_index: 1 })
+        ));
+        for value in 3..5 {
+            let tx = [FRAME_MAGIC, value, FRAME_MAGIC, value];
+            framed.transfer(&tx, &mut rx).unwrap();
+            assert_eq!(rx, tx);
+        }
+        io.finish().unwrap();
+    }
+
+    #[test]
+    fn decoder_rejects_wrong_payload_buffer() {
+        let mut decoder = FrameDecoder::new(FramingOptions::default(), 2);
+        let mut out = [0u16; 3];
+        assert!(matches!(
+            decoder.next_frame(&mut out),
+            Some(Err(Error::InvalidBufferLength { .. }))
+        ));
+    }
+}
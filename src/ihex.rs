@@ -0,0 +1,229 @@
+use crate::error::{Error, Result};
+use crate::program::line_error;
+
+/// Decoder for Intel HEX bitfiles, fed one line at a time.
+///
+/// Data records must describe one contiguous byte range in file order; a
+/// record that starts past the end of the previous one is a gap and one
+/// that starts before it an overlap. The bytes are the FIFO byte stream,
+/// so consecutive pairs become little-endian words.
+#[derive(Debug, Default)]
+pub(crate) struct IntelHexDecoder {
+    /// Upper address bits set by the last type 02 or 04 record.
+    base: u32,
+    /// Address the next data byte must have.
+    next: Option<u32>,
+    /// First byte of a word split across two records.
+    odd: Option<u8>,
+    ended: bool,
+}
+
+impl IntelHexDecoder {
+    pub(crate) fn parse_line(
+        &mut self,
+        line: &str,
+        line_number: usize,
+        out: &mut Vec<u16>,
+    ) -> Result<()> {
+        let record = line.trim();
+        if record.is_empty() {
+            return Ok(());
+        }
+        // Column of the character at `index` into the record's hex digits.
+        let offset = line.len() - line.trim_start().len();
+        let at = |index: usize, reason| line_error(line_number, offset + 1 + index, record, reason);
+
+        if self.ended {
+            return Err(line_error(
+                line_number,
+                offset,
+                record,
+                "record after end-of-file record",
+            ));
+        }
+        let Some(digits) = record.strip_prefix(':') else {
+            return Err(line_error(
+                line_number,
+                offset,
+                record,
+                "record does not start with ':'",
+            ));
+        };
+        if let Some(bad) = digits.find(|c: char| !c.is_ascii_hexdigit()) {
+            return Err(at(bad, "record contains non-hexadecimal characters"));
+        }
+        if digits.len() < 10 || digits.len() % 2 != 0 {
+            return Err(at(digits.len(), "truncated record"));
+        }
+
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&digits[index..index + 2], 16))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .expect("checked hex digits");
+        let len = usize::from(bytes[0]);
+        if bytes.len() != len + 5 {
+            return Err(at(0, "record length does not match its byte count"));
+        }
+        if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(at(digits.len() - 2, "record checksum mismatch"));
+        }
+
+        let address = u32::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+        let data = &bytes[4..4 + len];
+        match bytes[3] {
+            0x00 => {
+                let start = self.base.wrapping_add(address);
+                match self.next {
+                    Some(next) if start > next => return Err(at(2, "gap before data record")),
+                    Some(next) if start < next => {
+                        return Err(at(2, "data record overlaps previous data"));
+                    }
+                    _ => {}
+                }
+                self.next = Some(start.wrapping_add(len as u32));
+                for &byte in data {
+                    match self.odd.take() {
+                        Some(low) => out.push(u16::from_le_bytes([low, byte])),
+                        None => self.odd = Some(byte),
+                    }
+                }
+            }
+            0x01 => self.ended = true,
+            0x02 | 0x04 => {
+                let [high, low] = data else {
+                    return Err(at(0, "address record must carry two bytes"));
+                };
+                let upper = u32::from(u16::from_be_bytes([*high, *low]));
+                self.base = if bytes[3] == 0x02 {
+                    upper << 4
+                } else {
+                    upper << 16
+                };
+            }
+            // Start addresses mean nothing to the programmer.
+            0x03 | 0x05 => {}
+            _ => return Err(at(6, "unsupported record type")),
+        }
+        Ok(())
+    }
+
+    /// Checks that the file ended cleanly.
+    pub(crate) fn finish(&self) -> Result<()> {
+        if !self.ended {
+            return Err(Error::InvalidBitfile(
+                "Intel HEX file has no end-of-file record",
+            ));
+        }
+        if self.odd.is_some() {
+            return Err(Error::InvalidBitfile(
+                "Intel HEX data has an odd number of bytes",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BitfileFormat, BitfileReader, Error, load_bitfile_from_reader, parse_bitfile};
+    use std::io::Cursor;
+
+    fn record(kind: u8, address: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8];
+        bytes.extend_from_slice(&address.to_be_bytes());
+        bytes.push(kind);
+        bytes.extend_from_slice(data);
+        let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        bytes.push(sum.wrapping_neg());
+        let digits: String = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+        format!(":{digits}\n")
+    }
+
+    fn located(data: &str) -> (usize, &'static str) {
+        match load_bitfile_from_reader(Cursor::new(data)).unwrap_err() {
+            Error::InvalidBitfileLine { line, reason, .. } => (line, reason),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn records_assemble_into_little_endian_words() {
+        let data = [
+            record(0x04, 0, &[0x00, 0x01]),
+            record(0x00, 0x0000, &[0x34, 0x12, 0xcd]),
+            record(0x00, 0x0003, &[0xab]),
+            record(0x01, 0, &[]),
+        ]
+        .concat();
+        assert_eq!(parse_bitfile(data.as_bytes()).unwrap(), [0x1234, 0xabcd]);
+
+        let mut reader = BitfileReader::new(Cursor::new(&data));
+        reader.next_chunk().unwrap();
+        assert_eq!(reader.format(), Some(BitfileFormat::IntelHex));
+    }
+
+    #[test]
+    fn malformed_records_report_their_line() {
+        let eof = record(0x01, 0, &[]);
+        let mut corrupt = record(0x00, 0, &[0x34, 0x12]);
+        corrupt.replace_range(9..11, "35");
+        assert_eq!(
+            located(&[record(0x00, 0, &[1, 2]), corrupt, eof.clone()].concat()),
+            (2, "record checksum mismatch")
+        );
+        assert_eq!(
+            located(
+                &[
+                    record(0x00, 0, &[1, 2]),
+                    record(0x00, 4, &[3, 4]),
+                    eof.clone()
+                ]
+                .concat()
+            ),
+            (2, "gap before data record")
+        );
+        assert_eq!(
+            located(
+                &[
+                    record(0x00, 0, &[1, 2]),
+                    record(0x00, 1, &[3, 4]),
+                    eof.clone()
+                ]
+                .concat()
+            ),
+            (2, "data record overlaps previous data")
+        );
+        assert_eq!(
+            located(&[eof.clone(), record(0x00, 0, &[1, 2])].concat()),
+            (2, "record after end-of-file record")
+        );
+    }
+
+    #[test]
+    fn incomplete_files_are_rejected() {
+        assert!(matches!(
+            load_bitfile_from_reader(Cursor::new(record(0x00, 0, &[1, 2]))),
+            Err(Error::InvalidBitfile(
+                "Intel HEX file has no end-of-file record"
+            ))
+        ));
+        let odd = [record(0x00, 0, &[1, 2, 3]), record(0x01, 0, &[])].concat();
+        assert!(matches!(
+            load_bitfile_from_reader(Cursor::new(odd)),
+            Err(Error::InvalidBitfile(
+                "Intel HEX data has an odd number of bytes"
+            ))
+        ));
+    }
+
+    #[test]
+    fn forced_format_overrides_detection() {
+        let data = [record(0x00, 0, &[0x34, 0x12]), record(0x01, 0, &[])].concat();
+        let mut text = BitfileReader::with_format(Cursor::new(&data), BitfileFormat::SmimsText);
+        assert!(matches!(
+            text.next_chunk(),
+            Err(Error::InvalidBitfileLine { line: 1, .. })
+        ));
+    }
+}
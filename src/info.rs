@@ -0,0 +1,70 @@
+use crate::config::{Abilities, Config, FlashGeometry, SmimsVersion};
+use std::fmt;
+
+/// Static facts about a board, gathered from its configuration block.
+///
+/// The `Display` output is a short multi-line summary meant for startup
+/// logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub version: SmimsVersion,
+    pub fifo_size_words: u16,
+    pub flash: FlashGeometry,
+    pub abilities: Abilities,
+}
+
+impl DeviceInfo {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            version: config.smims_version(),
+            fifo_size_words: config.fifo_size_words(),
+            flash: config.flash_geometry(),
+            abilities: config.abilities(),
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SMIMS version: {}", self.version)?;
+        writeln!(f, "FIFO depth:    {} words", self.fifo_size_words)?;
+        writeln!(
+            f,
+            "Flash:         {} blocks x {} clusters x {} words ({} bytes)",
+            self.flash.total_blocks,
+            self.flash.block_size,
+            self.flash.cluster_size,
+            self.flash.capacity_bytes()
+        )?;
+        write!(f, "Abilities:     {}", self.abilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceInfo;
+    use crate::Config;
+    use crate::config::word;
+
+    #[test]
+    fn info_summarizes_version_fifo_and_flash() {
+        let mut words = [0u16; Config::WORD_COUNT];
+        words[word::SMIMS_VERSION] = 0x0213;
+        words[word::FIFO_SIZE_WORDS] = 1024;
+        words[word::FLASH_TOTAL_BLOCK] = 512;
+        words[word::FLASH_BLOCK_SIZE] = 64;
+        words[word::FLASH_CLUSTER_SIZE] = 256;
+        words[word::ABILITY_FLAGS] = 0x0001;
+        let info = DeviceInfo::from_config(&Config::from_words(words));
+
+        assert_eq!(info.flash.capacity_bytes(), 16 * 1024 * 1024);
+        assert_eq!(
+            info.to_string(),
+            "SMIMS version: 2.1.3\n\
+             FIFO depth:    1024 words\n\
+             Flash:         512 blocks x 64 clusters x 256 words (16777216 bytes)\n\
+             Abilities:     VERICOMM"
+        );
+    }
+}
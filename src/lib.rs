@@ -30,22 +30,102 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Sessions double as the board's states: transfers only exist on an
+//! [`IoSession`], and while one is alive it holds the board's unique
+//! borrow, so programming or reconfiguring mid-session does not compile.
+//! Finishing or dropping the session hands the board back in control mode.
+//!
+//! ```compile_fail
+//! use vlfd_rs::{Board, IoConfig, Result};
+//!
+//! fn main() -> Result<()> {
+//!     let mut board = Board::open()?;
+//!     let mut io = board.configure_io(&IoConfig::default())?;
+//!     let program = board.programmer()?;
+//!     io.transfer(&[0; 4], &mut [0; 4])?;
+//!     Ok(())
+//! }
+//! ```
 
+pub mod bitfile;
 pub mod constants;
 
+#[cfg(feature = "async")]
+mod async_board;
+mod calibration;
+mod cancel;
+mod capture;
+mod channel;
+mod compress;
 mod config;
+mod emergency;
 mod error;
+mod flash;
+mod framing;
+mod ihex;
+mod info;
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+mod pacing;
 mod program;
+mod protocol;
+mod scan;
 mod session;
+mod transform;
+mod transport;
 mod usb;
+mod warm_start;
+mod watcher;
+#[cfg(feature = "wire-log")]
+mod wire_log;
 
-pub use config::Config;
-pub use error::{Error, Result};
-pub use program::{Programmer, load_bitfile, load_bitfile_from_reader};
+#[cfg(feature = "async")]
+pub use async_board::{AsyncBoard, AsyncProgrammer};
+pub use calibration::{ClockCalibration, WORDS_PER_IO_CYCLE};
+pub use cancel::{CancelSource, CancelToken};
+pub use capture::CaptureStamp;
+pub use channel::{
+    ChannelOptions, ChannelTotals, IoReceiver, IoSender, OverflowPolicy, ShutdownMode, TrySendError,
+};
+pub use config::{
+    Abilities, Config, ConfigDiff, FlashAddress, FlashGeometry, FlashRange, LicenceDerivation,
+    LicenceKey, SecurityKey, SmimsVersion,
+};
+pub use emergency::{
+    EMERGENCY_CLAIM_STEP, EMERGENCY_WRITE_TIMEOUT, EmergencyFailure, EmergencyHandle,
+};
+pub use error::{
+    Error, ErrorClass, ErrorKind, ErrorSummary, Result, TransferDirection, UsbErrorKind,
+    UsbTransfer,
+};
+pub use flash::USER_DATA_SLOTS;
+pub use framing::{
+    Crc16Mode, FRAME_MAGIC, FrameDecoder, FramedIo, FramingOptions, crc16_ccitt_false, encode_frame,
+};
+pub use info::DeviceInfo;
+#[cfg(feature = "mock")]
+pub use mock::MockTransport;
+pub use program::{
+    BitfileFormat, BitfileReader, MAX_BITFILE_LINE_BYTES, ProgramOptions, ProgramOutcome,
+    ProgramPhase, ProgramProgress, Programmer, bitstream_crc32, load_bitfile,
+    load_bitfile_from_reader, parse_bitfile, parse_bitfile_parallel,
+};
+pub use protocol::Command;
+pub use scan::{ScanEntry, ScanFailure, ScannedBoard, scan, scan_with_transport};
 pub use session::{
-    Board, BoardMode, IoConfig, IoSession, IoTransferWindow, ProgramSession, TransferStageProfile,
+    Board, BoardMode, DeviceStatus, EncryptionDiagnostics, ErrorCounts, HealthReport, IoConfig,
+    IoConfigBuilder, IoFrame, IoReader, IoSession, IoStream, IoTransferWindow, IoWriter,
+    ProgramSession, RECOVERY_SYNC_TIMEOUT, RecoveryReport, RecoveryStep, SdkConfig, StreamStats,
+    TransferStageProfile, TransferStats,
 };
+pub use transform::{BitReverse, ByteSwap, GrayCode, IoTransform};
+pub use transport::{EndpointOpener, PipelineEndpoint, Transport};
 pub use usb::{
-    HotplugDeviceInfo, HotplugEvent, HotplugEventKind, HotplugOptions, HotplugRegistration, Probe,
-    TransportConfig,
+    DEFAULT_FIXED_SYNC_DELAY, DescriptorInfo, DeviceLocation, DeviceOptions, Endpoint,
+    HOTPLUG_CHANNEL_CAPACITY, HotplugDeviceInfo, HotplugEvent, HotplugEventKind, HotplugOptions,
+    HotplugRegistration, Probe, ReconnectPolicy, SyncPolicy, SyncStrategy, TransportConfig,
 };
+pub use watcher::{BoardEvent, BoardWatcher, WatchOptions};
+#[cfg(feature = "wire-log")]
+pub use wire_log::set_wire_log_hexdump_bytes;
@@ -0,0 +1,828 @@
+use crate::config::{Abilities, Config, word};
+use crate::constants;
+use crate::error::{Error, Result};
+use crate::protocol::Command;
+use crate::session::Board;
+use crate::transport::{EndpointOpener, PipelineEndpoint, Transport};
+use crate::usb::{Endpoint, TransportConfig, bytes_into_words};
+use nusb::transfer::{Buffer, Completion, TransferError};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+const MOCK_FIFO_SIZE_WORDS: u16 = 1024;
+/// Read back from word 31, whatever licence was written over it.
+const MOCK_SECURITY_KEY: u16 = 0x3c5a;
+/// Flash layout: blocks, clusters per block, words per cluster.
+const MOCK_FLASH_GEOMETRY: [u16; 3] = [16, 4, 64];
+
+/// Models the FPGA design behind VeriComm: rewrites each transfer in place
+/// before it is read back.
+type Design = Box<dyn FnMut(&mut [u16]) + Send>;
+
+/// In-memory [`Transport`] that answers like the SMIMS firmware, for tests
+/// and examples without hardware.
+///
+/// It acknowledges every sync poll, serves a fixed encryption table and a
+/// configuration block reporting a programmed FPGA with VeriComm support,
+/// stores configuration writes, accepts bitstreams, and models a 16-block
+/// flash that starts out erased. In VeriComm and VeriSDK mode every FIFO
+/// write is passed through the design (identity by default) and queued for
+/// the following FIFO read.
+///
+/// Reads with no queued data fail with [`Error::TransferTimeout`], as a
+/// real board would; partial reads return the complete words queued,
+/// possibly none. FIFO transfers are blocking unless
+/// [`set_pipelined`](Self::set_pipelined) is on.
+pub struct MockTransport {
+    config: TransportConfig,
+    endpoint_timeouts: [Option<Duration>; Endpoint::COUNT],
+    state: Arc<Mutex<MockState>>,
+}
+
+struct MockState {
+    mode: MockMode,
+    config: Config,
+    /// Decoded key table; host writes use the first half, reads the second.
+    keys: [u16; 32],
+    rx_index: usize,
+    tx_index: usize,
+    encryption: bool,
+    fifo: VecDeque<u8>,
+    /// Licence last written to word 31.
+    licence: u16,
+    /// Licence the design answers under; `None` accepts any.
+    accepted_licence: Option<u16>,
+    /// Every command but sync polls, in the order received.
+    commands: Vec<Command>,
+    flash: Vec<u16>,
+    design: Design,
+    /// Whether FIFO endpoints are offered for pipelining.
+    pipelined: bool,
+    /// Endpoints held by a [`MockPipeline`]. nusb claims endpoints
+    /// exclusively, so other transfers on them fail until it is dropped.
+    claimed: [bool; Endpoint::COUNT],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MockMode {
+    Control,
+    AwaitConfig,
+    Programmer,
+    VeriComm,
+    FlashRead,
+    /// Flash programming; the field is the next word offset to program.
+    FlashWrite(usize),
+}
+
+impl MockTransport {
+    /// A mock whose design echoes every word back.
+    pub fn new() -> Self {
+        Self::with_design(|_| {})
+    }
+
+    /// A mock whose VeriComm replies are produced by `design`, which
+    /// rewrites each transfer in place.
+    pub fn with_design(design: impl FnMut(&mut [u16]) + Send + 'static) -> Self {
+        let mut config = Config::new();
+        let words = config.words_mut();
+        words[word::SMIMS_VERSION] = constants::SMIMS_VERSION;
+        words[word::FIFO_SIZE_WORDS] = MOCK_FIFO_SIZE_WORDS;
+        words[word::ABILITY_FLAGS] = 0x0001;
+        words[word::PROGRAM_STATE] = 0x0001;
+        words[word::LICENCE_AND_SECURITY_KEY] = MOCK_SECURITY_KEY;
+        let [blocks, block_size, cluster_size] = MOCK_FLASH_GEOMETRY;
+        words[word::FLASH_TOTAL_BLOCK] = blocks;
+        words[word::FLASH_BLOCK_SIZE] = block_size;
+        words[word::FLASH_CLUSTER_SIZE] = cluster_size;
+        let flash_words = MOCK_FLASH_GEOMETRY
+            .iter()
+            .map(|&n| usize::from(n))
+            .product();
+
+        let mut keys = [0u16; 32];
+        for (index, key) in keys.iter_mut().enumerate() {
+            *key = 0x9e37u16.rotate_left(index as u32) ^ (index as u16).wrapping_mul(0x0101);
+        }
+
+        Self {
+            config: TransportConfig::default(),
+            endpoint_timeouts: [None; Endpoint::COUNT],
+            state: Arc::new(Mutex::new(MockState {
+                mode: MockMode::Control,
+                config,
+                keys,
+                rx_index: 0,
+                tx_index: 0,
+                encryption: true,
+                fifo: VecDeque::new(),
+                licence: 0,
+                accepted_licence: None,
+                commands: Vec::new(),
+                flash: vec![0xffff; flash_words],
+                design: Box::new(design),
+                pipelined: false,
+                claimed: [false; Endpoint::COUNT],
+            })),
+        }
+    }
+
+    /// Turns the mock's FIFO scrambling on or off. Keep it in step with
+    /// [`Board::set_encryption_enabled`]; the encryption table is still
+    /// served so boards initialize normally.
+    pub fn set_encryption_enabled(&self, enabled: bool) {
+        let mut state = self.state();
+        state.encryption = enabled;
+        state.rx_index = 0;
+        state.tx_index = 0;
+    }
+
+    /// Sets the daughterboard-connected status reported by config reads.
+    pub fn set_pcb_connected(&self, connected: bool) {
+        let mut state = self.state();
+        let status = &mut state.config.words_mut()[word::PROGRAM_STATE];
+        if connected {
+            *status &= !0x0100;
+        } else {
+            *status |= 0x0100;
+        }
+    }
+
+    /// Sets the programming FIFO size reported by later config reads.
+    pub fn set_fifo_size_words(&self, words: u16) {
+        self.state().config.words_mut()[word::FIFO_SIZE_WORDS] = words;
+    }
+
+    /// Sets the ability flags reported by later config reads.
+    pub fn set_abilities(&self, abilities: Abilities) {
+        self.state().config.words_mut()[word::ABILITY_FLAGS] = abilities.bits();
+    }
+
+    /// Takes the commands received since the last call, sync polls left
+    /// out.
+    pub fn take_commands(&self) -> Vec<Command> {
+        std::mem::take(&mut self.state().commands)
+    }
+
+    /// Makes VeriComm and VeriSDK sessions drop every FIFO write unless
+    /// `licence` was written to word 31, as firmware that rejects the
+    /// licence does. `None`, the default, accepts any licence.
+    pub fn set_accepted_licence(&self, licence: Option<u16>) {
+        self.state().accepted_licence = licence;
+    }
+
+    /// Licence key last written to configuration word 31.
+    pub fn licence_key(&self) -> u16 {
+        self.state().licence
+    }
+
+    /// The configuration block the mock currently holds.
+    pub fn config(&self) -> Config {
+        self.state().config.clone()
+    }
+
+    /// Overwrites flash contents starting `offset` words in.
+    pub fn set_flash_words(&self, offset: usize, words: &[u16]) {
+        self.state().flash[offset..offset + words.len()].copy_from_slice(words);
+    }
+
+    /// Offers pipelined FIFO endpoints, so IO sessions queue transfers
+    /// as they do on a USB device instead of falling back to one blocking
+    /// write and read per transfer.
+    pub fn set_pipelined(&self, pipelined: bool) {
+        self.state().pipelined = pipelined;
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        lock(&self.state)
+    }
+
+    fn pipeline_endpoint(&self, endpoint: Endpoint) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        if !self.state().pipelined {
+            return Ok(None);
+        }
+        claim(&self.state, endpoint).map(Some)
+    }
+
+    /// Fails like nusb when `endpoint` is held by a [`MockPipeline`].
+    fn check_unclaimed(&self, endpoint: Endpoint) -> Result<()> {
+        if self.state().claimed[endpoint.index()] {
+            return Err(endpoint_claimed());
+        }
+        Ok(())
+    }
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Opens `endpoint` as a [`MockPipeline`], claiming it until the pipeline
+/// is dropped.
+fn claim(state: &Arc<Mutex<MockState>>, endpoint: Endpoint) -> Result<Box<dyn PipelineEndpoint>> {
+    let claimed = &mut lock(state).claimed[endpoint.index()];
+    if *claimed {
+        return Err(endpoint_claimed());
+    }
+    *claimed = true;
+    Ok(Box::new(MockPipeline {
+        state: Arc::clone(state),
+        endpoint,
+        queue: VecDeque::new(),
+        cancelled: VecDeque::new(),
+    }))
+}
+
+fn endpoint_claimed() -> Error {
+    Error::Busy {
+        current_operation: "mock endpoint claim",
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockState {
+    fn command(&mut self, bytes: &[u8]) -> Result<()> {
+        let command = Command::decode(bytes);
+        if let Some(command) = command.filter(|command| *command != Command::SyncPoll) {
+            self.commands.push(command);
+        }
+        match command {
+            Some(Command::SyncPoll) => {}
+            Some(Command::ResetEngine) => {
+                self.mode = MockMode::Control;
+                self.fifo.clear();
+            }
+            Some(Command::CommandActive) => self.mode = MockMode::Control,
+            Some(Command::ReadConfig) => {
+                let mut words = *self.config.words();
+                self.queue_encrypted(&mut words);
+            }
+            // Activating an engine mode restarts both key streams.
+            Some(Command::ActivateFpgaProgrammer) => {
+                self.mode = MockMode::Programmer;
+                self.rx_index = 0;
+                self.tx_index = 0;
+            }
+            Some(Command::ActivateFlashRead) => {
+                self.mode = MockMode::FlashRead;
+                self.rx_index = 0;
+                self.tx_index = 0;
+                let mut words = self.flash[self.flash_read_range()?].to_vec();
+                self.queue_encrypted(&mut words);
+            }
+            Some(Command::ActivateFlashWrite) => {
+                let [_, block_size, cluster_size] = MOCK_FLASH_GEOMETRY.map(usize::from);
+                let words = self.config.words();
+                let cluster = usize::from(words[word::FLASH_BEGIN_BLOCK]) * block_size
+                    + usize::from(words[word::FLASH_BEGIN_CLUSTER]);
+                self.mode = MockMode::FlashWrite(cluster * cluster_size);
+                self.rx_index = 0;
+                self.tx_index = 0;
+            }
+            Some(Command::ActivateVeriComm | Command::ActivateVeriSdk) => {
+                self.mode = MockMode::VeriComm;
+                self.rx_index = 0;
+                self.tx_index = 0;
+            }
+            Some(Command::ReadEncryptTable) => {
+                let raw = encode_table(&self.keys);
+                self.fifo
+                    .extend(raw.iter().flat_map(|word| word.to_le_bytes()));
+                self.rx_index = 0;
+                self.tx_index = 0;
+            }
+            Some(Command::WriteConfig) => self.mode = MockMode::AwaitConfig,
+            _ => {
+                return Err(Error::UnexpectedResponse(
+                    "mock transport received an unsupported command",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn fifo_write(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() % 2 != 0 {
+            return Err(Error::InvalidBufferLength {
+                context: "mock fifo write",
+                expected: bytes.len() + 1,
+                actual: bytes.len(),
+            });
+        }
+        let mut words: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        self.decrypt(&mut words);
+
+        match self.mode {
+            MockMode::AwaitConfig => {
+                if words.len() != Config::WORD_COUNT {
+                    return Err(Error::InvalidBufferLength {
+                        context: "mock config write",
+                        expected: Config::WORD_COUNT,
+                        actual: words.len(),
+                    });
+                }
+                // Word 31 keeps reading back the security key, and the
+                // status words after it are read-only.
+                let stored = self.config.words_mut();
+                stored[..word::LICENCE_AND_SECURITY_KEY]
+                    .copy_from_slice(&words[..word::LICENCE_AND_SECURITY_KEY]);
+                self.licence = words[word::LICENCE_AND_SECURITY_KEY];
+                self.mode = MockMode::Control;
+            }
+            MockMode::Programmer => {
+                self.config.words_mut()[word::PROGRAM_STATE] |= 0x0001;
+            }
+            MockMode::FlashWrite(offset) => {
+                let Some(cells) = self.flash.get_mut(offset..offset + words.len()) else {
+                    return Err(Error::UnexpectedResponse(
+                        "mock transport received a flash write past the end of flash",
+                    ));
+                };
+                // Programming only clears bits; erasing sets them again.
+                for (cell, word) in cells.iter_mut().zip(&words) {
+                    *cell &= word;
+                }
+                self.mode = MockMode::FlashWrite(offset + words.len());
+            }
+            MockMode::VeriComm => {
+                if self
+                    .accepted_licence
+                    .is_some_and(|accepted| accepted != self.licence)
+                {
+                    return Ok(());
+                }
+                (self.design)(&mut words);
+                self.queue_encrypted(&mut words);
+            }
+            MockMode::Control | MockMode::FlashRead => {
+                return Err(Error::UnexpectedResponse(
+                    "mock transport received FIFO data outside a write mode",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Flash words between the configured begin and read end clusters.
+    fn flash_read_range(&self) -> Result<std::ops::Range<usize>> {
+        let [_, block_size, cluster_size] = MOCK_FLASH_GEOMETRY.map(usize::from);
+        let words = self.config.words();
+        let cluster_index = |block: usize, cluster: usize| {
+            usize::from(words[block]) * block_size + usize::from(words[cluster])
+        };
+        let start = cluster_index(word::FLASH_BEGIN_BLOCK, word::FLASH_BEGIN_CLUSTER);
+        let end = cluster_index(word::FLASH_READ_END_BLOCK, word::FLASH_READ_END_CLUSTER);
+        if end < start || (end + 1) * cluster_size > self.flash.len() {
+            return Err(Error::UnexpectedResponse(
+                "mock transport received an invalid flash read range",
+            ));
+        }
+        Ok(start * cluster_size..(end + 1) * cluster_size)
+    }
+
+    fn decrypt(&mut self, words: &mut [u16]) {
+        if !self.encryption {
+            return;
+        }
+        for word in words {
+            *word ^= self.keys[self.rx_index];
+            self.rx_index = (self.rx_index + 1) & 0x0f;
+        }
+    }
+
+    fn queue_encrypted(&mut self, words: &mut [u16]) {
+        for word in words {
+            if self.encryption {
+                *word ^= self.keys[16 + self.tx_index];
+                self.tx_index = (self.tx_index + 1) & 0x0f;
+            }
+            self.fifo.extend(word.to_le_bytes());
+        }
+    }
+}
+
+/// An endpoint of a [`MockTransport`] opened for queued transfers.
+///
+/// Transfers run when they are waited for, in submission order, so a read
+/// returns what the writes retired before it queued. A read with nothing
+/// queued stays pending, as it would on the device.
+struct MockPipeline {
+    state: Arc<Mutex<MockState>>,
+    endpoint: Endpoint,
+    queue: VecDeque<Buffer>,
+    cancelled: VecDeque<Buffer>,
+}
+
+impl PipelineEndpoint for MockPipeline {
+    fn max_packet_size(&self) -> usize {
+        constants::DEFAULT_MAX_PACKET_BYTES
+    }
+
+    fn pending(&self) -> usize {
+        self.queue.len() + self.cancelled.len()
+    }
+
+    fn allocate(&self, len: usize) -> Buffer {
+        Buffer::new(len)
+    }
+
+    fn submit(&mut self, buffer: Buffer) {
+        self.queue.push_back(buffer);
+    }
+
+    fn wait_next_complete(&mut self, _timeout: Duration) -> Option<Completion> {
+        if let Some(buffer) = self.cancelled.pop_front() {
+            return Some(Completion {
+                buffer,
+                actual_len: 0,
+                status: Err(TransferError::Cancelled),
+            });
+        }
+        let mut buffer = self.queue.pop_front()?;
+        let mut state = lock(&self.state);
+        let status = if self.endpoint == Endpoint::FifoWrite {
+            state.fifo_write(&buffer).map_err(|_| TransferError::Fault)
+        } else if self.endpoint == Endpoint::Command {
+            state.command(&buffer).map_err(|_| TransferError::Fault)
+        } else {
+            let len = buffer.requested_len().min(state.fifo.len());
+            if len == 0 {
+                self.queue.push_front(buffer);
+                return None;
+            }
+            let bytes = state.fifo.drain(..len).collect::<Vec<_>>();
+            buffer.extend_from_slice(&bytes);
+            Ok(())
+        };
+        Some(Completion {
+            actual_len: buffer.len(),
+            buffer,
+            status,
+        })
+    }
+
+    fn cancel_all(&mut self) {
+        self.cancelled.append(&mut self.queue);
+    }
+}
+
+impl Drop for MockPipeline {
+    fn drop(&mut self) {
+        lock(&self.state).claimed[self.endpoint.index()] = false;
+    }
+}
+
+/// Inverse of the host-side table decoding: the firmware sends the first
+/// word inverted and every later word XORed with its decoded predecessor.
+fn encode_table(keys: &[u16; 32]) -> [u16; 32] {
+    let mut raw = [0u16; 32];
+    raw[0] = !keys[0];
+    for index in 1..keys.len() {
+        raw[index] = keys[index] ^ keys[index - 1];
+    }
+    raw
+}
+
+impl Transport for MockTransport {
+    fn transport_config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    fn read_bytes(&self, endpoint: Endpoint, buffer: &mut [u8]) -> Result<()> {
+        if endpoint == Endpoint::Sync {
+            buffer.fill(1);
+            return Ok(());
+        }
+
+        self.check_unclaimed(endpoint)?;
+        let mut state = self.state();
+        if endpoint != Endpoint::FifoRead || state.fifo.len() < buffer.len() {
+            return Err(Error::TransferTimeout {
+                context: "mock_bulk_read",
+                endpoint,
+                timeout: self.timeout_for(endpoint),
+            });
+        }
+        let len = buffer.len();
+        for (byte, queued) in buffer.iter_mut().zip(state.fifo.drain(..len)) {
+            *byte = queued;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, endpoint: Endpoint, buffer: &[u8]) -> Result<()> {
+        self.check_unclaimed(endpoint)?;
+        let mut state = self.state();
+        match endpoint {
+            Endpoint::Command => state.command(buffer),
+            Endpoint::FifoWrite => state.fifo_write(buffer),
+            _ => Err(Error::UnexpectedResponse(
+                "mock transport cannot write to a read endpoint",
+            )),
+        }
+    }
+
+    fn read_words_partial(&mut self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<usize> {
+        if endpoint != Endpoint::FifoRead {
+            return self.read_words(endpoint, buffer).map(|()| buffer.len());
+        }
+        self.check_unclaimed(endpoint)?;
+        let mut state = self.state();
+        let words = buffer.len().min(state.fifo.len() / 2);
+        let bytes = state.fifo.drain(..words * 2).collect::<Vec<_>>();
+        bytes_into_words(&bytes, &mut buffer[..words]);
+        Ok(words)
+    }
+
+    fn timeout_for(&self, endpoint: Endpoint) -> Duration {
+        self.endpoint_timeouts[endpoint.index()].unwrap_or(self.config.usb_timeout)
+    }
+
+    fn replace_timeout_override(
+        &mut self,
+        endpoint: Endpoint,
+        timeout: Option<Duration>,
+    ) -> Option<Duration> {
+        std::mem::replace(&mut self.endpoint_timeouts[endpoint.index()], timeout)
+    }
+
+    fn pipeline_out_endpoint(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        self.pipeline_endpoint(endpoint)
+    }
+
+    fn pipeline_in_endpoint(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        self.pipeline_endpoint(endpoint)
+    }
+
+    fn out_endpoint_opener(&self, endpoint: Endpoint) -> Result<Option<EndpointOpener>> {
+        let state = Arc::clone(&self.state);
+        Ok(Some(Box::new(move || claim(&state, endpoint))))
+    }
+}
+
+impl Board<MockTransport> {
+    /// Opens an initialized board backed by an echoing [`MockTransport`].
+    ///
+    /// Use [`Board::from_transport`] with [`MockTransport::with_design`] to
+    /// model a specific FPGA design.
+    pub fn open_mock() -> Result<Self> {
+        Self::from_transport(MockTransport::new())
+    }
+
+    pub fn mock(&self) -> &MockTransport {
+        self.usb()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockTransport;
+    use crate::{
+        Abilities, Board, BoardMode, Command, Endpoint, Error, IoConfig, LicenceDerivation,
+        LicenceKey, SdkConfig,
+    };
+
+    #[test]
+    fn mock_board_loops_vericomm_transfers_back() {
+        let mut board = Board::open_mock().unwrap();
+        assert!(board.config().is_programmed());
+        assert!(board.config().vericomm_ability());
+
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let tx: Vec<u16> = (0..64).collect();
+        let mut rx = vec![0u16; tx.len()];
+        io.transfer(&tx, &mut rx).unwrap();
+        assert_eq!(rx, tx);
+        io.transfer(&tx, &mut rx).unwrap();
+        assert_eq!(rx, tx);
+        io.finish().unwrap();
+    }
+
+    #[test]
+    fn prepared_emergency_handles_leave_the_command_endpoint_free() {
+        use crate::Transport as _;
+
+        let mut board = Board::open_mock().unwrap();
+        let mut handle = board.prepare_emergency_shutdown().unwrap();
+        board.mock().take_commands();
+        board.refresh_config().unwrap();
+        assert!(!board.mock().take_commands().is_empty());
+
+        // An endpoint held open, as the handle once did, locks the board
+        // out.
+        let held = board
+            .mock()
+            .out_endpoint_opener(Endpoint::Command)
+            .unwrap()
+            .unwrap()()
+        .unwrap();
+        assert!(matches!(board.refresh_config(), Err(Error::Busy { .. })));
+        drop(held);
+
+        handle.execute().unwrap();
+        assert_eq!(
+            board.mock().take_commands(),
+            [Command::ResetEngine, Command::CommandActive]
+        );
+        board.refresh_config().unwrap();
+    }
+
+    #[test]
+    fn plaintext_boards_send_fifo_data_unscrambled() {
+        let mut board = Board::open_mock().unwrap();
+        board.activate(BoardMode::VeriComm).unwrap();
+        assert!(board.set_encryption_enabled(false).is_err());
+        board.activate(BoardMode::Control).unwrap();
+        board.set_encryption_enabled(false).unwrap();
+        board.mock().set_encryption_enabled(false);
+
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let mut rx = [0u16; 4];
+        io.transfer(&[1, 2, 3, 4], &mut rx).unwrap();
+        assert_eq!(rx, [1, 2, 3, 4]);
+        io.finish().unwrap();
+        assert_eq!(board.refresh_config().unwrap().fifo_size_words(), 1024);
+        assert!(!board.encryption_enabled());
+    }
+
+    #[test]
+    fn sdk_sessions_select_the_channel_and_loop_back() {
+        let mut board = Board::open_mock().unwrap();
+        assert!(matches!(
+            board.configure_sdk(&SdkConfig::default()).err(),
+            Some(Error::FeatureUnavailable("veri_sdk"))
+        ));
+
+        board
+            .mock()
+            .set_abilities(Abilities::VERICOMM | Abilities::VERI_SDK);
+        board.refresh_config().unwrap();
+        board.mock().take_commands();
+
+        let settings = SdkConfig {
+            channel: 0x05,
+            ..SdkConfig::default()
+        };
+        let mut io = board.configure_sdk(&settings).unwrap();
+        let mut rx = [0u16; 4];
+        io.transfer(&[1, 2, 3, 4], &mut rx).unwrap();
+        assert_eq!(rx, [1, 2, 3, 4]);
+        io.finish().unwrap();
+
+        assert_eq!(
+            board.mock().take_commands(),
+            [
+                Command::WriteConfig,
+                Command::CommandActive,
+                Command::ActivateVeriSdk,
+                Command::ResetEngine,
+                Command::CommandActive,
+            ]
+        );
+        assert_eq!(board.mock().config().veri_sdk_channel_selector(), 0x05);
+        assert_eq!(board.mock().licence_key(), LicenceKey::DEFAULT.value());
+        assert_eq!(board.mode(), BoardMode::Control);
+    }
+
+    #[test]
+    fn derived_licences_use_the_fresh_security_key() {
+        let mut board = Board::open_mock().unwrap();
+        // Leaves the cached word 31 holding the default licence.
+        board
+            .configure_io(&IoConfig::default())
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        // A customer ID only known at run time.
+        let customer_id = std::hint::black_box(0x1234);
+        let settings = IoConfig {
+            licence_key: None,
+            licence_derivation: Some(LicenceDerivation::new(move |key| {
+                LicenceKey::new(key.value() ^ customer_id)
+            })),
+            ..IoConfig::default()
+        };
+        let io = board.configure_io(&settings).unwrap();
+        let derived = LicenceKey::new(super::MOCK_SECURITY_KEY ^ 0x1234);
+        assert_eq!(io.licence_key(), Some(derived));
+        io.finish().unwrap();
+        assert_eq!(board.mock().licence_key(), derived.value());
+
+        // An explicit key alongside a derivation is ambiguous.
+        let both = IoConfig {
+            licence_key: Some(LicenceKey::DEFAULT),
+            ..settings.clone()
+        };
+        assert!(matches!(
+            board.configure_io(&both).err(),
+            Some(Error::InvalidSettings(_))
+        ));
+        let built = IoConfig::builder()
+            .licence_derivation(settings.licence_derivation)
+            .build()
+            .unwrap();
+        assert_eq!(built.licence_key, None);
+    }
+
+    #[test]
+    fn silent_licence_checks_report_the_rejected_licence() {
+        let transport = MockTransport::new();
+        transport.set_accepted_licence(Some(0xff41));
+        let mut board = Board::from_transport(transport).unwrap();
+        let mut rx = [0u16; 4];
+
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        // Plain transfers keep their timeout; only the probe infers.
+        assert!(matches!(
+            io.transfer(&[1, 2, 3, 4], &mut rx),
+            Err(Error::TransferTimeout { .. })
+        ));
+        match io.check_licence(&[1, 2, 3, 4], &mut rx) {
+            Err(Error::LicenceRejected {
+                provided: 0xff40,
+                source,
+            }) => assert!(matches!(
+                *source,
+                Error::TransferTimeout {
+                    endpoint: Endpoint::FifoRead,
+                    ..
+                }
+            )),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        io.finish().unwrap();
+
+        let settings = IoConfig::builder()
+            .licence_key(Some(LicenceKey::new(0xff41)))
+            .build()
+            .unwrap();
+        let mut io = board.configure_io(&settings).unwrap();
+        io.check_licence(&[1, 2, 3, 4], &mut rx).unwrap();
+        assert_eq!(rx, [1, 2, 3, 4]);
+        io.finish().unwrap();
+    }
+
+    #[test]
+    fn programming_checks_the_reported_fifo_size() {
+        let transport = MockTransport::new();
+        transport.set_fifo_size_words(0);
+        let mut board = Board::from_transport(transport).unwrap();
+        assert!(matches!(
+            board.programmer().err(),
+            Some(Error::ProgramFifoTooSmall {
+                words: 0,
+                minimum: 1
+            })
+        ));
+        // The board stays usable once the firmware reports a real FIFO,
+        // however small.
+        board.mock().set_fifo_size_words(16);
+        assert_eq!(board.programmer().unwrap().chunk_words(), 16);
+
+        let mut board = Board::open_mock().unwrap();
+        let mut session = board.programmer().unwrap();
+        assert_eq!(session.chunk_words(), 1024);
+        session.write_bitstream_words(&[0x1234; 3000]).unwrap();
+        session.finish().unwrap();
+    }
+
+    #[test]
+    fn mock_design_and_config_writes_round_trip() {
+        let transport = MockTransport::with_design(|words| {
+            for word in words {
+                *word = !*word;
+            }
+        });
+        let mut board = Board::from_transport(transport).unwrap();
+        let settings = IoConfig {
+            clock_high_delay: 7,
+            ..IoConfig::default()
+        };
+        let mut io = board.configure_io(&settings).unwrap();
+        let mut rx = [0u16; 8];
+        io.transfer(&[0x00ff; 8], &mut rx).unwrap();
+        assert_eq!(rx, [0xff00; 8]);
+        io.finish().unwrap();
+
+        assert_eq!(
+            board.refresh_config().unwrap().vericomm_clock_high_delay(),
+            7
+        );
+    }
+}
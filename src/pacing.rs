@@ -0,0 +1,154 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Amount of traffic the limiter lets through without pacing, expressed as
+/// time at the configured rate.
+const BURST_WINDOW: Duration = Duration::from_millis(10);
+
+pub(crate) trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Token bucket measured in words.
+///
+/// Submissions that fit in the bucket pass straight through, so pacing only
+/// adds latency once the caller actually exceeds the configured rate.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter<C: Clock = SystemClock> {
+    words_per_second: f64,
+    burst_words: f64,
+    tokens: f64,
+    last_refill: Instant,
+    clock: C,
+}
+
+impl RateLimiter<SystemClock> {
+    pub(crate) fn new(words_per_second: u64) -> Self {
+        Self::with_clock(words_per_second, SystemClock)
+    }
+}
+
+impl<C: Clock> RateLimiter<C> {
+    pub(crate) fn with_clock(words_per_second: u64, clock: C) -> Self {
+        let words_per_second = words_per_second.max(1) as f64;
+        let burst_words = (words_per_second * BURST_WINDOW.as_secs_f64()).max(1.0);
+        Self {
+            words_per_second,
+            burst_words,
+            tokens: burst_words,
+            last_refill: clock.now(),
+            clock,
+        }
+    }
+
+    pub(crate) fn words_per_second(&self) -> u64 {
+        self.words_per_second as u64
+    }
+
+    /// Blocks until `words` may be submitted without exceeding the rate.
+    pub(crate) fn acquire(&mut self, words: usize) {
+        self.refill();
+        self.tokens -= words as f64;
+        if self.tokens < 0.0 {
+            self.clock.sleep(Duration::from_secs_f64(
+                -self.tokens / self.words_per_second,
+            ));
+            self.refill();
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.last_refill = now;
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.words_per_second).min(self.burst_words);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, RateLimiter};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone)]
+    struct FakeClock {
+        start: Instant,
+        offset: Rc<Cell<Duration>>,
+        slept: Rc<Cell<Duration>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                offset: Rc::new(Cell::new(Duration::ZERO)),
+                slept: Rc::new(Cell::new(Duration::ZERO)),
+            }
+        }
+
+        fn advance(&self, duration: Duration) {
+            self.offset.set(self.offset.get() + duration);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.offset.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.slept.set(self.slept.get() + duration);
+            self.advance(duration);
+        }
+    }
+
+    #[test]
+    fn limiter_holds_the_configured_rate_over_a_simulated_second() {
+        let clock = FakeClock::new();
+        let mut limiter = RateLimiter::with_clock(100_000, clock.clone());
+        let mut words = 0u64;
+        while clock.offset.get() < Duration::from_secs(1) {
+            limiter.acquire(512);
+            words += 512;
+        }
+        // The initial burst allowance is 10 ms worth of words.
+        assert!((100_000..=101_000 + 512).contains(&words), "{words}");
+    }
+
+    #[test]
+    fn limiter_does_not_sleep_below_the_limit() {
+        let clock = FakeClock::new();
+        let mut limiter = RateLimiter::with_clock(100_000, clock.clone());
+        for _ in 0..100 {
+            limiter.acquire(64);
+            clock.advance(Duration::from_millis(1));
+        }
+        assert_eq!(clock.slept.get(), Duration::ZERO);
+    }
+
+    #[test]
+    fn oversized_requests_are_paced_proportionally() {
+        let clock = FakeClock::new();
+        let mut limiter = RateLimiter::with_clock(1_000, clock.clone());
+        limiter.acquire(10);
+        limiter.acquire(2_000);
+        assert_eq!(clock.slept.get(), Duration::from_secs(2));
+    }
+}
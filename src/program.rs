@@ -1,12 +1,119 @@
+use crate::compress::{decompressed, is_gzip, read_error};
+use crate::config::FlashAddress;
 use crate::error::{Error, Result};
-use crate::session::Board;
+use crate::flash::bitstream_flash_words;
+use crate::ihex::IntelHexDecoder;
+use crate::session::{Board, ProgramSession};
+use crate::transport::Transport;
 use crate::usb::TransportConfig;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    panic::{self, AssertUnwindSafe},
     path::Path,
+    thread,
+    time::{Duration, Instant},
 };
 
+/// Lines longer than this, line ending aside, are rejected. A
+/// [`BitfileReader`] stops reading a line just past this length, so a
+/// corrupt or binary file without line breaks cannot make it buffer
+/// unbounded data.
+pub const MAX_BITFILE_LINE_BYTES: usize = 64 * 1024;
+
+/// Longest token quoted in an [`Error::InvalidBitfileLine`] excerpt.
+const EXCERPT_CHARS: usize = 16;
+
+/// Inputs smaller than this are parsed on the calling thread; spawning
+/// workers costs more than it saves.
+const PARALLEL_PARSE_MIN_BYTES: usize = 1024 * 1024;
+
+/// How a bitfile encodes its words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitfileFormat {
+    /// SMIMS text: underscore-separated 16-bit hex words, one group per
+    /// line.
+    SmimsText,
+    /// Intel HEX records holding the FIFO byte stream.
+    IntelHex,
+    /// The FIFO byte stream itself: little-endian words, no framing.
+    Binary,
+}
+
+impl BitfileFormat {
+    /// Guesses the format from the start of a file (after any gzip
+    /// layer): Intel HEX when the first non-blank character is `:`, SMIMS
+    /// text when the first bytes are printable ASCII. Empty input counts as
+    /// text. A damaged text file is still read as text, so its parse error
+    /// points at the damage.
+    ///
+    /// Anything else is `None`. Binary is never guessed, since a text file
+    /// with a byte order mark, UTF-16 encoding or a stray NUL would
+    /// otherwise upload as raw words; binary bitfiles must be read with the
+    /// format given explicitly.
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        const SNIFF_BYTES: usize = 512;
+
+        let head = &head[..head.len().min(SNIFF_BYTES)];
+        match head.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            None => Some(Self::SmimsText),
+            Some(b':') => Some(Self::IntelHex),
+            Some(_)
+                if head
+                    .iter()
+                    .all(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace()) =>
+            {
+                Some(Self::SmimsText)
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+/// The stage a programming run is in when a [`ProgramProgress`] is
+/// reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramPhase {
+    Parsing,
+    Activating,
+    Uploading,
+    Verifying,
+}
+
+/// A progress report passed to the callback of
+/// [`Programmer::program_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramProgress {
+    pub phase: ProgramPhase,
+    /// Bitstream length in words; zero while the bitfile is being parsed.
+    pub total_words: usize,
+    /// The detected or forced bitfile format, once parsing has started.
+    pub format: Option<BitfileFormat>,
+    pub words_sent: usize,
+    /// Time since the run started.
+    pub elapsed: Duration,
+}
+
+/// Options for [`Programmer::program_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgramOptions {
+    /// Record the bitstream's [`bitstream_crc32`] in the configuration
+    /// after programming and read it back. Only a run with `verify` set
+    /// leaves a CRC that [`Programmer::verify`] can check.
+    pub verify: bool,
+    /// Read the bitfile as this format instead of detecting it, for
+    /// ambiguous files and for binary ones, which are never detected.
+    pub format: Option<BitfileFormat>,
+}
+
+/// What [`Programmer::program_if_changed`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramOutcome {
+    Programmed,
+    /// The FPGA already held the bitstream; nothing was uploaded.
+    AlreadyCurrent,
+}
+
 pub struct Programmer {
     board: Board,
 }
@@ -30,11 +137,154 @@ impl Programmer {
         &mut self.board
     }
 
+    /// Programs `bitfile`, checking the board's
+    /// [`cancel_source`](Board::cancel_source) before each FIFO-sized
+    /// bitstream chunk.
+    ///
+    /// The file is parsed once before the device is touched, so a bad
+    /// bitfile leaves the resident design alone, and again as it is
+    /// uploaded one chunk at a time, so it is never held in memory whole.
+    /// Should the file change in between and fail to parse part-way
+    /// through the upload, the board is returned to control mode with the
+    /// FPGA unprogrammed.
+    ///
+    /// Once programming has started, cancellation takes effect within one
+    /// chunk write (bounded by the transport's bulk timeout). The board is
+    /// then returned to control mode and [`Error::Cancelled`] is returned;
+    /// the FPGA is left unprogrammed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "info",
+            skip_all,
+            fields(bitfile = %bitfile.as_ref().display()),
+            err
+        )
+    )]
     pub fn program(&mut self, bitfile: impl AsRef<Path>) -> Result<()> {
-        let words = load_bitfile(bitfile.as_ref())?;
-        let mut session = self.board.programmer()?;
-        session.write_bitstream_words(&words)?;
-        session.finish()
+        program_bitfile(&mut self.board, bitfile.as_ref())
+    }
+
+    /// [`program`](Self::program) with `options` applied.
+    pub fn program_with_options(
+        &mut self,
+        bitfile: impl AsRef<Path>,
+        options: &ProgramOptions,
+    ) -> Result<()> {
+        program_bitfile_with_options(&mut self.board, bitfile.as_ref(), options)
+    }
+
+    /// Programs `bitfile` unless a verified run already left exactly this
+    /// bitstream on the FPGA, recording its CRC when it does program.
+    ///
+    /// Every programming run clears the recorded CRC before uploading, so
+    /// an interrupted or unverified upload is never mistaken for a current
+    /// one. To upload regardless, call
+    /// [`program_with_options`](Self::program_with_options) with `verify`
+    /// set.
+    pub fn program_if_changed(&mut self, bitfile: impl AsRef<Path>) -> Result<ProgramOutcome> {
+        let bitfile = bitfile.as_ref();
+        let crc = bitfile_crc32(bitfile, None)?;
+        program_source_if_changed(&mut self.board, crc, || open_bitfile(bitfile, None))
+    }
+
+    /// Checks without reprogramming that the resident design is `bitfile`,
+    /// by comparing its CRC with the one recorded by a verified
+    /// [`program_with_options`](Self::program_with_options) run.
+    ///
+    /// Returns [`Error::NotProgrammed`] if the FPGA holds no design and
+    /// [`Error::BitstreamMismatch`] if it holds a different one.
+    ///
+    /// The CRC lives in configuration words 29 and 30, which have no known
+    /// firmware use. No firmware documentation says those words survive a
+    /// power cycle or a reconfiguration from flash, and that has not been
+    /// checked on hardware; if the firmware clears them, this reports a
+    /// mismatch rather than a false match.
+    pub fn verify(&mut self, bitfile: impl AsRef<Path>) -> Result<()> {
+        let crc = bitfile_crc32(bitfile.as_ref(), None)?;
+        verify_crc(&mut self.board, crc)
+    }
+
+    /// [`program`](Self::program) for a bitfile already in memory, such as
+    /// one downloaded from a build service.
+    pub fn program_bytes(&mut self, bitfile: &[u8]) -> Result<()> {
+        let words = parse_bitfile(bitfile)?;
+        program_words(&mut self.board, &words, &mut Progress::silent())
+    }
+
+    /// [`program`](Self::program) for a bitfile read from `reader`.
+    ///
+    /// A reader can only be read once, so unlike the path-based entry
+    /// points the bitfile is parsed as it is uploaded: a parse error
+    /// part-way through aborts programming and leaves the FPGA
+    /// unprogrammed, wiping the design it held. Use
+    /// [`program_bytes`](Self::program_bytes) to parse everything first.
+    pub fn program_reader(&mut self, reader: impl BufRead) -> Result<()> {
+        let mut source = BitfileReader::new(decompressed(reader)?);
+        program_source(&mut self.board, &mut source, &mut Progress::silent())
+    }
+
+    /// Programs an already parsed bitstream.
+    pub fn program_words(&mut self, words: &[u16]) -> Result<()> {
+        program_words(&mut self.board, words, &mut Progress::silent())
+    }
+
+    /// [`program`](Self::program), calling `progress` as each phase starts
+    /// and after every FIFO-sized bitstream chunk.
+    ///
+    /// If `progress` panics, the board is returned to control mode and
+    /// [`Error::CallbackPanicked`] is returned; the panic does not unwind
+    /// through the programmer. To stop the upload, from the callback or any
+    /// other thread, cancel the board's
+    /// [`cancel_source`](Board::cancel_source).
+    pub fn program_with_progress(
+        &mut self,
+        bitfile: impl AsRef<Path>,
+        mut progress: impl FnMut(ProgramProgress),
+    ) -> Result<()> {
+        let mut progress = Progress::new(&mut progress);
+        progress.report(ProgramPhase::Parsing, 0)?;
+        let mut reader = open_bitfile(bitfile.as_ref(), None)?;
+        let words = read_all(&mut reader)?;
+        progress.format = reader.format();
+        program_words(&mut self.board, &words, &mut progress)
+    }
+
+    /// Stores `bitfile` in on-board flash from block 0 and reads it back,
+    /// for boards that configure the FPGA from flash at power-on.
+    ///
+    /// The SMIMS headers document no image header for flash, so the words
+    /// are stored exactly as [`program`](Self::program) would upload them.
+    ///
+    /// The SMIMS command set has no erase command, so the region cannot be
+    /// erased here: it must be erased already, and a board that already
+    /// holds a flashed image fails with [`Error::FlashNotErased`] before
+    /// anything is written. Re-flashing such a board needs the vendor
+    /// tool. A bitstream that would reach the user data
+    /// slots ([`crate::USER_DATA_SLOTS`]) is rejected with
+    /// [`Error::FlashCapacityExceeded`] before anything is written. The
+    /// FPGA itself is not reprogrammed.
+    pub fn program_flash(&mut self, bitfile: impl AsRef<Path>) -> Result<()> {
+        let words = read_all(&mut open_bitfile(bitfile.as_ref(), None)?)?;
+        program_flash_words(&mut self.board, &words, &mut Progress::silent())
+    }
+
+    /// [`program_flash`](Self::program_flash), reporting progress as
+    /// [`program_with_progress`](Self::program_with_progress) does.
+    /// Cancelling the board's [`cancel_source`](Board::cancel_source)
+    /// stops the write within one FIFO-sized chunk, leaving the flash
+    /// partly written.
+    pub fn program_flash_with_progress(
+        &mut self,
+        bitfile: impl AsRef<Path>,
+        mut progress: impl FnMut(ProgramProgress),
+    ) -> Result<()> {
+        let mut progress = Progress::new(&mut progress);
+        progress.report(ProgramPhase::Parsing, 0)?;
+        let mut reader = open_bitfile(bitfile.as_ref(), None)?;
+        let words = read_all(&mut reader)?;
+        progress.format = reader.format();
+        program_flash_words(&mut self.board, &words, &mut progress)
     }
 
     pub fn close(self) -> Result<()> {
@@ -42,38 +292,559 @@ impl Programmer {
     }
 }
 
+/// Body of [`Programmer::program_flash`] for an already parsed bitstream.
+pub(crate) fn program_flash_words<T: Transport>(
+    board: &mut Board<T>,
+    words: &[u16],
+    progress: &mut Progress<'_>,
+) -> Result<()> {
+    board.check_cancelled("flash_write")?;
+    board.ensure_ready()?;
+    let available = bitstream_flash_words(&board.config().flash_geometry());
+    if words.len() > available {
+        return Err(Error::FlashCapacityExceeded {
+            words: words.len(),
+            available,
+        });
+    }
+    progress.total_words = words.len();
+    progress.report(ProgramPhase::Activating, 0)?;
+    board.write_flash(FlashAddress::default(), words, progress)
+}
+
+/// Body of [`Programmer::program`], shared with the async programmer's
+/// worker thread.
+pub(crate) fn program_bitfile<T: Transport>(board: &mut Board<T>, bitfile: &Path) -> Result<()> {
+    // A streamed parse error would only surface after programming began.
+    bitfile_crc32(bitfile, None)?;
+    program_source(
+        board,
+        &mut open_bitfile(bitfile, None)?,
+        &mut Progress::silent(),
+    )
+}
+
+/// Body of [`Programmer::program_with_options`].
+pub(crate) fn program_bitfile_with_options<T: Transport>(
+    board: &mut Board<T>,
+    bitfile: &Path,
+    options: &ProgramOptions,
+) -> Result<()> {
+    bitfile_crc32(bitfile, options.format)?;
+    let mut source = open_bitfile(bitfile, options.format)?;
+    if options.verify {
+        program_recorded(board, &mut source)
+    } else {
+        program_source(board, &mut source, &mut Progress::silent())
+    }
+}
+
+/// Bitstream words handed to the programming sequence piece by piece.
+pub(crate) trait BitstreamSource {
+    /// Bitstream length, when it is known before the upload starts.
+    fn total_words(&self) -> Option<usize>;
+
+    /// The next at most `max_words` words, or `None` once the bitstream
+    /// is exhausted.
+    fn next_chunk(&mut self, max_words: usize) -> Result<Option<&[u16]>>;
+
+    /// [`bitstream_crc32`] of the words yielded so far.
+    fn crc32(&self) -> u32;
+}
+
+/// An already parsed bitstream.
+pub(crate) struct SliceSource<'a> {
+    words: &'a [u16],
+    crc: Crc32,
+}
+
+impl<'a> SliceSource<'a> {
+    pub(crate) fn new(words: &'a [u16]) -> Self {
+        Self {
+            words,
+            crc: Crc32::new(),
+        }
+    }
+}
+
+impl BitstreamSource for SliceSource<'_> {
+    fn total_words(&self) -> Option<usize> {
+        Some(self.words.len())
+    }
+
+    fn next_chunk(&mut self, max_words: usize) -> Result<Option<&[u16]>> {
+        if self.words.is_empty() {
+            return Ok(None);
+        }
+        let (chunk, rest) = self.words.split_at(max_words.min(self.words.len()));
+        self.words = rest;
+        self.crc.update(chunk);
+        Ok(Some(chunk))
+    }
+
+    fn crc32(&self) -> u32 {
+        self.crc.value()
+    }
+}
+
+impl<R: BufRead> BitstreamSource for BitfileReader<R> {
+    fn total_words(&self) -> Option<usize> {
+        None
+    }
+
+    fn next_chunk(&mut self, max_words: usize) -> Result<Option<&[u16]>> {
+        self.fill(max_words)
+    }
+
+    fn crc32(&self) -> u32 {
+        self.crc.value()
+    }
+}
+
+/// [`program_source`] for an already parsed bitstream.
+pub(crate) fn program_words<T: Transport>(
+    board: &mut Board<T>,
+    words: &[u16],
+    progress: &mut Progress<'_>,
+) -> Result<()> {
+    program_source(board, &mut SliceSource::new(words), progress)
+}
+
+/// The programming sequence every entry point ends in: activates the
+/// programmer, uploads `source` one FIFO-sized chunk at a time and checks
+/// that the FPGA reports programmed.
+pub(crate) fn program_source<T: Transport>(
+    board: &mut Board<T>,
+    source: &mut impl BitstreamSource,
+    progress: &mut Progress<'_>,
+) -> Result<()> {
+    board.check_cancelled("program")?;
+    progress.total_words = source.total_words().unwrap_or(0);
+    progress.report(ProgramPhase::Activating, 0)?;
+    if board.config().bitstream_crc() != 0 {
+        board.write_bitstream_crc(0)?;
+    }
+    let mut session = board.programmer()?;
+    match upload(&mut session, source, progress) {
+        Ok(()) => session.finish(),
+        // Errors that leave the device healthy get it out of programming
+        // mode; transport errors are left to the caller's recovery.
+        Err(
+            err @ (Error::Cancelled(_)
+            | Error::CallbackPanicked(_)
+            | Error::InvalidBitfile(_)
+            | Error::InvalidBitfileLine { .. }
+            | Error::CorruptArchive(_)
+            | Error::Io(_)),
+        ) => {
+            session.abort()?;
+            Err(err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn upload<T: Transport>(
+    session: &mut ProgramSession<'_, T>,
+    source: &mut impl BitstreamSource,
+    progress: &mut Progress<'_>,
+) -> Result<()> {
+    let mut sent = 0;
+    while let Some(chunk) = source.next_chunk(session.chunk_words())? {
+        session.write_bitstream_chunk(chunk)?;
+        sent += chunk.len();
+        progress.report(ProgramPhase::Uploading, sent)?;
+    }
+    progress.report(ProgramPhase::Verifying, sent)
+}
+
+/// Programs `source`, then records and reads back its CRC.
+pub(crate) fn program_recorded<T: Transport>(
+    board: &mut Board<T>,
+    source: &mut impl BitstreamSource,
+) -> Result<()> {
+    program_source(board, source, &mut Progress::silent())?;
+    let crc = source.crc32();
+    board.write_bitstream_crc(crc)?;
+    verify_crc(board, crc)
+}
+
+/// Body of [`Programmer::program_if_changed`]: `crc` is the bitstream's
+/// CRC and `open` provides its words if it has to be uploaded.
+pub(crate) fn program_source_if_changed<T: Transport, S: BitstreamSource>(
+    board: &mut Board<T>,
+    crc: u32,
+    open: impl FnOnce() -> Result<S>,
+) -> Result<ProgramOutcome> {
+    match verify_crc(board, crc) {
+        Ok(()) => return Ok(ProgramOutcome::AlreadyCurrent),
+        Err(Error::NotProgrammed | Error::BitstreamMismatch { .. }) => {}
+        Err(err) => return Err(err),
+    }
+    program_recorded(board, &mut open()?)?;
+    Ok(ProgramOutcome::Programmed)
+}
+
+/// Re-reads the configuration and compares the recorded bitstream CRC with
+/// `expected`.
+///
+/// Relies on the firmware keeping words 29 and 30 as last written, which is
+/// unconfirmed on hardware; see [`Programmer::verify`].
+pub(crate) fn verify_crc<T: Transport>(board: &mut Board<T>, expected: u32) -> Result<()> {
+    let config = board.refresh_config()?;
+    if !config.is_programmed() {
+        return Err(Error::NotProgrammed);
+    }
+    let actual = config.bitstream_crc();
+    if actual != expected {
+        return Err(Error::BitstreamMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3) of a bitstream, taken over its words in
+/// little-endian byte order.
+pub fn bitstream_crc32(words: &[u16]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(words);
+    crc.value()
+}
+
+/// Rolling state behind [`bitstream_crc32`].
+#[derive(Debug, Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(!0)
+    }
+
+    fn update(&mut self, words: &[u16]) {
+        for byte in words.iter().flat_map(|word| word.to_le_bytes()) {
+            self.0 ^= u32::from(byte);
+            for _ in 0..8 {
+                self.0 = (self.0 >> 1) ^ (0xedb8_8320 & (self.0 & 1).wrapping_neg());
+            }
+        }
+    }
+
+    fn value(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Forwards progress to an optional user callback, turning a panic in the
+/// callback into [`Error::CallbackPanicked`].
+pub(crate) struct Progress<'a> {
+    callback: Option<&'a mut dyn FnMut(ProgramProgress)>,
+    started: Instant,
+    total_words: usize,
+    format: Option<BitfileFormat>,
+}
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(callback: &'a mut dyn FnMut(ProgramProgress)) -> Self {
+        Self {
+            callback: Some(callback),
+            started: Instant::now(),
+            total_words: 0,
+            format: None,
+        }
+    }
+
+    pub(crate) fn silent() -> Self {
+        Self {
+            callback: None,
+            started: Instant::now(),
+            total_words: 0,
+            format: None,
+        }
+    }
+
+    pub(crate) fn report(&mut self, phase: ProgramPhase, words_sent: usize) -> Result<()> {
+        let Some(callback) = self.callback.as_mut() else {
+            return Ok(());
+        };
+        let update = ProgramProgress {
+            phase,
+            total_words: self.total_words,
+            format: self.format,
+            words_sent,
+            elapsed: self.started.elapsed(),
+        };
+        panic::catch_unwind(AssertUnwindSafe(|| callback(update))).map_err(|_| {
+            // A callback that panicked once is not trusted with further updates.
+            self.callback = None;
+            Error::CallbackPanicked("program progress")
+        })
+    }
+}
+
 pub fn load_bitfile(path: &Path) -> Result<Vec<u16>> {
-    let file = File::open(path)?;
-    load_bitfile_from_reader(BufReader::new(file))
+    parse_bitfile(&fs::read(path)?)
 }
 
+/// Parses an in-memory bitfile, using every available core for large
+/// SMIMS text inputs.
+pub fn parse_bitfile(data: &[u8]) -> Result<Vec<u16>> {
+    if is_gzip(data) || BitfileFormat::detect(data) != Some(BitfileFormat::SmimsText) {
+        return load_bitfile_from_reader(data);
+    }
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    parse_bitfile_parallel(data, threads)
+}
+
+/// Parses a bitfile from `reader`, decompressing it first if it is
+/// gzip-compressed (with the `compress` feature).
 pub fn load_bitfile_from_reader<R: BufRead>(reader: R) -> Result<Vec<u16>> {
+    read_all(&mut BitfileReader::new(decompressed(reader)?))
+}
+
+fn read_all<R: BufRead>(reader: &mut BitfileReader<R>) -> Result<Vec<u16>> {
     let mut program_data = Vec::new();
+    while let Some(chunk) = reader.next_chunk()? {
+        program_data.extend_from_slice(chunk);
+    }
+    Ok(program_data)
+}
 
-    for (line_index, line) in reader.lines().enumerate() {
-        let line_number = line_index + 1;
-        let line = line?;
-        let payload = line.split_whitespace().next().unwrap_or_default();
+/// Opens `path` for streaming, decompressing it if needed and detecting
+/// its format unless one is given.
+pub(crate) fn open_bitfile(
+    path: &Path,
+    format: Option<BitfileFormat>,
+) -> Result<BitfileReader<Box<dyn BufRead>>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut reader = BitfileReader::new(decompressed(file)?);
+    reader.format = format;
+    Ok(reader)
+}
+
+/// CRC of the bitfile at `path`, parsed without holding it in memory.
+/// Also serves as the check that the whole file parses.
+fn bitfile_crc32(path: &Path, format: Option<BitfileFormat>) -> Result<u32> {
+    let mut reader = open_bitfile(path, format)?;
+    while reader.next_chunk()?.is_some() {}
+    Ok(reader.crc32())
+}
 
-        if payload.is_empty() {
-            continue;
+/// Parses a bitfile incrementally, yielding its words in fixed-size
+/// chunks so that a bitstream never has to be held in memory whole.
+///
+/// The reader counts the words it has yielded and keeps a running
+/// [`bitstream_crc32`] of them. Errors are the same as
+/// [`load_bitfile_from_reader`]'s; an empty bitfile is reported by the
+/// first [`next_chunk`](Self::next_chunk) call. Unless a format is given,
+/// it is detected from the first buffered bytes. The reader parses its
+/// input as is; the `load_*`, `parse_*` and `program*` entry points are
+/// the ones that look through gzip compression.
+pub struct BitfileReader<R> {
+    reader: R,
+    format: Option<BitfileFormat>,
+    hex: IntelHexDecoder,
+    /// First byte of a binary word whose second byte is not buffered yet.
+    odd_byte: Option<u8>,
+    line: String,
+    line_number: usize,
+    words: Vec<u16>,
+    yielded: usize,
+    chunk_words: usize,
+    words_read: usize,
+    crc: Crc32,
+    eof: bool,
+}
+
+impl<R: BufRead> BitfileReader<R> {
+    /// Chunk length used by [`BitfileReader::new`], matching the usual
+    /// programming FIFO.
+    pub const DEFAULT_CHUNK_WORDS: usize = 1024;
+
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_words(reader, Self::DEFAULT_CHUNK_WORDS)
+    }
+
+    pub fn with_chunk_words(reader: R, chunk_words: usize) -> Self {
+        Self {
+            reader,
+            format: None,
+            hex: IntelHexDecoder::default(),
+            odd_byte: None,
+            line: String::new(),
+            line_number: 0,
+            words: Vec::new(),
+            yielded: 0,
+            chunk_words: chunk_words.max(1),
+            words_read: 0,
+            crc: Crc32::new(),
+            eof: false,
         }
+    }
+
+    /// A reader that parses `reader` as `format` without detection.
+    pub fn with_format(reader: R, format: BitfileFormat) -> Self {
+        Self {
+            format: Some(format),
+            ..Self::new(reader)
+        }
+    }
 
-        for segment in payload.split('_') {
-            if segment.is_empty() {
-                return Err(Error::InvalidBitfileLine {
-                    line: line_number,
-                    reason: "empty word segment",
-                });
+    /// The format being parsed; `None` until detection has run on the
+    /// first [`next_chunk`](Self::next_chunk) call.
+    pub fn format(&self) -> Option<BitfileFormat> {
+        self.format
+    }
+
+    /// The next chunk of up to the configured number of words, or `None`
+    /// at the end of the bitfile.
+    pub fn next_chunk(&mut self) -> Result<Option<&[u16]>> {
+        self.fill(self.chunk_words)
+    }
+
+    /// Words yielded so far.
+    pub fn words_read(&self) -> usize {
+        self.words_read
+    }
+
+    /// [`bitstream_crc32`] of the words yielded so far.
+    pub fn crc32(&self) -> u32 {
+        self.crc.value()
+    }
+
+    fn fill(&mut self, max_words: usize) -> Result<Option<&[u16]>> {
+        let max_words = max_words.max(1);
+        self.words.drain(..self.yielded);
+        self.yielded = 0;
+        let format = match self.format {
+            Some(format) => format,
+            None => {
+                let format = BitfileFormat::detect(self.reader.fill_buf().map_err(read_error)?)
+                    .ok_or(Error::InvalidBitfile(
+                        "unrecognized bitfile format; binary bitfiles must be read with BitfileFormat::Binary given explicitly",
+                    ))?;
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?format, "detected bitfile format");
+                *self.format.insert(format)
             }
+        };
+        if format == BitfileFormat::Binary {
+            self.read_binary(max_words)?;
+        } else {
+            self.read_lines(format, max_words)?;
+        }
 
-            let value =
-                u16::from_str_radix(segment, 16).map_err(|_| Error::InvalidBitfileLine {
-                    line: line_number,
-                    reason: "bitfile contains non-hexadecimal characters",
-                })?;
-            program_data.push(value);
+        if self.words.is_empty() {
+            return match (self.words_read, self.line_number) {
+                (0, 0) => Err(Error::InvalidBitfile("bitfile is empty")),
+                (0, _) => Err(Error::InvalidBitfile("bitfile produced no data")),
+                _ => Ok(None),
+            };
         }
+        self.yielded = self.words.len().min(max_words);
+        let chunk = &self.words[..self.yielded];
+        self.crc.update(chunk);
+        self.words_read += chunk.len();
+        Ok(Some(chunk))
+    }
+
+    fn read_binary(&mut self, max_words: usize) -> Result<()> {
+        while self.words.len() < max_words && !self.eof {
+            let buffer = self.reader.fill_buf().map_err(read_error)?;
+            if buffer.is_empty() {
+                self.eof = true;
+                if self.odd_byte.is_some() {
+                    return Err(Error::InvalidBitfile(
+                        "binary bitfile has an odd number of bytes",
+                    ));
+                }
+                break;
+            }
+            let wanted = (max_words - self.words.len()) * 2 - usize::from(self.odd_byte.is_some());
+            let taken = buffer.len().min(wanted);
+            for &byte in &buffer[..taken] {
+                match self.odd_byte.take() {
+                    Some(low) => self.words.push(u16::from_le_bytes([low, byte])),
+                    None => self.odd_byte = Some(byte),
+                }
+            }
+            self.reader.consume(taken);
+        }
+        Ok(())
+    }
+
+    fn read_lines(&mut self, format: BitfileFormat, max_words: usize) -> Result<()> {
+        while self.words.len() < max_words && !self.eof {
+            self.line.clear();
+            // Room for the longest allowed line and a CRLF; anything longer
+            // is cut off here and rejected below.
+            let limit = MAX_BITFILE_LINE_BYTES as u64 + 2;
+            let read = io::Read::take(&mut self.reader, limit)
+                .read_line(&mut self.line)
+                .map_err(read_error)?;
+            if read == 0 {
+                self.eof = true;
+                if format == BitfileFormat::IntelHex {
+                    self.hex.finish()?;
+                }
+                break;
+            }
+            self.line_number += 1;
+            let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            match format {
+                BitfileFormat::SmimsText | BitfileFormat::Binary => {
+                    parse_bitfile_line(line, self.line_number, &mut self.words)?
+                }
+                BitfileFormat::IntelHex => {
+                    if line.len() > MAX_BITFILE_LINE_BYTES {
+                        return Err(line_error(
+                            self.line_number,
+                            MAX_BITFILE_LINE_BYTES,
+                            line,
+                            "line exceeds maximum length",
+                        ));
+                    }
+                    self.hex
+                        .parse_line(line, self.line_number, &mut self.words)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses an in-memory bitfile on up to `threads` worker threads.
+///
+/// The input is split into shards on line boundaries. Words never span a
+/// line, so every shard can be parsed independently; results are
+/// concatenated in input order and errors report absolute line numbers.
+pub fn parse_bitfile_parallel(data: &[u8], threads: usize) -> Result<Vec<u16>> {
+    if data.is_empty() {
+        return Err(Error::InvalidBitfile("bitfile is empty"));
+    }
+    let shards = shard_lines(data, threads.max(1));
+    let results = if shards.len() <= 1 || data.len() < PARALLEL_PARSE_MIN_BYTES {
+        vec![parse_bitfile_shard(data)]
+    } else {
+        thread::scope(|scope| {
+            let workers = shards
+                .iter()
+                .map(|shard| scope.spawn(|| parse_bitfile_shard(shard)))
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("bitfile parser thread panicked"))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let mut program_data = Vec::with_capacity(results.iter().map(|r| r.words.len()).sum());
+    let mut first_line = 0usize;
+    for shard in results {
+        if let Some(err) = shard.error {
+            return Err(err.into_error(first_line));
+        }
+        program_data.extend_from_slice(&shard.words);
+        first_line += shard.lines;
     }
 
     if program_data.is_empty() {
@@ -83,12 +854,535 @@ pub fn load_bitfile_from_reader<R: BufRead>(reader: R) -> Result<Vec<u16>> {
     Ok(program_data)
 }
 
+/// An [`Error::InvalidBitfileLine`] for the token at byte `offset`.
+/// Columns are byte offsets; a valid line is all ASCII.
+pub(crate) fn line_error(
+    line_number: usize,
+    offset: usize,
+    token: &str,
+    reason: &'static str,
+) -> Error {
+    Error::InvalidBitfileLine {
+        line: line_number,
+        column: offset + 1,
+        excerpt: token.chars().take(EXCERPT_CHARS).collect(),
+        reason,
+    }
+}
+
+fn parse_bitfile_line(line: &str, line_number: usize, out: &mut Vec<u16>) -> Result<()> {
+    let error = |offset, token, reason| line_error(line_number, offset, token, reason);
+
+    if line.len() > MAX_BITFILE_LINE_BYTES {
+        return Err(error(
+            MAX_BITFILE_LINE_BYTES,
+            line,
+            "line exceeds maximum length",
+        ));
+    }
+
+    let trimmed = line.trim_start();
+    let payload = trimmed.split_whitespace().next().unwrap_or_default();
+    if payload.is_empty() {
+        return Ok(());
+    }
+
+    let mut offset = line.len() - trimmed.len();
+    let segments = payload.split('_').count();
+    for (index, segment) in payload.split('_').enumerate() {
+        if segment.is_empty() {
+            let reason = if index + 1 == segments {
+                "trailing underscore"
+            } else {
+                "empty word segment"
+            };
+            return Err(error(offset.saturating_sub(1), payload, reason));
+        }
+        if let Some(bad) = segment.find(|c: char| !c.is_ascii_hexdigit()) {
+            return Err(error(
+                offset + bad,
+                segment,
+                "bitfile contains non-hexadecimal characters",
+            ));
+        }
+        if segment.len() > 4 {
+            return Err(error(
+                offset + 4,
+                segment,
+                "word has more than 4 hex digits",
+            ));
+        }
+
+        out.push(u16::from_str_radix(segment, 16).expect("checked hex digits"));
+        offset += segment.len() + 1;
+    }
+
+    Ok(())
+}
+
+/// Splits `data` into at most `count` pieces that each end on a newline
+/// (except possibly the last one).
+fn shard_lines(data: &[u8], count: usize) -> Vec<&[u8]> {
+    let target = data.len().div_ceil(count).max(1);
+    let mut shards = Vec::with_capacity(count);
+    let mut rest = data;
+    while !rest.is_empty() {
+        if shards.len() + 1 == count || rest.len() <= target {
+            shards.push(rest);
+            break;
+        }
+        let split = rest[target..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(rest.len(), |offset| target + offset + 1);
+        let (shard, tail) = rest.split_at(split);
+        shards.push(shard);
+        rest = tail;
+    }
+    shards
+}
+
+struct ShardResult {
+    words: Vec<u16>,
+    lines: usize,
+    error: Option<ShardError>,
+}
+
+enum ShardError {
+    Line { line: usize, err: Error },
+    Utf8,
+}
+
+impl ShardError {
+    fn into_error(self, first_line: usize) -> Error {
+        match self {
+            ShardError::Line { line, err } => match err {
+                Error::InvalidBitfileLine {
+                    column,
+                    excerpt,
+                    reason,
+                    ..
+                } => Error::InvalidBitfileLine {
+                    line: first_line + line,
+                    column,
+                    excerpt,
+                    reason,
+                },
+                other => other,
+            },
+            ShardError::Utf8 => Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )),
+        }
+    }
+}
+
+fn parse_bitfile_shard(shard: &[u8]) -> ShardResult {
+    let mut words = Vec::with_capacity(shard.len() / 5);
+    let mut lines = 0usize;
+    for line in shard.split_inclusive(|byte| *byte == b'\n') {
+        lines += 1;
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let error = match std::str::from_utf8(line) {
+            Ok(line) => parse_bitfile_line(line, lines, &mut words)
+                .err()
+                .map(|err| ShardError::Line { line: lines, err }),
+            Err(_) => Some(ShardError::Utf8),
+        };
+        if error.is_some() {
+            return ShardResult {
+                words,
+                lines,
+                error,
+            };
+        }
+    }
+    ShardResult {
+        words,
+        lines,
+        error: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::load_bitfile_from_reader;
-    use crate::Error;
+    use super::{
+        BitfileFormat, BitfileReader, ProgramOptions, ProgramOutcome, ProgramPhase, Progress,
+        SliceSource, bitstream_crc32, load_bitfile_from_reader, parse_bitfile,
+        parse_bitfile_parallel, program_flash_words, program_source, program_source_if_changed,
+        program_words, verify_crc,
+    };
+    use crate::{Board, BoardMode, Error, FlashAddress, FlashRange};
     use std::io::Cursor;
 
+    #[test]
+    fn in_memory_bitfiles_parse_like_readers() {
+        let data = "1234_abcd\r\n\n5678_9abc trailing\n";
+        assert_eq!(
+            parse_bitfile(data.as_bytes()).unwrap(),
+            load_bitfile_from_reader(Cursor::new(data)).unwrap()
+        );
+        assert!(matches!(
+            parse_bitfile(b"\n\n"),
+            Err(Error::InvalidBitfile("bitfile produced no data"))
+        ));
+    }
+
+    #[test]
+    fn parsed_words_program_the_mock_board() {
+        let mut board = Board::open_mock().unwrap();
+        program_words(&mut board, &[0x1234; 3000], &mut Progress::silent()).unwrap();
+        assert!(board.config().is_programmed());
+        assert_eq!(board.current_operation(), None);
+    }
+
+    #[test]
+    fn bitstream_crc_matches_the_ieee_check_value() {
+        // "12345678" as little-endian words.
+        assert_eq!(
+            bitstream_crc32(&[0x3231, 0x3433, 0x3635, 0x3837]),
+            0x9ae0_daaf
+        );
+    }
+
+    #[test]
+    fn recorded_crc_verifies_the_resident_design() {
+        let mut board = Board::open_mock().unwrap();
+        let words = [0x1234; 3000];
+        program_words(&mut board, &words, &mut Progress::silent()).unwrap();
+        let crc = bitstream_crc32(&words);
+        board.write_bitstream_crc(crc).unwrap();
+
+        verify_crc(&mut board, crc).unwrap();
+        let other = bitstream_crc32(&words[1..]);
+        match verify_crc(&mut board, other).unwrap_err() {
+            Error::BitstreamMismatch { expected, actual } => {
+                assert_eq!((expected, actual), (other, crc));
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    fn program_if_changed(
+        board: &mut Board<crate::mock::MockTransport>,
+        words: &[u16],
+    ) -> crate::Result<ProgramOutcome> {
+        program_source_if_changed(board, bitstream_crc32(words), || {
+            Ok(SliceSource::new(words))
+        })
+    }
+
+    #[test]
+    fn formats_are_detected_from_the_first_bytes() {
+        assert_eq!(BitfileFormat::detect(b""), Some(BitfileFormat::SmimsText));
+        assert_eq!(
+            BitfileFormat::detect(b"\n 1234_abcd\n"),
+            Some(BitfileFormat::SmimsText)
+        );
+        assert_eq!(
+            BitfileFormat::detect(b"12g4\n"),
+            Some(BitfileFormat::SmimsText)
+        );
+        assert_eq!(
+            BitfileFormat::detect(b"\r\n:020000"),
+            Some(BitfileFormat::IntelHex)
+        );
+        // Binary data, a UTF-8 byte order mark, UTF-16 text, a stray NUL.
+        for head in [
+            &[0xff, 0x00, 0x99][..],
+            b"\xef\xbb\xbf1234_abcd\n",
+            b"1\x002\x003\x004\x00",
+            b"1234_\x00abcd\n",
+        ] {
+            assert_eq!(BitfileFormat::detect(head), None, "{head:?}");
+            assert!(matches!(
+                load_bitfile_from_reader(head),
+                Err(Error::InvalidBitfile(reason)) if reason.starts_with("unrecognized")
+            ));
+        }
+    }
+
+    #[test]
+    fn binary_bitfiles_are_read_as_little_endian_words() {
+        let bytes = [0x34, 0x12, 0xcd, 0xab, 0x00, 0xff, 0x78, 0x56];
+        // A tiny buffer splits words across refills.
+        let mut reader =
+            BitfileReader::with_chunk_words(std::io::BufReader::with_capacity(3, &bytes[..]), 3);
+        reader.format = Some(BitfileFormat::Binary);
+        assert_eq!(
+            reader.next_chunk().unwrap(),
+            Some(&[0x1234, 0xabcd, 0xff00][..])
+        );
+        assert_eq!(reader.format(), Some(BitfileFormat::Binary));
+        assert_eq!(reader.next_chunk().unwrap(), Some(&[0x5678][..]));
+        assert_eq!(reader.next_chunk().unwrap(), None);
+
+        assert!(matches!(
+            super::read_all(&mut BitfileReader::with_format(
+                &bytes[..7],
+                BitfileFormat::Binary
+            )),
+            Err(Error::InvalidBitfile(
+                "binary bitfile has an odd number of bytes"
+            ))
+        ));
+    }
+
+    #[test]
+    fn program_options_force_the_format() {
+        let mut board = Board::open_mock().unwrap();
+        let words = [0x1234u16; 1500];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let path =
+            std::env::temp_dir().join(format!("vlfd-program-forced-{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let detected =
+            super::program_bitfile_with_options(&mut board, &path, &ProgramOptions::default());
+        let forced = super::program_bitfile_with_options(
+            &mut board,
+            &path,
+            &ProgramOptions {
+                verify: true,
+                format: Some(BitfileFormat::Binary),
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(detected, Err(Error::InvalidBitfile(_))));
+        forced.unwrap();
+        assert_eq!(board.config().bitstream_crc(), bitstream_crc32(&words));
+    }
+
+    #[test]
+    fn binary_that_looks_like_text_can_be_forced() {
+        let data = b"12345678";
+        assert!(matches!(
+            parse_bitfile(data),
+            Err(Error::InvalidBitfileLine { line: 1, .. })
+        ));
+        let mut reader = BitfileReader::with_format(&data[..], BitfileFormat::Binary);
+        assert_eq!(
+            reader.next_chunk().unwrap(),
+            Some(&[0x3231, 0x3433, 0x3635, 0x3837][..])
+        );
+    }
+
+    #[test]
+    fn bitfile_reader_yields_fixed_chunks_with_a_running_crc() {
+        let data = "0001_0002_0003\n\n0004_0005\n0006\n";
+        let mut reader = BitfileReader::with_chunk_words(Cursor::new(data), 4);
+        assert_eq!(reader.next_chunk().unwrap(), Some(&[1, 2, 3, 4][..]));
+        assert_eq!(reader.next_chunk().unwrap(), Some(&[5, 6][..]));
+        assert_eq!(reader.next_chunk().unwrap(), None);
+        assert_eq!(reader.words_read(), 6);
+        assert_eq!(reader.crc32(), bitstream_crc32(&[1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn streamed_parse_errors_abort_programming() {
+        let mut board = Board::open_mock().unwrap();
+        let mut data = "1234_5678\n".repeat(1000);
+        data.push_str("12_zz\n");
+        let mut source = BitfileReader::new(Cursor::new(data));
+        let err = program_source(&mut board, &mut source, &mut Progress::silent()).unwrap_err();
+        assert!(matches!(err, Error::InvalidBitfileLine { line: 1001, .. }));
+        assert_eq!(board.mode(), BoardMode::Control);
+        assert_eq!(board.current_operation(), None);
+        assert!(board.stats().words_written >= 1024);
+    }
+
+    #[test]
+    fn bad_bitfiles_fail_before_the_device_is_touched() {
+        let mut board = Board::open_mock().unwrap();
+        board.mock().take_commands();
+        let mut data = "1234_5678\n".repeat(1000);
+        data.push_str("12_zz\n");
+        let path =
+            std::env::temp_dir().join(format!("vlfd-program-bad-tail-{}.txt", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let err = super::program_bitfile(&mut board, &path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, Error::InvalidBitfileLine { line: 1001, .. }));
+        assert!(board.mock().take_commands().is_empty());
+        assert_eq!(board.stats().words_written, 0);
+    }
+
+    #[test]
+    fn unchanged_bitstreams_are_not_uploaded_again() {
+        let mut board = Board::open_mock().unwrap();
+        let words = [0x1234; 3000];
+        assert_eq!(
+            program_if_changed(&mut board, &words).unwrap(),
+            ProgramOutcome::Programmed
+        );
+        let writes = board.stats().words_written;
+        assert_eq!(
+            program_if_changed(&mut board, &words).unwrap(),
+            ProgramOutcome::AlreadyCurrent
+        );
+        assert_eq!(board.stats().words_written, writes);
+        assert_eq!(
+            program_if_changed(&mut board, &words[1..]).unwrap(),
+            ProgramOutcome::Programmed
+        );
+    }
+
+    #[test]
+    fn interrupted_programming_clears_the_recorded_crc() {
+        let mut board = Board::open_mock().unwrap();
+        let words = [0x1234; 3000];
+        program_if_changed(&mut board, &words).unwrap();
+
+        let source = board.cancel_source();
+        let mut cancel = |update: super::ProgramProgress| {
+            if update.phase == ProgramPhase::Uploading {
+                source.cancel();
+            }
+        };
+        program_words(&mut board, &words, &mut Progress::new(&mut cancel)).unwrap_err();
+        source.reset();
+
+        assert_eq!(board.refresh_config().unwrap().bitstream_crc(), 0);
+        assert_eq!(
+            program_if_changed(&mut board, &words).unwrap(),
+            ProgramOutcome::Programmed
+        );
+    }
+
+    #[test]
+    fn progress_is_reported_for_every_chunk() {
+        let mut board = Board::open_mock().unwrap();
+        let mut reports = Vec::new();
+        let mut record = |update| reports.push(update);
+        program_words(&mut board, &[0x1234; 3000], &mut Progress::new(&mut record)).unwrap();
+
+        let phases = reports.iter().map(|r| r.phase).collect::<Vec<_>>();
+        assert_eq!(phases.first(), Some(&ProgramPhase::Activating));
+        assert_eq!(phases.last(), Some(&ProgramPhase::Verifying));
+        let sent = reports
+            .iter()
+            .filter(|r| r.phase == ProgramPhase::Uploading)
+            .map(|r| r.words_sent)
+            .collect::<Vec<_>>();
+        assert_eq!(sent, [1024, 2048, 3000]);
+        assert!(reports.iter().all(|r| r.total_words == 3000));
+    }
+
+    #[test]
+    fn bitstreams_are_stored_in_flash_from_block_zero() {
+        let mut board = Board::open_mock().unwrap();
+        let geometry = board.config().flash_geometry();
+        let words: Vec<u16> = (0..1500).collect();
+        let mut reports = Vec::new();
+        let mut record = |update: super::ProgramProgress| reports.push(update.phase);
+        program_flash_words(&mut board, &words, &mut Progress::new(&mut record)).unwrap();
+        assert_eq!(reports.first(), Some(&ProgramPhase::Activating));
+        assert_eq!(reports.last(), Some(&ProgramPhase::Verifying));
+
+        let end = FlashAddress {
+            block: 5,
+            cluster: geometry.block_size - 1,
+        };
+        let stored = board
+            .flash_read(FlashRange {
+                start: FlashAddress::default(),
+                end,
+            })
+            .unwrap();
+        assert_eq!(stored[..words.len()], words);
+
+        // Blocks holding the user data slots are off limits.
+        let room = super::bitstream_flash_words(&geometry);
+        assert!(matches!(
+            program_flash_words(&mut board, &vec![0; room + 1], &mut Progress::silent()),
+            Err(Error::FlashCapacityExceeded { available, .. }) if available == room
+        ));
+    }
+
+    #[test]
+    fn flashing_over_a_stored_image_leaves_it_untouched() {
+        let mut board = Board::open_mock().unwrap();
+        let first: Vec<u16> = (0..1500).collect();
+        program_flash_words(&mut board, &first, &mut Progress::silent()).unwrap();
+        let range = board
+            .config()
+            .flash_geometry()
+            .range_for_len(FlashAddress::default(), first.len())
+            .unwrap();
+        let before = board.flash_read(range).unwrap();
+
+        let second: Vec<u16> = (0..1500).map(|word| !word).collect();
+        assert!(matches!(
+            program_flash_words(&mut board, &second, &mut Progress::silent()),
+            Err(Error::FlashNotErased {
+                block: 0,
+                cluster: 0
+            })
+        ));
+        assert_eq!(board.flash_read(range).unwrap(), before);
+        assert_eq!(board.mode(), BoardMode::Control);
+    }
+
+    #[test]
+    fn flash_programming_can_be_cancelled() {
+        let mut board = Board::open_mock().unwrap();
+        let source = board.cancel_source();
+        let mut cancel = |update: super::ProgramProgress| {
+            if update.phase == ProgramPhase::Uploading {
+                source.cancel();
+            }
+        };
+        let err = program_flash_words(&mut board, &[0; 3000], &mut Progress::new(&mut cancel))
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled("flash_write")));
+        assert_eq!(board.mode(), BoardMode::Control);
+        assert_eq!(board.config().flash_read_end_block_addr(), 0);
+    }
+
+    #[test]
+    fn panicking_progress_callback_aborts_programming() {
+        let mut board = Board::open_mock().unwrap();
+        let mut explode = |update: super::ProgramProgress| {
+            if update.phase == ProgramPhase::Uploading {
+                panic!("progress bar went away");
+            }
+        };
+        let err = program_words(
+            &mut board,
+            &[0x1234; 3000],
+            &mut Progress::new(&mut explode),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::CallbackPanicked("program progress")));
+        assert_eq!(board.mode(), BoardMode::Control);
+        assert_eq!(board.current_operation(), None);
+    }
+
+    #[test]
+    fn cancelled_programming_leaves_the_board_ready_to_retry() {
+        let mut board = Board::open_mock().unwrap();
+        let source = board.cancel_source();
+        let mut cancel_midway = |update: super::ProgramProgress| {
+            if update.words_sent >= 1024 {
+                source.cancel();
+            }
+        };
+        let err = program_words(
+            &mut board,
+            &[0x1234; 3000],
+            &mut Progress::new(&mut cancel_midway),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::Cancelled("program")));
+        assert_eq!(board.mode(), BoardMode::Control);
+
+        board.cancel_source().reset();
+        program_words(&mut board, &[0x1234; 3000], &mut Progress::silent()).unwrap();
+        assert!(board.config().is_programmed());
+    }
+
     #[test]
     fn parses_cpp_style_bitfile_lines_into_words() {
         let data = "1234_abcd\n5678_9abc trailing\n";
@@ -101,11 +1395,124 @@ mod tests {
         let err =
             load_bitfile_from_reader(Cursor::new("1234_gggg\n")).expect_err("parse should fail");
         match err {
-            Error::InvalidBitfileLine { line, reason } => {
-                assert_eq!(line, 1);
+            Error::InvalidBitfileLine {
+                line,
+                column,
+                excerpt,
+                reason,
+            } => {
+                assert_eq!((line, column), (1, 6));
+                assert_eq!(excerpt, "gggg");
                 assert_eq!(reason, "bitfile contains non-hexadecimal characters");
             }
             other => panic!("unexpected error: {other}"),
         }
     }
+
+    fn located_error(data: &str) -> (usize, usize, String, &'static str) {
+        match load_bitfile_from_reader(Cursor::new(data)).expect_err("parse should fail") {
+            Error::InvalidBitfileLine {
+                line,
+                column,
+                excerpt,
+                reason,
+            } => (line, column, excerpt, reason),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn malformed_words_report_distinct_locations() {
+        assert_eq!(
+            located_error("1234_abcd\n  1234_\n"),
+            (2, 7, "1234_".into(), "trailing underscore")
+        );
+        assert_eq!(
+            located_error("1234__abcd\n"),
+            (1, 5, "1234__abcd".into(), "empty word segment")
+        );
+        assert_eq!(
+            located_error("0000\n1234_abcde\n"),
+            (2, 10, "abcde".into(), "word has more than 4 hex digits")
+        );
+        assert_eq!(
+            located_error("12x4\n"),
+            (
+                1,
+                3,
+                "12x4".into(),
+                "bitfile contains non-hexadecimal characters"
+            )
+        );
+    }
+
+    #[test]
+    fn empty_bitfiles_are_distinguished_from_blank_ones() {
+        for data in ["", "\n\n"] {
+            let reader = load_bitfile_from_reader(Cursor::new(data)).unwrap_err();
+            let memory = parse_bitfile(data.as_bytes()).unwrap_err();
+            assert_eq!(reader.to_string(), memory.to_string());
+        }
+        assert!(matches!(
+            parse_bitfile(b""),
+            Err(Error::InvalidBitfile("bitfile is empty"))
+        ));
+    }
+
+    #[test]
+    fn parallel_parse_matches_sequential_parse() {
+        let mut data = String::new();
+        for line in 0..200_000u32 {
+            data.push_str(&format!("{:04x}_{:04x}\r\n", line & 0xffff, !line & 0xffff));
+        }
+        let sequential = load_bitfile_from_reader(Cursor::new(&data)).unwrap();
+        for threads in [1, 2, 3, 8] {
+            let parallel = parse_bitfile_parallel(data.as_bytes(), threads).unwrap();
+            assert_eq!(parallel, sequential);
+        }
+    }
+
+    #[test]
+    fn parallel_parse_reports_absolute_line_numbers() {
+        let mut data = "0000_0000\n".repeat(150_000);
+        data.push_str("1234_zz\n");
+        data.push_str(&"0000_0000\n".repeat(10));
+        let err = parse_bitfile_parallel(data.as_bytes(), 4).expect_err("parse should fail");
+        match err {
+            Error::InvalidBitfileLine { line, .. } => assert_eq!(line, 150_001),
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn overlong_lines_are_rejected() {
+        let data = "0".repeat(super::MAX_BITFILE_LINE_BYTES + 1);
+        let err = load_bitfile_from_reader(Cursor::new(data)).expect_err("parse should fail");
+        match err {
+            Error::InvalidBitfileLine {
+                line,
+                column,
+                reason,
+                ..
+            } => {
+                assert_eq!((line, column), (1, super::MAX_BITFILE_LINE_BYTES + 1));
+                assert_eq!(reason, "line exceeds maximum length");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn endless_lines_are_rejected_without_buffering_them() {
+        let endless = std::io::BufReader::new(std::io::repeat(b'0'));
+        let mut reader = BitfileReader::new(endless);
+        assert!(matches!(
+            reader.next_chunk(),
+            Err(Error::InvalidBitfileLine {
+                reason: "line exceeds maximum length",
+                ..
+            })
+        ));
+        assert!(reader.line.len() <= super::MAX_BITFILE_LINE_BYTES + 2);
+    }
 }
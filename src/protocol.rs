@@ -0,0 +1,138 @@
+use crate::session::BoardMode;
+
+/// Prefix of every control-plane command; the second byte selects it.
+const CONTROL_PREFIX: u8 = 0x01;
+
+/// Commands written to the SMIMS engine's Command endpoint.
+///
+/// This is the full set from the legacy ProgramVLFD headers. Flash has only
+/// read and write modes: there is no erase command and no flash status in
+/// the configuration block, so erasing stays with the vendor tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Polls readiness; the firmware answers on the Sync endpoint.
+    SyncPoll,
+    /// Stops whatever the engine is doing. Sent without a sync handshake
+    /// because it is used to recover an engine that no longer answers.
+    ResetEngine,
+    /// Returns the engine to control mode.
+    CommandActive,
+    ReadConfig,
+    WriteConfig,
+    ReadEncryptTable,
+    ActivateFpgaProgrammer,
+    ActivateVeriComm,
+    ActivateVeriSdk,
+    ActivateFlashRead,
+    ActivateVeriInstrument,
+    ActivateVeriLink,
+    ActivateVeriSoc,
+    ActivateVeriCommPro,
+    ActivateFlashWrite,
+}
+
+impl Command {
+    #[cfg(any(test, feature = "mock"))]
+    pub(crate) const ALL: [Command; 15] = [
+        Command::SyncPoll,
+        Command::ResetEngine,
+        Command::CommandActive,
+        Command::ReadConfig,
+        Command::WriteConfig,
+        Command::ReadEncryptTable,
+        Command::ActivateFpgaProgrammer,
+        Command::ActivateVeriComm,
+        Command::ActivateVeriSdk,
+        Command::ActivateFlashRead,
+        Command::ActivateVeriInstrument,
+        Command::ActivateVeriLink,
+        Command::ActivateVeriSoc,
+        Command::ActivateVeriCommPro,
+        Command::ActivateFlashWrite,
+    ];
+
+    /// Bytes written to the Command endpoint.
+    pub const fn encode(self) -> &'static [u8] {
+        match self {
+            Command::SyncPoll => &[0x00],
+            Command::ResetEngine => &[0x02],
+            Command::CommandActive => &[CONTROL_PREFIX, 0x00],
+            Command::ReadConfig => &[CONTROL_PREFIX, 0x01],
+            Command::WriteConfig => &[CONTROL_PREFIX, 0x11],
+            Command::ReadEncryptTable => &[CONTROL_PREFIX, 0x0f],
+            Command::ActivateFpgaProgrammer => &[CONTROL_PREFIX, 0x02],
+            Command::ActivateVeriComm => &[CONTROL_PREFIX, 0x03],
+            Command::ActivateVeriSdk => &[CONTROL_PREFIX, 0x04],
+            Command::ActivateFlashRead => &[CONTROL_PREFIX, 0x05],
+            Command::ActivateVeriInstrument => &[CONTROL_PREFIX, 0x08],
+            Command::ActivateVeriLink => &[CONTROL_PREFIX, 0x09],
+            Command::ActivateVeriSoc => &[CONTROL_PREFIX, 0x0a],
+            Command::ActivateVeriCommPro => &[CONTROL_PREFIX, 0x0b],
+            Command::ActivateFlashWrite => &[CONTROL_PREFIX, 0x15],
+        }
+    }
+
+    /// Whether the sync handshake must succeed before the command is sent.
+    pub const fn needs_sync(self) -> bool {
+        !matches!(self, Command::SyncPoll | Command::ResetEngine)
+    }
+
+    /// The command that switches the engine into `mode`.
+    pub fn activate(mode: BoardMode) -> Option<Self> {
+        Some(match mode {
+            BoardMode::Control => Command::CommandActive,
+            BoardMode::FpgaProgrammer => Command::ActivateFpgaProgrammer,
+            BoardMode::VeriComm => Command::ActivateVeriComm,
+            BoardMode::VeriSdk => Command::ActivateVeriSdk,
+            BoardMode::FlashRead => Command::ActivateFlashRead,
+            BoardMode::VeriInstrument => Command::ActivateVeriInstrument,
+            BoardMode::VeriLink => Command::ActivateVeriLink,
+            BoardMode::VeriSoc => Command::ActivateVeriSoc,
+            BoardMode::VeriCommPro => Command::ActivateVeriCommPro,
+            BoardMode::FlashWrite => Command::ActivateFlashWrite,
+            BoardMode::Closed | BoardMode::Unknown => return None,
+        })
+    }
+
+    /// Inverse of [`Command::encode`].
+    #[cfg(any(test, feature = "mock"))]
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|command| command.encode() == bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+    use crate::BoardMode;
+
+    #[test]
+    fn commands_encode_to_the_firmware_byte_sequences() {
+        assert_eq!(Command::SyncPoll.encode(), [0x00]);
+        assert_eq!(Command::ResetEngine.encode(), [0x02]);
+        assert_eq!(Command::CommandActive.encode(), [0x01, 0x00]);
+        assert_eq!(Command::ReadConfig.encode(), [0x01, 0x01]);
+        assert_eq!(Command::WriteConfig.encode(), [0x01, 0x11]);
+        assert_eq!(Command::ReadEncryptTable.encode(), [0x01, 0x0f]);
+        assert_eq!(Command::ActivateFpgaProgrammer.encode(), [0x01, 0x02]);
+        assert_eq!(Command::ActivateVeriComm.encode(), [0x01, 0x03]);
+        assert_eq!(Command::ActivateVeriSdk.encode(), [0x01, 0x04]);
+        assert_eq!(Command::ActivateFlashRead.encode(), [0x01, 0x05]);
+        assert_eq!(Command::ActivateVeriInstrument.encode(), [0x01, 0x08]);
+        assert_eq!(Command::ActivateVeriLink.encode(), [0x01, 0x09]);
+        assert_eq!(Command::ActivateVeriSoc.encode(), [0x01, 0x0a]);
+        assert_eq!(Command::ActivateVeriCommPro.encode(), [0x01, 0x0b]);
+        assert_eq!(Command::ActivateFlashWrite.encode(), [0x01, 0x15]);
+
+        for command in Command::ALL {
+            assert_eq!(Command::decode(command.encode()), Some(command));
+        }
+        assert_eq!(
+            Command::activate(BoardMode::VeriComm),
+            Some(Command::ActivateVeriComm)
+        );
+        assert_eq!(Command::activate(BoardMode::Unknown), None);
+    }
+}
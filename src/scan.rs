@@ -0,0 +1,165 @@
+use crate::config::Config;
+use crate::error::{Error, ErrorKind, ErrorSummary, Result, UsbErrorKind};
+use crate::session::Board;
+use crate::usb::{self, HotplugDeviceInfo, TransportConfig, UsbDevice};
+
+/// [`ScanFailure::InitFailed`] step for initializing an opened board.
+const INITIALIZE_STEP: &str = "initialize";
+
+/// Inventory of one board that opened and initialized successfully.
+#[derive(Debug, Clone)]
+pub struct ScannedBoard {
+    pub location: HotplugDeviceInfo,
+    pub serial_number: Option<String>,
+    /// Configuration block read during initialization; carries the firmware
+    /// version, abilities, FIFO and flash geometry and programmed state.
+    pub config: Config,
+    /// Whether the firmware answered the sync handshake after
+    /// initialization.
+    pub ping_ok: bool,
+}
+
+/// Why a candidate board could not be inventoried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanFailure {
+    PermissionDenied,
+    /// The interface is claimed by another process; the board was left
+    /// untouched.
+    InUse,
+    OpenFailed(ErrorSummary),
+    /// The board opened but a later scan stage failed. `step` names the
+    /// stage, `"initialize"` for the handshake, encryption table and
+    /// configuration read; `error` carries the transfer-level context.
+    InitFailed {
+        step: &'static str,
+        error: ErrorSummary,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum ScanEntry {
+    Ready(ScannedBoard),
+    Failed {
+        location: HotplugDeviceInfo,
+        serial_number: Option<String>,
+        failure: ScanFailure,
+    },
+}
+
+impl ScanEntry {
+    pub fn location(&self) -> &HotplugDeviceInfo {
+        match self {
+            ScanEntry::Ready(board) => &board.location,
+            ScanEntry::Failed { location, .. } => location,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self, ScanEntry::Ready(_))
+    }
+}
+
+/// Opens every attached board in turn and reports its inventory.
+///
+/// Each board is opened, initialized, pinged and closed before the next one
+/// is touched, and a failure on one board never aborts the scan. Only an
+/// error enumerating the bus itself is returned as `Err`.
+pub fn scan() -> Result<Vec<ScanEntry>> {
+    scan_with_transport(TransportConfig::default())
+}
+
+pub fn scan_with_transport(transport: TransportConfig) -> Result<Vec<ScanEntry>> {
+    let devices =
+        usb::list_matching_devices(transport.device.vendor_id, transport.device.product_id)?;
+    Ok(devices
+        .iter()
+        .map(|device_info| {
+            let location = HotplugDeviceInfo::from_device_info(device_info);
+            let serial_number = device_info.serial_number().map(str::to_owned);
+            match scan_one(device_info, transport) {
+                Ok((config, ping_ok)) => ScanEntry::Ready(ScannedBoard {
+                    location,
+                    serial_number,
+                    config,
+                    ping_ok,
+                }),
+                Err(failure) => ScanEntry::Failed {
+                    location,
+                    serial_number,
+                    failure,
+                },
+            }
+        })
+        .collect())
+}
+
+fn scan_one(
+    device_info: &nusb::DeviceInfo,
+    transport: TransportConfig,
+) -> std::result::Result<(Config, bool), ScanFailure> {
+    let mut usb = UsbDevice::with_transport_config(transport).map_err(open_failure)?;
+    usb.open_device_info(device_info).map_err(open_failure)?;
+
+    let board = Board::from_transport(usb).map_err(|err| init_failure(INITIALIZE_STEP, err))?;
+    let ping_ok = board.ping().is_ok();
+    let config = board.config().clone();
+    // A close failure does not change what was learned about the board.
+    let _ = board.close();
+    Ok((config, ping_ok))
+}
+
+fn init_failure(step: &'static str, err: Error) -> ScanFailure {
+    ScanFailure::InitFailed {
+        step,
+        error: err.to_owned_summary(),
+    }
+}
+
+fn open_failure(err: Error) -> ScanFailure {
+    match err.kind() {
+        ErrorKind::Usb(UsbErrorKind::Access) => ScanFailure::PermissionDenied,
+        ErrorKind::Usb(UsbErrorKind::Busy) | ErrorKind::InterfaceClaimed => ScanFailure::InUse,
+        _ => ScanFailure::OpenFailed(err.to_owned_summary()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{INITIALIZE_STEP, ScanFailure, init_failure, open_failure};
+    use crate::error::Error;
+
+    fn usb_error(kind: std::io::ErrorKind) -> Error {
+        Error::Usb {
+            source: Box::new(std::io::Error::from(kind)),
+            context: "nusb_claim_interface",
+            transfer: None,
+        }
+    }
+
+    #[test]
+    fn open_failures_are_classified_for_the_inventory() {
+        assert_eq!(
+            open_failure(usb_error(std::io::ErrorKind::PermissionDenied)),
+            ScanFailure::PermissionDenied
+        );
+        assert_eq!(
+            open_failure(Error::InterfaceClaimed { interface: 0 }),
+            ScanFailure::InUse
+        );
+        let ScanFailure::OpenFailed(summary) = open_failure(Error::DeviceNotOpen) else {
+            panic!("expected a generic open failure");
+        };
+        assert_eq!(summary.message, "device is not open");
+    }
+
+    #[test]
+    fn init_failures_name_the_scan_stage_not_the_transfer() {
+        let ScanFailure::InitFailed { step, error } =
+            init_failure(INITIALIZE_STEP, usb_error(std::io::ErrorKind::TimedOut))
+        else {
+            panic!("expected an init failure");
+        };
+        assert_eq!(step, "initialize");
+        assert_eq!(error.context, Some("nusb_claim_interface"));
+    }
+}
@@ -0,0 +1,154 @@
+/// Word transform applied on the VeriComm IO path.
+///
+/// [`IoTransform::encode_tx`] runs on outgoing words after they are copied
+/// from the caller and before encryption; [`IoTransform::decode_rx`] runs
+/// on incoming words after decryption. Install one with
+/// [`crate::IoSession::set_transform`].
+pub trait IoTransform: Send {
+    fn encode_tx(&mut self, words: &mut [u16]);
+
+    fn decode_rx(&mut self, words: &mut [u16]);
+
+    /// Whether each output word depends only on the matching input word.
+    ///
+    /// Word-local transforms give the same result however a stream is split
+    /// into transfers, so they are the only ones accepted on pipelined
+    /// transfer windows, where frames are transformed out of step with the
+    /// caller's view of the stream.
+    fn is_word_local(&self) -> bool {
+        false
+    }
+}
+
+/// Reverses the bit order of every word (bit 0 becomes bit 15).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BitReverse;
+
+/// Swaps the two bytes of every word.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteSwap;
+
+/// Gray-encodes outgoing words and Gray-decodes incoming ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrayCode;
+
+impl IoTransform for BitReverse {
+    fn encode_tx(&mut self, words: &mut [u16]) {
+        words
+            .iter_mut()
+            .for_each(|word| *word = word.reverse_bits());
+    }
+
+    fn decode_rx(&mut self, words: &mut [u16]) {
+        self.encode_tx(words);
+    }
+
+    fn is_word_local(&self) -> bool {
+        true
+    }
+}
+
+impl IoTransform for ByteSwap {
+    fn encode_tx(&mut self, words: &mut [u16]) {
+        words.iter_mut().for_each(|word| *word = word.swap_bytes());
+    }
+
+    fn decode_rx(&mut self, words: &mut [u16]) {
+        self.encode_tx(words);
+    }
+
+    fn is_word_local(&self) -> bool {
+        true
+    }
+}
+
+impl IoTransform for GrayCode {
+    fn encode_tx(&mut self, words: &mut [u16]) {
+        words.iter_mut().for_each(|word| *word ^= *word >> 1);
+    }
+
+    fn decode_rx(&mut self, words: &mut [u16]) {
+        for word in words {
+            let mut value = *word;
+            let mut shift = *word >> 1;
+            while shift != 0 {
+                value ^= shift;
+                shift >>= 1;
+            }
+            *word = value;
+        }
+    }
+
+    fn is_word_local(&self) -> bool {
+        true
+    }
+}
+
+/// Chains two transforms: `A` then `B` on transmit, `B` then `A` on
+/// receive.
+impl<A: IoTransform, B: IoTransform> IoTransform for (A, B) {
+    fn encode_tx(&mut self, words: &mut [u16]) {
+        self.0.encode_tx(words);
+        self.1.encode_tx(words);
+    }
+
+    fn decode_rx(&mut self, words: &mut [u16]) {
+        self.1.decode_rx(words);
+        self.0.decode_rx(words);
+    }
+
+    fn is_word_local(&self) -> bool {
+        self.0.is_word_local() && self.1.is_word_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitReverse, ByteSwap, GrayCode, IoTransform};
+
+    fn round_trip(transform: &mut impl IoTransform, words: &[u16]) -> Vec<u16> {
+        let mut buffer = words.to_vec();
+        transform.encode_tx(&mut buffer);
+        transform.decode_rx(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn built_ins_encode_known_values() {
+        let mut words = [0x0001, 0x1234];
+        BitReverse.encode_tx(&mut words);
+        assert_eq!(words, [0x8000, 0x2c48]);
+
+        let mut words = [0x1234];
+        ByteSwap.encode_tx(&mut words);
+        assert_eq!(words, [0x3412]);
+
+        let mut words = [0, 1, 2, 3, 4, 0xffff];
+        GrayCode.encode_tx(&mut words);
+        assert_eq!(words, [0, 1, 3, 2, 6, 0x8000]);
+    }
+
+    #[test]
+    fn built_ins_and_chains_round_trip() {
+        let words = (0..=u16::MAX).step_by(97).collect::<Vec<_>>();
+        assert_eq!(round_trip(&mut BitReverse, &words), words);
+        assert_eq!(round_trip(&mut ByteSwap, &words), words);
+        assert_eq!(round_trip(&mut GrayCode, &words), words);
+        assert_eq!(round_trip(&mut (GrayCode, BitReverse), &words), words);
+    }
+
+    #[test]
+    fn word_local_transforms_ignore_chunk_boundaries() {
+        let words = (0..64u16).map(|word| word * 1031).collect::<Vec<_>>();
+        let mut whole = words.clone();
+        let mut chained = (ByteSwap, GrayCode);
+        assert!(chained.is_word_local());
+        chained.encode_tx(&mut whole);
+
+        let mut chunked = words.clone();
+        for chunk in chunked.chunks_mut(7) {
+            chained.encode_tx(chunk);
+        }
+        assert_eq!(chunked, whole);
+    }
+}
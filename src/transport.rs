@@ -0,0 +1,185 @@
+use crate::constants;
+use crate::error::{Error, Result};
+use crate::usb::{
+    Endpoint, ReconnectPolicy, SyncStrategy, TransportConfig, bytes_into_words, words_to_bytes,
+};
+use nusb::{
+    Endpoint as UsbEndpoint,
+    transfer::{Buffer, Bulk, Completion, EndpointDirection},
+};
+use std::time::Duration;
+
+/// Byte pipe to the SMIMS engine, addressed by [`Endpoint`].
+///
+/// [`crate::Board`] drives the whole protocol (sync handshake, encryption
+/// table, configuration block, mode switches and FIFO chunking) through
+/// this trait, so any implementation that answers like the firmware can
+/// stand in for the USB device used by default.
+///
+/// Words travel little-endian on the wire; the provided word methods
+/// convert to and from host order.
+pub trait Transport {
+    fn transport_config(&self) -> &TransportConfig;
+
+    /// How [`crate::Board`] waits for the firmware before each command.
+    fn sync_strategy(&self) -> SyncStrategy {
+        self.transport_config().sync_strategy
+    }
+
+    fn read_bytes(&self, endpoint: Endpoint, buffer: &mut [u8]) -> Result<()>;
+
+    fn write_bytes(&self, endpoint: Endpoint, buffer: &[u8]) -> Result<()>;
+
+    fn read_words(&self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<()> {
+        let mut raw = vec![0u8; std::mem::size_of_val(buffer)];
+        self.read_bytes(endpoint, &mut raw)?;
+        bytes_into_words(&raw, buffer);
+        Ok(())
+    }
+
+    fn write_words(&self, endpoint: Endpoint, buffer: &[u16]) -> Result<()> {
+        let mut raw = vec![0u8; std::mem::size_of_val(buffer)];
+        words_to_bytes(buffer, &mut raw);
+        self.write_bytes(endpoint, &raw)
+    }
+
+    /// Makes a single read attempt on `endpoint` and returns how many words
+    /// it stored in `buffer`; a timeout is not an error.
+    ///
+    /// The default reads all of `buffer` or, on timeout, nothing.
+    fn read_words_partial(&mut self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<usize> {
+        match self.read_words(endpoint, buffer) {
+            Ok(()) => Ok(buffer.len()),
+            Err(Error::TransferTimeout { .. }) => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Bulk timeout in effect for `endpoint`.
+    fn timeout_for(&self, _endpoint: Endpoint) -> Duration {
+        self.transport_config().usb_timeout
+    }
+
+    /// Replaces the timeout override for `endpoint` and returns the old one.
+    ///
+    /// Transports without per-endpoint timeouts ignore the call.
+    fn replace_timeout_override(
+        &mut self,
+        _endpoint: Endpoint,
+        _timeout: Option<Duration>,
+    ) -> Option<Duration> {
+        None
+    }
+
+    /// Bulk packet size of `endpoint`, in bytes.
+    fn max_packet_size(&self, _endpoint: Endpoint) -> usize {
+        constants::DEFAULT_MAX_PACKET_BYTES
+    }
+
+    /// Opens `endpoint` for pipelined VeriComm writes.
+    ///
+    /// `Ok(None)` means the transport has no pipeline; IO sessions then
+    /// fall back to one blocking write and read per transfer and cannot
+    /// open transfer windows.
+    fn pipeline_out_endpoint(
+        &self,
+        _endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        Ok(None)
+    }
+
+    /// Read side of [`Transport::pipeline_out_endpoint`].
+    fn pipeline_in_endpoint(
+        &self,
+        _endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        Ok(None)
+    }
+
+    /// Returns a function that opens `endpoint` for writing whenever it is
+    /// called, without borrowing the transport.
+    ///
+    /// Endpoints are claimed exclusively, so the caller should drop what
+    /// the opener returns as soon as it is done. `Ok(None)` means the
+    /// transport cannot open its endpoints on another thread's behalf.
+    fn out_endpoint_opener(&self, _endpoint: Endpoint) -> Result<Option<EndpointOpener>> {
+        Ok(None)
+    }
+
+    /// Clears stalls on every endpoint in use, as part of error recovery.
+    fn clear_halt_all(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Stalled transfers the transport has cleared and retried so far.
+    fn stall_retries(&self) -> u64 {
+        0
+    }
+
+    /// Reopens the device after it disappeared; see
+    /// [`crate::Board::set_reconnect_policy`].
+    fn reconnect(&mut self, _policy: &ReconnectPolicy) -> Result<()> {
+        Err(Error::FeatureUnavailable("reconnect"))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Opens an endpoint on demand; see [`Transport::out_endpoint_opener`].
+pub type EndpointOpener = Box<dyn FnMut() -> Result<Box<dyn PipelineEndpoint>> + Send>;
+
+/// Queue of in-flight bulk transfers on one endpoint, as opened by
+/// [`Transport::pipeline_out_endpoint`] and
+/// [`Transport::pipeline_in_endpoint`].
+///
+/// The methods mirror the queue API of [`nusb::Endpoint`], which implements
+/// this trait. Completions come back in submission order; an IN buffer is
+/// submitted empty with its requested length set and returns holding the
+/// bytes read.
+pub trait PipelineEndpoint: Send {
+    fn max_packet_size(&self) -> usize;
+
+    /// Transfers submitted and not yet returned by
+    /// [`wait_next_complete`](Self::wait_next_complete).
+    fn pending(&self) -> usize;
+
+    fn allocate(&self, len: usize) -> Buffer;
+
+    fn submit(&mut self, buffer: Buffer);
+
+    /// The oldest pending transfer once it finishes, or `None` if it is
+    /// still running after `timeout`.
+    fn wait_next_complete(&mut self, timeout: Duration) -> Option<Completion>;
+
+    /// Cancels every pending transfer; each still comes back through
+    /// [`wait_next_complete`](Self::wait_next_complete).
+    fn cancel_all(&mut self);
+}
+
+impl<Dir: EndpointDirection> PipelineEndpoint for UsbEndpoint<Bulk, Dir> {
+    fn max_packet_size(&self) -> usize {
+        UsbEndpoint::max_packet_size(self)
+    }
+
+    fn pending(&self) -> usize {
+        UsbEndpoint::pending(self)
+    }
+
+    fn allocate(&self, len: usize) -> Buffer {
+        UsbEndpoint::allocate(self, len)
+    }
+
+    fn submit(&mut self, buffer: Buffer) {
+        UsbEndpoint::submit(self, buffer);
+    }
+
+    fn wait_next_complete(&mut self, timeout: Duration) -> Option<Completion> {
+        UsbEndpoint::wait_next_complete(self, timeout)
+    }
+
+    fn cancel_all(&mut self) {
+        UsbEndpoint::cancel_all(self);
+    }
+}
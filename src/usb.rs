@@ -1,31 +1,97 @@
-use crate::error::{Error, Result};
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, TransferDirection, UsbErrorKind, UsbTransfer};
+use crate::transport::{EndpointOpener, PipelineEndpoint, Transport};
 use nusb::{
-    self, Device, DeviceId, DeviceInfo, Interface, MaybeFuture,
-    transfer::{Bulk, In, Out},
+    self, Device, DeviceId, DeviceInfo, GetDescriptorError, Interface, MaybeFuture,
+    descriptors::language_id::US_ENGLISH,
+    transfer::{Bulk, In, Out, TransferError},
 };
 use std::{
+    fmt,
     io::{Read, Write},
+    num::NonZeroU8,
     sync::{
         Arc,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver},
     },
     thread,
     time::Duration,
 };
 
-#[cfg(target_endian = "big")]
-compile_error!("vlfd-rs currently supports little-endian hosts only");
-
-const INTERFACE: u8 = 0;
 const HOTPLUG_POLL_INTERVAL: Duration = Duration::from_millis(100);
 const IO_BUFFER_SIZE: usize = 16 * 1024;
 
+/// Delay used when the Sync endpoint is missing and
+/// [`TransportConfig::detect_sync_endpoint`] falls back to a fixed delay.
+pub const DEFAULT_FIXED_SYNC_DELAY: Duration = Duration::from_millis(2);
+
+/// Number of events a [`Probe::hotplug_events`] channel holds before the
+/// watcher starts dropping new ones.
+pub const HOTPLUG_CHANNEL_CAPACITY: usize = 64;
+
+/// USB identity of the boards to open.
+///
+/// Defaults to the stock DW VID/PID on interface 0. Override it for boards
+/// re-flashed with a custom EZ-USB descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceOptions {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub interface: u8,
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        Self {
+            vendor_id: constants::DW_VID,
+            product_id: constants::DW_PID,
+            interface: 0,
+        }
+    }
+}
+
+/// How the host waits for the firmware to be ready for the next command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStrategy {
+    /// Poll the Sync endpoint until the firmware reports ready. The only
+    /// strategy that observes the firmware, and the only reliable one.
+    Endpoint,
+    /// Sleep for a fixed time instead. Works on firmware builds without a
+    /// Sync endpoint, but cannot tell when the firmware is actually ready:
+    /// a delay that is too short lets commands overrun a busy engine and be
+    /// lost silently, and every command pays the full delay.
+    FixedDelay(Duration),
+    /// Do not wait at all. Only safe when the caller paces commands itself.
+    None,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TransportConfig {
     pub usb_timeout: Duration,
+    /// [`SyncPolicy::timeout`] of boards opened with this config.
     pub sync_timeout: Duration,
     pub reset_on_open: bool,
     pub clear_halt_on_open: bool,
+    pub sync_strategy: SyncStrategy,
+    /// Probe for the Sync endpoint at open and fall back to
+    /// `FixedDelay(DEFAULT_FIXED_SYNC_DELAY)` when it is missing. Only
+    /// applies when `sync_strategy` is [`SyncStrategy::Endpoint`].
+    pub detect_sync_endpoint: bool,
+    /// Detach a kernel driver bound to the interface before claiming it,
+    /// and reattach it on close. Only has an effect on Linux.
+    pub detach_kernel_driver: bool,
+    /// How many times a bulk read or write that stalls before moving any
+    /// data is retried after clearing the halt. A stall mid-transfer and
+    /// other failures are never retried.
+    pub stall_retries: u32,
+    /// End bulk writes whose length is a multiple of the endpoint's max
+    /// packet size with a zero-length packet, so the FIFO core commits the
+    /// last packet immediately. Disable for old firmware that mishandles
+    /// zero-length packets.
+    pub zero_length_packets: bool,
+    /// Identity used by every open and reopen made with this config.
+    pub device: DeviceOptions,
 }
 
 impl Default for TransportConfig {
@@ -35,11 +101,76 @@ impl Default for TransportConfig {
             sync_timeout: Duration::from_secs(1),
             reset_on_open: false,
             clear_halt_on_open: true,
+            sync_strategy: SyncStrategy::Endpoint,
+            detect_sync_endpoint: true,
+            detach_kernel_driver: true,
+            stall_retries: 1,
+            zero_length_packets: true,
+            device: DeviceOptions::default(),
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Strategy to use for a device whose Sync endpoint is or is not
+    /// present.
+    pub(crate) fn resolve_sync_strategy(&self, sync_endpoint_present: bool) -> SyncStrategy {
+        match self.sync_strategy {
+            SyncStrategy::Endpoint if self.detect_sync_endpoint && !sync_endpoint_present => {
+                SyncStrategy::FixedDelay(DEFAULT_FIXED_SYNC_DELAY)
+            }
+            strategy => strategy,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// How a [`crate::Board`] waits for its device to come back after it
+/// disappeared from the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Number of reopen attempts before giving up.
+    pub attempts: u32,
+    /// Pause before each attempt.
+    pub delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 50,
+            delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// How a [`crate::Board`] polls the Sync endpoint before each command.
+///
+/// The first poll is sent immediately. After each poll that finds the
+/// firmware busy the board sleeps for the current interval, which starts at
+/// `initial_interval` and doubles up to `max_interval`. Zero intervals poll
+/// back to back, which keeps a CPU core busy and floods the bus while the
+/// firmware is slow; the default sleeps 1 ms, growing to 8 ms, so a ready
+/// board still answers on the first poll without any added delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncPolicy {
+    /// Total time to wait for the firmware before giving up with
+    /// [`crate::Error::SyncTimeout`].
+    pub timeout: Duration,
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Endpoint {
     FifoWrite = 0x02,
     Command = 0x04,
@@ -47,6 +178,74 @@ pub enum Endpoint {
     Sync = 0x88,
 }
 
+impl Endpoint {
+    pub(crate) const COUNT: usize = 4;
+
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Endpoint::FifoWrite => 0,
+            Endpoint::Command => 1,
+            Endpoint::FifoRead => 2,
+            Endpoint::Sync => 3,
+        }
+    }
+}
+
+/// Identity strings and revision read from the open device's descriptors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorInfo {
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+    /// `bcdDevice` from the device descriptor.
+    pub device_version: u16,
+}
+
+/// Physical USB location of a board.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceLocation {
+    /// Bus number and device address. Addresses are reassigned on every
+    /// re-enumeration, so this only identifies a board until it is
+    /// replugged or reset.
+    Address { bus: u8, address: u8 },
+    /// Bus number and hub port chain, stable across replugs as long as the
+    /// cabling does not change.
+    Ports { bus: u8, ports: Vec<u8> },
+}
+
+impl DeviceLocation {
+    pub fn matches(&self, device: &HotplugDeviceInfo) -> bool {
+        match self {
+            DeviceLocation::Address { bus, address } => {
+                device.bus_number == *bus && device.address == *address
+            }
+            DeviceLocation::Ports { bus, ports } => {
+                device.bus_number == *bus && device.port_numbers == *ports
+            }
+        }
+    }
+}
+
+impl fmt::Display for DeviceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceLocation::Address { bus, address } => {
+                write!(f, "bus {bus} address {address}")
+            }
+            DeviceLocation::Ports { bus, ports } => {
+                write!(f, "bus {bus} port ")?;
+                for (index, port) in ports.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(".")?;
+                    }
+                    write!(f, "{port}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HotplugEventKind {
     Arrived,
@@ -66,7 +265,7 @@ pub struct HotplugDeviceInfo {
 }
 
 impl HotplugDeviceInfo {
-    fn from_device_info(device: &DeviceInfo) -> Self {
+    pub(crate) fn from_device_info(device: &DeviceInfo) -> Self {
         Self {
             #[cfg(target_os = "linux")]
             bus_number: device.busnum(),
@@ -92,14 +291,31 @@ pub struct HotplugEvent {
     pub device: HotplugDeviceInfo,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct HotplugOptions {
     pub vendor_id: Option<u16>,
     pub product_id: Option<u16>,
     pub class_code: Option<u8>,
+    /// Only report devices on this bus.
+    pub bus_number: Option<u8>,
+    /// Only report devices behind this hub port chain, as in
+    /// [`HotplugDeviceInfo::port_numbers`]. Never matches on platforms that
+    /// do not report port chains.
+    pub port_numbers: Option<Vec<u8>>,
     pub enumerate: bool,
 }
 
+impl HotplugOptions {
+    fn matches_location(&self, device: &HotplugDeviceInfo) -> bool {
+        self.bus_number
+            .is_none_or(|bus_number| device.bus_number == bus_number)
+            && self
+                .port_numbers
+                .as_ref()
+                .is_none_or(|ports| device.port_numbers == *ports)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Probe {
     transport: TransportConfig,
@@ -125,12 +341,35 @@ impl Probe {
         UsbDevice::with_transport_config(self.transport)?
             .register_hotplug_callback(options, callback)
     }
+
+    /// Channel-based [`Probe::watch`], delivering events through a channel
+    /// holding up to [`HOTPLUG_CHANNEL_CAPACITY`] of them.
+    ///
+    /// Events that arrive while the channel is full are dropped, so a
+    /// stalled consumer cannot delay dropping the registration. Rescan
+    /// with [`crate::scan`] after falling behind.
+    pub fn hotplug_events(
+        &self,
+        options: HotplugOptions,
+    ) -> Result<(HotplugRegistration, Receiver<HotplugEvent>)> {
+        UsbDevice::with_transport_config(self.transport)?.hotplug_events(options)
+    }
 }
 
 pub struct UsbDevice {
     handle: Option<Device>,
     interface: Option<Interface>,
     transport: TransportConfig,
+    serial_number: Option<String>,
+    sync_strategy: SyncStrategy,
+    kernel_driver_detached: bool,
+    endpoint_timeouts: [Option<Duration>; Endpoint::COUNT],
+    /// Bytes received by partial reads past the last complete word.
+    read_carry: [Vec<u8>; Endpoint::COUNT],
+    /// Max packet size of each endpoint, read at open; 0 when unknown.
+    max_packet_sizes: [usize; Endpoint::COUNT],
+    /// Stalled transfers retried since this value was created.
+    stall_retries: AtomicU64,
 }
 
 impl UsbDevice {
@@ -138,10 +377,26 @@ impl UsbDevice {
         Ok(Self {
             handle: None,
             interface: None,
+            sync_strategy: transport.sync_strategy,
             transport,
+            serial_number: None,
+            kernel_driver_detached: false,
+            endpoint_timeouts: [None; Endpoint::COUNT],
+            read_carry: Default::default(),
+            max_packet_sizes: [0; Endpoint::COUNT],
+            stall_retries: AtomicU64::new(0),
         })
     }
 
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// Sync strategy in effect after probing the opened device.
+    pub fn sync_strategy(&self) -> SyncStrategy {
+        self.sync_strategy
+    }
+
     pub fn is_open(&self) -> bool {
         self.interface.is_some()
     }
@@ -150,41 +405,134 @@ impl UsbDevice {
         &self.transport
     }
 
+    /// Enables or disables terminating zero-length packets; see
+    /// [`TransportConfig::zero_length_packets`].
+    pub fn set_zero_length_packets(&mut self, enabled: bool) {
+        self.transport.zero_length_packets = enabled;
+    }
+
+    /// Sets the bulk transfer timeout for endpoints without an override.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.transport.usb_timeout = timeout;
+    }
+
+    /// Overrides the bulk transfer timeout for one endpoint.
+    pub fn set_timeout_for(&mut self, endpoint: Endpoint, timeout: Duration) {
+        self.endpoint_timeouts[endpoint.index()] = Some(timeout);
+    }
+
+    /// Removes an override set with [`UsbDevice::set_timeout_for`].
+    pub fn clear_timeout_for(&mut self, endpoint: Endpoint) {
+        self.endpoint_timeouts[endpoint.index()] = None;
+    }
+
+    /// Bulk transfer timeout in effect for `endpoint`.
+    pub fn timeout_for(&self, endpoint: Endpoint) -> Duration {
+        self.endpoint_timeouts[endpoint.index()].unwrap_or(self.transport.usb_timeout)
+    }
+
     pub fn open(&mut self, vid: u16, pid: u16) -> Result<()> {
         if self.is_open() {
             return Ok(());
         }
 
+        let device_info = list_matching_devices(vid, pid)?
+            .into_iter()
+            .next()
+            .ok_or(Error::DeviceNotFound { vid, pid })?;
+        self.open_device_info(&device_info)
+    }
+
+    /// Opens the matching device whose serial string descriptor equals
+    /// `serial`. Devices that report no serial number are skipped.
+    pub fn open_by_serial(&mut self, vid: u16, pid: u16, serial: &str) -> Result<()> {
+        if self.is_open() {
+            return Ok(());
+        }
+
+        let device_info = list_matching_devices(vid, pid)?
+            .into_iter()
+            .find(|device| device.serial_number() == Some(serial))
+            .ok_or_else(|| Error::SerialNotFound(serial.to_owned()))?;
+        self.open_device_info(&device_info)
+    }
+
+    /// Opens the device at `location`, checking that it reports `vid:pid`.
+    pub fn open_at(&mut self, vid: u16, pid: u16, location: &DeviceLocation) -> Result<()> {
+        if self.is_open() {
+            return Ok(());
+        }
+
         let device_info = nusb::list_devices()
             .wait()
             .map_err(|err| usb_error(err, "nusb_list_devices"))?
-            .find(|device| device.vendor_id() == vid && device.product_id() == pid)
+            .find(|device| location.matches(&HotplugDeviceInfo::from_device_info(device)))
+            .ok_or_else(|| Error::NoDeviceAtLocation(location.to_string()))?;
+        if device_info.vendor_id() != vid || device_info.product_id() != pid {
+            return Err(Error::NotABoard {
+                location: location.to_string(),
+                vid: device_info.vendor_id(),
+                pid: device_info.product_id(),
+            });
+        }
+        self.open_device_info(&device_info)
+    }
+
+    /// Opens the first matching device without the reset and clear-halt
+    /// steps, for callers that validate device state themselves.
+    pub(crate) fn open_warm(&mut self, vid: u16, pid: u16) -> Result<()> {
+        let device_info = list_matching_devices(vid, pid)?
+            .into_iter()
+            .next()
             .ok_or(Error::DeviceNotFound { vid, pid })?;
+        self.open_device(&device_info, false)
+    }
+
+    /// Opens and claims a specific enumerated device.
+    pub(crate) fn open_device_info(&mut self, device_info: &DeviceInfo) -> Result<()> {
+        self.open_device(device_info, true)
+    }
+
+    fn open_device(&mut self, device_info: &DeviceInfo, cold: bool) -> Result<()> {
+        if self.is_open() {
+            return Ok(());
+        }
 
         let device = device_info
             .open()
             .wait()
             .map_err(|err| usb_error(err, "nusb_open_device"))?;
 
-        if self.transport.reset_on_open {
+        if cold && self.transport.reset_on_open {
             device
                 .reset()
                 .wait()
                 .map_err(|err| usb_error(err, "nusb_reset_device"))?;
         }
 
-        let interface = device
-            .detach_and_claim_interface(INTERFACE)
-            .wait()
-            .map_err(|err| usb_error(err, "nusb_claim_interface"))?;
+        let (interface, kernel_driver_detached) = self.claim_interface(&device)?;
 
+        let sync_endpoint_present = interface.endpoint::<Bulk, In>(Endpoint::Sync as u8).is_ok();
+        let mut max_packet_sizes = [0; Endpoint::COUNT];
+        for endpoint in [Endpoint::FifoWrite, Endpoint::Command] {
+            if let Ok(ep) = interface.endpoint::<Bulk, Out>(endpoint as u8) {
+                max_packet_sizes[endpoint.index()] = ep.max_packet_size();
+            }
+        }
         let mut usb_device = Self {
             handle: Some(device),
             interface: Some(interface),
             transport: self.transport,
+            serial_number: device_info.serial_number().map(str::to_owned),
+            sync_strategy: self.transport.resolve_sync_strategy(sync_endpoint_present),
+            kernel_driver_detached,
+            endpoint_timeouts: self.endpoint_timeouts,
+            read_carry: Default::default(),
+            max_packet_sizes,
+            stall_retries: AtomicU64::new(self.stall_retries.load(Ordering::Relaxed)),
         };
 
-        if usb_device.transport.clear_halt_on_open {
+        if cold && usb_device.transport.clear_halt_on_open {
             usb_device.clear_halt_all()?;
         }
 
@@ -192,30 +540,367 @@ impl UsbDevice {
         Ok(())
     }
 
+    /// Claims the configured interface, detaching a bound kernel driver
+    /// first if the claim is refused. Returns whether a driver was detached.
+    fn claim_interface(&self, device: &Device) -> Result<(Interface, bool)> {
+        let number = self.transport.device.interface;
+        let claimed = |err: nusb::Error| match err.kind() {
+            nusb::ErrorKind::Busy => Error::InterfaceClaimed { interface: number },
+            _ => usb_error(err, "nusb_claim_interface"),
+        };
+        match device.claim_interface(number).wait() {
+            Ok(interface) => Ok((interface, false)),
+            #[cfg(target_os = "linux")]
+            Err(err)
+                if err.kind() == nusb::ErrorKind::Busy && self.transport.detach_kernel_driver =>
+            {
+                // Detaching fails when no kernel driver is bound, in which
+                // case another process holds the interface and the second
+                // claim reports that.
+                let detached = device.detach_kernel_driver(number).is_ok();
+                let interface = device.claim_interface(number).wait().map_err(claimed)?;
+                Ok((interface, detached))
+            }
+            Err(err) => Err(claimed(err)),
+        }
+    }
+
     pub fn close(&mut self) -> Result<()> {
         self.interface.take();
+        #[cfg(target_os = "linux")]
+        if self.kernel_driver_detached {
+            if let Some(device) = self.handle.as_ref() {
+                // Best effort: the board is already released, and the kernel
+                // may have rebound the driver on its own.
+                let _ = device.attach_kernel_driver(self.transport.device.interface);
+            }
+        }
+        self.kernel_driver_detached = false;
+        self.read_carry.iter_mut().for_each(Vec::clear);
         self.handle.take();
+        self.serial_number = None;
         Ok(())
     }
 
-    pub fn read_bytes(&self, endpoint: Endpoint, buffer: &mut [u8]) -> Result<()> {
+    /// Closes the stale handle and reopens the device once it reappears.
+    ///
+    /// A device that reported a serial number is only reopened under the
+    /// same serial. Endpoint timeouts and other settings are kept.
+    pub fn reconnect(&mut self, policy: &ReconnectPolicy) -> Result<()> {
+        let serial = self.serial_number.clone();
+        self.close()?;
+        let vid = self.transport.device.vendor_id;
+        let pid = self.transport.device.product_id;
+        let mut last_error = Error::DeviceNotFound { vid, pid };
+        for _ in 0..policy.attempts {
+            thread::sleep(policy.delay);
+            let reopened = match serial.as_deref() {
+                Some(serial) => self.open_by_serial(vid, pid, serial),
+                None => self.open(vid, pid),
+            };
+            match reopened {
+                Ok(()) => return Ok(()),
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(?endpoint, len = buffer.len()),
+            err
+        )
+    )]
+    pub fn read_bytes_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &mut [u8],
+        timeout: Duration,
+    ) -> Result<()> {
         let interface = self.interface.as_ref().ok_or(Error::DeviceNotOpen)?;
-        bulk_read(interface, endpoint, buffer, self.transport.usb_timeout)
+        self.retry_stalled(endpoint, || bulk_read(interface, endpoint, buffer, timeout))
+    }
+
+    pub fn read_bytes(&self, endpoint: Endpoint, buffer: &mut [u8]) -> Result<()> {
+        self.read_bytes_timeout(endpoint, buffer, self.timeout_for(endpoint))
     }
 
+    /// Reads little-endian words from the wire into host-order `buffer`.
     pub fn read_words(&self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<()> {
-        let raw = words_as_bytes_mut(buffer);
-        self.read_bytes(endpoint, raw)
+        self.read_words_timeout(endpoint, buffer, self.timeout_for(endpoint))
+    }
+
+    /// [`UsbDevice::read_words`] with `timeout` in place of the endpoint's
+    /// timeout for this call only.
+    #[cfg(target_endian = "little")]
+    pub fn read_words_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &mut [u16],
+        timeout: Duration,
+    ) -> Result<()> {
+        self.read_bytes_timeout(endpoint, words_as_bytes_mut(buffer), timeout)
+            .map_err(mark_word_transfer)
+    }
+
+    #[cfg(target_endian = "big")]
+    pub fn read_words_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &mut [u16],
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut raw = vec![0u8; std::mem::size_of_val(buffer)];
+        self.read_bytes_timeout(endpoint, &mut raw, timeout)
+            .map_err(mark_word_transfer)?;
+        bytes_into_words(&raw, buffer);
+        Ok(())
+    }
+
+    /// Makes a single bulk read attempt and stores the complete words
+    /// received, returning how many were stored.
+    ///
+    /// A timeout is not an error: whatever arrived before it, possibly
+    /// nothing, is returned. Bytes that do not fit in `buffer` or that end
+    /// in half a word are carried over to the next call on `endpoint`, and
+    /// when the carried bytes alone fill `buffer` no transfer is made.
+    pub fn read_words_partial(&mut self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<usize> {
+        let wanted = std::mem::size_of_val(buffer);
+        if self.read_carry[endpoint.index()].len() < wanted {
+            let received =
+                self.read_once(endpoint, wanted - self.read_carry[endpoint.index()].len())?;
+            self.read_carry[endpoint.index()].extend_from_slice(&received);
+        }
+        let carry = &mut self.read_carry[endpoint.index()];
+        let words = buffer.len().min(carry.len() / 2);
+        bytes_into_words(&carry[..words * 2], &mut buffer[..words]);
+        carry.drain(..words * 2);
+        Ok(words)
+    }
+
+    /// Submits one IN transfer for at least `len` bytes, rounded up to whole
+    /// packets, and returns what arrived before completion or timeout.
+    fn read_once(&self, endpoint: Endpoint, len: usize) -> Result<Vec<u8>> {
+        let mut ep = self.open_in_endpoint(endpoint)?;
+        let max_packet = ep.max_packet_size().max(1);
+        let request = len.div_ceil(max_packet).max(1) * max_packet;
+        let mut transfer = ep.allocate(request);
+        transfer.set_requested_len(request);
+        ep.submit(transfer);
+        let completion = match ep.wait_next_complete(self.timeout_for(endpoint)) {
+            Some(completion) => completion,
+            None => {
+                ep.cancel_all();
+                loop {
+                    if let Some(completion) = ep.wait_next_complete(Duration::from_secs(1)) {
+                        break completion;
+                    }
+                }
+            }
+        };
+        match completion.status {
+            Ok(()) | Err(TransferError::Cancelled) => {}
+            Err(err) => {
+                return Err(Error::Usb {
+                    source: Box::new(err),
+                    context: "nusb_bulk_read",
+                    transfer: Some(UsbTransfer {
+                        endpoint,
+                        direction: TransferDirection::In,
+                        requested: request,
+                        completed: completion.actual_len,
+                        word_aligned: None,
+                    }),
+                });
+            }
+        }
+        Ok(completion.buffer[..completion.actual_len].to_vec())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(?endpoint, len = buffer.len()),
+            err
+        )
+    )]
+    pub fn write_bytes_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &[u8],
+        timeout: Duration,
+    ) -> Result<()> {
+        self.write_parts_timeout(endpoint, &[buffer], timeout)
     }
 
     pub fn write_bytes(&self, endpoint: Endpoint, buffer: &[u8]) -> Result<()> {
+        self.write_bytes_timeout(endpoint, buffer, self.timeout_for(endpoint))
+    }
+
+    /// Writes the concatenation of `parts` as one bulk transfer.
+    fn write_parts_timeout(
+        &self,
+        endpoint: Endpoint,
+        parts: &[&[u8]],
+        timeout: Duration,
+    ) -> Result<()> {
         let interface = self.interface.as_ref().ok_or(Error::DeviceNotOpen)?;
-        bulk_write(interface, endpoint, buffer, self.transport.usb_timeout)
+        let len = parts.iter().map(|part| part.len()).sum();
+        let zero_length_packet = self.transport.zero_length_packets
+            && needs_zero_length_packet(len, self.max_packet_sizes[endpoint.index()]);
+        self.retry_stalled(endpoint, || {
+            bulk_write(interface, endpoint, parts, timeout, zero_length_packet)
+        })
+    }
+
+    /// Writes the concatenation of `parts` as one bulk transfer without
+    /// first copying them into a single buffer.
+    #[cfg(target_endian = "little")]
+    pub fn write_words_vectored<'a>(
+        &self,
+        endpoint: Endpoint,
+        parts: impl IntoIterator<Item = &'a [u16]>,
+    ) -> Result<()> {
+        let parts = parts.into_iter().map(words_as_bytes).collect::<Vec<_>>();
+        self.write_parts_timeout(endpoint, &parts, self.timeout_for(endpoint))
+    }
+
+    #[cfg(target_endian = "big")]
+    pub fn write_words_vectored<'a>(
+        &self,
+        endpoint: Endpoint,
+        parts: impl IntoIterator<Item = &'a [u16]>,
+    ) -> Result<()> {
+        let raw = parts
+            .into_iter()
+            .flatten()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.write_bytes(endpoint, &raw)
+    }
+
+    /// Runs `transfer`, clearing the halt and starting it again each time
+    /// the endpoint stalls before any data moved, up to
+    /// [`TransportConfig::stall_retries`] times.
+    ///
+    /// A stall after part of the transfer went through is returned as is:
+    /// replaying it would send or consume FIFO words twice and put the key
+    /// streams out of step with the device.
+    fn retry_stalled(
+        &self,
+        endpoint: Endpoint,
+        mut transfer: impl FnMut() -> Result<()>,
+    ) -> Result<()> {
+        let mut attempts = 1;
+        loop {
+            match transfer() {
+                Err(err)
+                    if is_stall(&err)
+                        && moved_nothing(&err)
+                        && attempts <= self.transport.stall_retries =>
+                {
+                    self.clear_halt(endpoint)?;
+                    self.stall_retries.fetch_add(1, Ordering::Relaxed);
+                    attempts += 1;
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(?endpoint, attempt = attempts, "retrying stalled transfer");
+                }
+                Err(err) if attempts > 1 => {
+                    return Err(Error::Retried {
+                        attempts,
+                        source: Box::new(err),
+                    });
+                }
+                result => return result,
+            }
+        }
     }
 
+    /// Writes host-order `buffer` to the wire as little-endian words.
     pub fn write_words(&self, endpoint: Endpoint, buffer: &[u16]) -> Result<()> {
-        let raw = words_as_bytes(buffer);
-        self.write_bytes(endpoint, raw)
+        self.write_words_timeout(endpoint, buffer, self.timeout_for(endpoint))
+    }
+
+    /// [`UsbDevice::write_words`] with `timeout` in place of the endpoint's
+    /// timeout for this call only.
+    #[cfg(target_endian = "little")]
+    pub fn write_words_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &[u16],
+        timeout: Duration,
+    ) -> Result<()> {
+        self.write_bytes_timeout(endpoint, words_as_bytes(buffer), timeout)
+            .map_err(mark_word_transfer)
+    }
+
+    #[cfg(target_endian = "big")]
+    pub fn write_words_timeout(
+        &self,
+        endpoint: Endpoint,
+        buffer: &[u16],
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut raw = vec![0u8; std::mem::size_of_val(buffer)];
+        words_to_bytes(buffer, &mut raw);
+        self.write_bytes_timeout(endpoint, &raw, timeout)
+            .map_err(mark_word_transfer)
+    }
+
+    /// Reads the manufacturer, product and serial strings and the device
+    /// revision from the open device handle.
+    ///
+    /// A string the device does not declare, or that it keeps refusing, is
+    /// reported as `None`.
+    pub fn descriptor_info(&self) -> Result<DescriptorInfo> {
+        let device = self.handle.as_ref().ok_or(Error::DeviceNotOpen)?;
+        let descriptor = device.device_descriptor();
+        Ok(DescriptorInfo {
+            manufacturer: self
+                .read_string_descriptor(device, descriptor.manufacturer_string_index())?,
+            product: self.read_string_descriptor(device, descriptor.product_string_index())?,
+            serial_number: self
+                .read_string_descriptor(device, descriptor.serial_number_string_index())?,
+            device_version: descriptor.device_version(),
+        })
+    }
+
+    fn read_string_descriptor(
+        &self,
+        device: &Device,
+        index: Option<NonZeroU8>,
+    ) -> Result<Option<String>> {
+        let Some(index) = index else {
+            return Ok(None);
+        };
+        let read = || {
+            device
+                .get_string_descriptor(index, US_ENGLISH, self.transport.usb_timeout)
+                .wait()
+        };
+        // Some firmware stalls the first string request after enumeration.
+        // A control-pipe stall is cleared by the next SETUP packet, so one
+        // retry is enough to tell a transient stall from a missing string.
+        let result = match read() {
+            Err(GetDescriptorError::Transfer(TransferError::Stall)) => read(),
+            result => result,
+        };
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(GetDescriptorError::Transfer(TransferError::Stall))
+            | Err(GetDescriptorError::InvalidDescriptor) => Ok(None),
+            Err(err) => Err(Error::Usb {
+                source: Box::new(err),
+                context: "nusb_get_string_descriptor",
+                transfer: None,
+            }),
+        }
     }
 
     pub fn open_in_endpoint(&self, endpoint: Endpoint) -> Result<nusb::Endpoint<Bulk, In>> {
@@ -241,7 +926,7 @@ impl UsbDevice {
         F: FnMut(HotplugEvent) + Send + 'static,
     {
         let mut seen_devices = Vec::<(DeviceId, HotplugDeviceInfo)>::new();
-        let initial_devices = matching_devices(options)?;
+        let initial_devices = matching_devices(&options)?;
         if options.enumerate {
             for device in &initial_devices {
                 callback(HotplugEvent {
@@ -258,12 +943,15 @@ impl UsbDevice {
 
         let running = Arc::new(AtomicBool::new(true));
         let thread_running = Arc::clone(&running);
+        let paused = Arc::new(AtomicBool::new(false));
+        let thread_paused = Arc::clone(&paused);
         let thread = thread::Builder::new()
             .name("vlfd-usb-hotplug".into())
             .spawn(move || {
                 let mut known = seen_devices;
                 while thread_running.load(Ordering::Relaxed) {
-                    if let Ok(devices) = matching_devices(options) {
+                    if let Ok(devices) = matching_devices(&options) {
+                        let muted = thread_paused.load(Ordering::SeqCst);
                         let mut current = devices
                             .iter()
                             .map(|device| {
@@ -272,7 +960,7 @@ impl UsbDevice {
                             .collect::<Vec<_>>();
 
                         for (id, info) in &current {
-                            if !known.iter().any(|(known_id, _)| known_id == id) {
+                            if !muted && !known.iter().any(|(known_id, _)| known_id == id) {
                                 callback(HotplugEvent {
                                     kind: HotplugEventKind::Arrived,
                                     device: info.clone(),
@@ -281,7 +969,7 @@ impl UsbDevice {
                         }
 
                         for (id, info) in &known {
-                            if !current.iter().any(|(current_id, _)| current_id == id) {
+                            if !muted && !current.iter().any(|(current_id, _)| current_id == id) {
                                 callback(HotplugEvent {
                                     kind: HotplugEventKind::Left,
                                     device: info.clone(),
@@ -300,10 +988,30 @@ impl UsbDevice {
 
         Ok(HotplugRegistration {
             running,
+            paused,
             thread: Some(thread),
         })
     }
 
+    /// Delivers hotplug events through a channel holding up to
+    /// [`HOTPLUG_CHANNEL_CAPACITY`] events instead of a callback.
+    ///
+    /// The watcher thread never blocks on the channel: events that arrive
+    /// while it is full are dropped, so a stalled consumer cannot delay
+    /// dropping the registration. Rescan with [`crate::scan`] after falling
+    /// behind. Events for a dropped receiver are discarded until the
+    /// registration is dropped.
+    pub fn hotplug_events(
+        &self,
+        options: HotplugOptions,
+    ) -> Result<(HotplugRegistration, Receiver<HotplugEvent>)> {
+        let (sender, receiver) = mpsc::sync_channel(HOTPLUG_CHANNEL_CAPACITY);
+        let registration = self.register_hotplug_callback(options, move |event| {
+            let _ = sender.try_send(event);
+        })?;
+        Ok((registration, receiver))
+    }
+
     pub(crate) fn clear_halt_all(&mut self) -> Result<()> {
         for endpoint in [
             Endpoint::FifoWrite,
@@ -311,12 +1019,15 @@ impl UsbDevice {
             Endpoint::FifoRead,
             Endpoint::Sync,
         ] {
+            if matches!(endpoint, Endpoint::Sync) && self.sync_strategy != SyncStrategy::Endpoint {
+                continue;
+            }
             self.clear_halt(endpoint)?;
         }
         Ok(())
     }
 
-    fn clear_halt(&mut self, endpoint: Endpoint) -> Result<()> {
+    fn clear_halt(&self, endpoint: Endpoint) -> Result<()> {
         let interface = self.interface.as_ref().ok_or(Error::DeviceNotOpen)?;
         match endpoint {
             Endpoint::FifoWrite | Endpoint::Command => {
@@ -340,6 +1051,97 @@ impl UsbDevice {
     }
 }
 
+impl Transport for UsbDevice {
+    fn transport_config(&self) -> &TransportConfig {
+        UsbDevice::transport_config(self)
+    }
+
+    fn sync_strategy(&self) -> SyncStrategy {
+        UsbDevice::sync_strategy(self)
+    }
+
+    fn read_bytes(&self, endpoint: Endpoint, buffer: &mut [u8]) -> Result<()> {
+        UsbDevice::read_bytes(self, endpoint, buffer)
+    }
+
+    fn write_bytes(&self, endpoint: Endpoint, buffer: &[u8]) -> Result<()> {
+        UsbDevice::write_bytes(self, endpoint, buffer)
+    }
+
+    fn read_words(&self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<()> {
+        UsbDevice::read_words(self, endpoint, buffer)
+    }
+
+    fn write_words(&self, endpoint: Endpoint, buffer: &[u16]) -> Result<()> {
+        UsbDevice::write_words(self, endpoint, buffer)
+    }
+
+    fn read_words_partial(&mut self, endpoint: Endpoint, buffer: &mut [u16]) -> Result<usize> {
+        UsbDevice::read_words_partial(self, endpoint, buffer)
+    }
+
+    fn timeout_for(&self, endpoint: Endpoint) -> Duration {
+        UsbDevice::timeout_for(self, endpoint)
+    }
+
+    fn stall_retries(&self) -> u64 {
+        self.stall_retries.load(Ordering::Relaxed)
+    }
+
+    fn max_packet_size(&self, endpoint: Endpoint) -> usize {
+        match self.max_packet_sizes[endpoint.index()] {
+            0 => constants::DEFAULT_MAX_PACKET_BYTES,
+            size => size,
+        }
+    }
+
+    fn replace_timeout_override(
+        &mut self,
+        endpoint: Endpoint,
+        timeout: Option<Duration>,
+    ) -> Option<Duration> {
+        std::mem::replace(&mut self.endpoint_timeouts[endpoint.index()], timeout)
+    }
+
+    fn pipeline_out_endpoint(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        let endpoint = self.open_out_endpoint(endpoint)?;
+        Ok(Some(Box::new(endpoint)))
+    }
+
+    fn pipeline_in_endpoint(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<Option<Box<dyn PipelineEndpoint>>> {
+        let endpoint = self.open_in_endpoint(endpoint)?;
+        Ok(Some(Box::new(endpoint)))
+    }
+
+    fn out_endpoint_opener(&self, endpoint: Endpoint) -> Result<Option<EndpointOpener>> {
+        let interface = self.interface.clone().ok_or(Error::DeviceNotOpen)?;
+        Ok(Some(Box::new(move || {
+            let endpoint = interface
+                .endpoint::<Bulk, Out>(endpoint as u8)
+                .map_err(|err| usb_error(err, "nusb_open_out_endpoint"))?;
+            Ok(Box::new(endpoint) as Box<dyn PipelineEndpoint>)
+        })))
+    }
+
+    fn clear_halt_all(&mut self) -> Result<()> {
+        UsbDevice::clear_halt_all(self)
+    }
+
+    fn reconnect(&mut self, policy: &ReconnectPolicy) -> Result<()> {
+        UsbDevice::reconnect(self, policy)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        UsbDevice::close(self)
+    }
+}
+
 impl Drop for UsbDevice {
     fn drop(&mut self) {
         let _ = self.close();
@@ -349,9 +1151,30 @@ impl Drop for UsbDevice {
 #[derive(Debug)]
 pub struct HotplugRegistration {
     running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
     thread: Option<thread::JoinHandle<()>>,
 }
 
+impl HotplugRegistration {
+    /// Mutes the callback without stopping the watcher, e.g. across a
+    /// deliberate power cycle.
+    ///
+    /// The watcher keeps tracking attached devices while paused, so changes
+    /// that happen during the pause are never reported after
+    /// [`HotplugRegistration::resume`].
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
 impl Drop for HotplugRegistration {
     fn drop(&mut self) {
         self.running.store(false, Ordering::SeqCst);
@@ -373,17 +1196,28 @@ fn bulk_read(
         .reader(IO_BUFFER_SIZE)
         .with_read_timeout(timeout);
 
-    reader
-        .read_exact(buffer)
-        .map_err(|err| io_error(err, "nusb_bulk_read"))?;
-    Ok(())
+    #[cfg(feature = "wire-log")]
+    let started = std::time::Instant::now();
+    let mut transfer = UsbTransfer {
+        endpoint,
+        direction: TransferDirection::In,
+        requested: buffer.len(),
+        completed: 0,
+        word_aligned: None,
+    };
+    let result = read_counted(&mut reader, buffer, &mut transfer.completed)
+        .map_err(|err| io_error(err, "nusb_bulk_read", timeout, transfer));
+    #[cfg(feature = "wire-log")]
+    crate::wire_log::transfer("IN", endpoint, buffer, started.elapsed(), result.is_ok());
+    result
 }
 
 fn bulk_write(
     interface: &Interface,
     endpoint: Endpoint,
-    buffer: &[u8],
+    parts: &[&[u8]],
     timeout: Duration,
+    zero_length_packet: bool,
 ) -> Result<()> {
     let mut writer = interface
         .endpoint::<Bulk, Out>(endpoint as u8)
@@ -391,16 +1225,64 @@ fn bulk_write(
         .writer(IO_BUFFER_SIZE)
         .with_write_timeout(timeout);
 
-    writer
-        .write_all(buffer)
-        .map_err(|err| io_error(err, "nusb_bulk_write"))?;
-    writer
-        .flush()
-        .map_err(|err| io_error(err, "nusb_bulk_flush"))?;
-    Ok(())
+    #[cfg(feature = "wire-log")]
+    let started = std::time::Instant::now();
+    let mut transfer = UsbTransfer {
+        endpoint,
+        direction: TransferDirection::Out,
+        requested: parts.iter().map(|part| part.len()).sum(),
+        completed: 0,
+        word_aligned: None,
+    };
+    let result = write_parts(&mut writer, parts, timeout, &mut transfer, |writer| {
+        if zero_length_packet {
+            writer.flush_end()
+        } else {
+            writer.flush()
+        }
+    });
+    #[cfg(feature = "wire-log")]
+    crate::wire_log::transfer(
+        "OUT",
+        endpoint,
+        &parts.concat(),
+        started.elapsed(),
+        result.is_ok(),
+    );
+    result
+}
+
+/// Writes `parts` through `writer` and then `flush`es it, counting in
+/// `transfer` the bytes the writer took.
+fn write_parts<W: Write>(
+    writer: &mut W,
+    parts: &[&[u8]],
+    timeout: Duration,
+    transfer: &mut UsbTransfer,
+    flush: impl FnOnce(&mut W) -> std::io::Result<()>,
+) -> Result<()> {
+    parts
+        .iter()
+        .try_for_each(|part| write_counted(writer, part, &mut transfer.completed))
+        .map_err(|err| io_error(err, "nusb_bulk_write", timeout, *transfer))?;
+    flush(writer).map_err(|err| io_error(err, "nusb_bulk_flush", timeout, *transfer))
 }
 
-fn matching_devices(options: HotplugOptions) -> Result<Vec<DeviceInfo>> {
+/// A transfer made only of full packets is not terminated until the
+/// device sees a short or zero-length packet.
+fn needs_zero_length_packet(len: usize, max_packet: usize) -> bool {
+    max_packet != 0 && len != 0 && len % max_packet == 0
+}
+
+pub(crate) fn list_matching_devices(vid: u16, pid: u16) -> Result<Vec<DeviceInfo>> {
+    Ok(nusb::list_devices()
+        .wait()
+        .map_err(|err| usb_error(err, "nusb_list_devices"))?
+        .filter(|device| device.vendor_id() == vid && device.product_id() == pid)
+        .collect())
+}
+
+fn matching_devices(options: &HotplugOptions) -> Result<Vec<DeviceInfo>> {
     let devices = nusb::list_devices()
         .wait()
         .map_err(|err| usb_error(err, "nusb_list_devices"))?;
@@ -415,14 +1297,39 @@ fn matching_devices(options: HotplugOptions) -> Result<Vec<DeviceInfo>> {
                 && options
                     .class_code
                     .is_none_or(|class_code| device.class() == class_code)
+                && options.matches_location(&HotplugDeviceInfo::from_device_info(device))
         })
         .collect())
 }
 
+/// Serializes `words` into `out` as little-endian bytes.
+pub(crate) fn words_to_bytes(words: &[u16], out: &mut [u8]) {
+    #[cfg(target_endian = "little")]
+    out[..std::mem::size_of_val(words)].copy_from_slice(words_as_bytes(words));
+    #[cfg(target_endian = "big")]
+    for (word, chunk) in words.iter().zip(out.chunks_exact_mut(2)) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// Decodes little-endian `bytes` into `out`, stopping at whichever ends
+/// first.
+pub(crate) fn bytes_into_words(bytes: &[u8], out: &mut [u16]) {
+    let len = out.len().min(bytes.len() / 2);
+    #[cfg(target_endian = "little")]
+    words_as_bytes_mut(&mut out[..len]).copy_from_slice(&bytes[..len * 2]);
+    #[cfg(target_endian = "big")]
+    for (word, chunk) in out[..len].iter_mut().zip(bytes.chunks_exact(2)) {
+        *word = u16::from_le_bytes([chunk[0], chunk[1]]);
+    }
+}
+
+#[cfg(target_endian = "little")]
 fn words_as_bytes(words: &[u16]) -> &[u8] {
     unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, std::mem::size_of_val(words)) }
 }
 
+#[cfg(target_endian = "little")]
 fn words_as_bytes_mut(words: &mut [u16]) -> &mut [u8] {
     unsafe {
         std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, std::mem::size_of_val(words))
@@ -433,23 +1340,115 @@ fn usb_error(err: nusb::Error, context: &'static str) -> Error {
     Error::Usb {
         source: Box::new(err),
         context,
+        transfer: None,
+    }
+}
+
+fn is_stall(err: &Error) -> bool {
+    err.kind() == ErrorKind::Usb(UsbErrorKind::Pipe)
+}
+
+/// Whether a failed bulk transfer stopped before any byte moved. Failures
+/// without transfer details came from opening the endpoint.
+fn moved_nothing(err: &Error) -> bool {
+    match err {
+        Error::Usb {
+            transfer: Some(transfer),
+            ..
+        } => transfer.completed == 0,
+        _ => true,
+    }
+}
+
+/// `read_exact` that keeps count of the bytes received in `completed`.
+fn read_counted(
+    reader: &mut impl Read,
+    buffer: &mut [u8],
+    completed: &mut usize,
+) -> std::io::Result<()> {
+    while *completed < buffer.len() {
+        match reader.read(&mut buffer[*completed..]) {
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) => *completed += len,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
     }
+    Ok(())
 }
 
-fn io_error(err: std::io::Error, context: &'static str) -> Error {
+/// `write_all` that adds to `completed` the bytes copied into `writer`.
+/// For an endpoint writer those are buffered, not yet sent: they go out
+/// once a packet fills or the writer is flushed.
+fn write_counted(
+    writer: &mut impl Write,
+    mut part: &[u8],
+    completed: &mut usize,
+) -> std::io::Result<()> {
+    while !part.is_empty() {
+        match writer.write(part) {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(len) => {
+                *completed += len;
+                part = &part[len..];
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+fn io_error(
+    err: std::io::Error,
+    context: &'static str,
+    timeout: Duration,
+    transfer: UsbTransfer,
+) -> Error {
     if err.kind() == std::io::ErrorKind::TimedOut {
-        Error::Timeout(context)
+        Error::TransferTimeout {
+            context,
+            endpoint: transfer.endpoint,
+            timeout,
+        }
     } else {
         Error::Usb {
             source: Box::new(err),
             context,
+            transfer: Some(transfer),
         }
     }
 }
 
+/// Records on a failed word transfer whether it stopped between words.
+fn mark_word_transfer(err: Error) -> Error {
+    match err {
+        Error::Usb {
+            source,
+            context,
+            transfer: Some(mut transfer),
+        } => {
+            transfer.word_aligned = Some(transfer.completed % 2 == 0);
+            Error::Usb {
+                source,
+                context,
+                transfer: Some(transfer),
+            }
+        }
+        Error::Retried { attempts, source } => Error::Retried {
+            attempts,
+            source: Box::new(mark_word_transfer(*source)),
+        },
+        err => err,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::TransportConfig;
+    use super::{
+        DEFAULT_FIXED_SYNC_DELAY, DeviceLocation, Endpoint, HotplugDeviceInfo, HotplugOptions,
+        SyncPolicy, SyncStrategy, TransportConfig, UsbDevice,
+    };
     use std::time::Duration;
 
     #[test]
@@ -459,5 +1458,356 @@ mod tests {
         assert_eq!(config.sync_timeout, Duration::from_secs(1));
         assert!(!config.reset_on_open);
         assert!(config.clear_halt_on_open);
+        assert_eq!(config.sync_strategy, SyncStrategy::Endpoint);
+        assert!(config.detect_sync_endpoint);
+
+        let policy = SyncPolicy::default();
+        assert_eq!(policy.timeout, config.sync_timeout);
+        assert!(!policy.initial_interval.is_zero());
+        assert!(policy.initial_interval <= policy.max_interval);
+    }
+
+    #[test]
+    fn missing_sync_endpoint_falls_back_only_when_detection_is_enabled() {
+        let config = TransportConfig::default();
+        assert_eq!(config.resolve_sync_strategy(true), SyncStrategy::Endpoint);
+        assert_eq!(
+            config.resolve_sync_strategy(false),
+            SyncStrategy::FixedDelay(DEFAULT_FIXED_SYNC_DELAY)
+        );
+
+        let strict = TransportConfig {
+            detect_sync_endpoint: false,
+            ..config
+        };
+        assert_eq!(strict.resolve_sync_strategy(false), SyncStrategy::Endpoint);
+
+        let fixed = TransportConfig {
+            sync_strategy: SyncStrategy::FixedDelay(Duration::from_millis(5)),
+            ..config
+        };
+        assert_eq!(
+            fixed.resolve_sync_strategy(true),
+            SyncStrategy::FixedDelay(Duration::from_millis(5))
+        );
+        let none = TransportConfig {
+            sync_strategy: SyncStrategy::None,
+            ..config
+        };
+        assert_eq!(none.resolve_sync_strategy(false), SyncStrategy::None);
+    }
+
+    #[test]
+    fn locations_match_by_address_or_port_chain() {
+        let device = HotplugDeviceInfo {
+            bus_number: 3,
+            address: 17,
+            port_numbers: vec![1, 4, 2],
+            vendor_id: None,
+            product_id: None,
+            class_code: None,
+            sub_class_code: None,
+            protocol_code: None,
+        };
+        let by_address = DeviceLocation::Address {
+            bus: 3,
+            address: 17,
+        };
+        let by_ports = DeviceLocation::Ports {
+            bus: 3,
+            ports: vec![1, 4, 2],
+        };
+        assert!(by_address.matches(&device));
+        assert!(by_ports.matches(&device));
+        assert!(
+            !DeviceLocation::Address {
+                bus: 2,
+                address: 17
+            }
+            .matches(&device)
+        );
+        assert!(
+            !DeviceLocation::Ports {
+                bus: 3,
+                ports: vec![1, 4],
+            }
+            .matches(&device)
+        );
+        assert_eq!(by_address.to_string(), "bus 3 address 17");
+        assert_eq!(by_ports.to_string(), "bus 3 port 1.4.2");
+
+        assert!(HotplugOptions::default().matches_location(&device));
+        let fixture = HotplugOptions {
+            bus_number: Some(3),
+            port_numbers: Some(vec![1, 4, 2]),
+            ..HotplugOptions::default()
+        };
+        assert!(fixture.matches_location(&device));
+        let other_port = HotplugOptions {
+            port_numbers: Some(vec![1, 4, 3]),
+            ..HotplugOptions::default()
+        };
+        assert!(!other_port.matches_location(&device));
+    }
+
+    #[test]
+    fn endpoint_timeouts_override_the_default() {
+        let mut usb = UsbDevice::with_transport_config(TransportConfig::default()).unwrap();
+        usb.set_timeout(Duration::from_millis(50));
+        usb.set_timeout_for(Endpoint::FifoRead, Duration::from_secs(30));
+        assert_eq!(
+            usb.timeout_for(Endpoint::FifoWrite),
+            Duration::from_millis(50)
+        );
+        assert_eq!(usb.timeout_for(Endpoint::FifoRead), Duration::from_secs(30));
+
+        usb.clear_timeout_for(Endpoint::FifoRead);
+        assert_eq!(
+            usb.timeout_for(Endpoint::FifoRead),
+            Duration::from_millis(50)
+        );
+
+        let err = super::io_error(
+            std::io::ErrorKind::TimedOut.into(),
+            "nusb_bulk_read",
+            Duration::from_millis(50),
+            fifo_read(64, 0),
+        );
+        assert_eq!(
+            err.to_string(),
+            "operation `nusb_bulk_read` on the FifoRead endpoint timed out after 50ms"
+        );
+    }
+
+    fn fifo_read(requested: usize, completed: usize) -> crate::UsbTransfer {
+        crate::UsbTransfer {
+            endpoint: Endpoint::FifoRead,
+            direction: crate::TransferDirection::In,
+            requested,
+            completed,
+            word_aligned: None,
+        }
+    }
+
+    #[test]
+    fn failed_reads_record_how_far_they_got() {
+        use crate::error::Error;
+        use std::io::{self, Read};
+
+        /// Hands out `left` bytes in short reads, then fails.
+        struct Dropout {
+            left: usize,
+        }
+        impl Read for Dropout {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.left == 0 {
+                    return Err(io::ErrorKind::ConnectionReset.into());
+                }
+                let len = buf.len().min(self.left).min(3);
+                self.left -= len;
+                Ok(len)
+            }
+        }
+
+        let mut buffer = [0u8; 16];
+        for (received, aligned) in [(7, false), (10, true)] {
+            let mut transfer = fifo_read(buffer.len(), 0);
+            let err = super::read_counted(
+                &mut Dropout { left: received },
+                &mut buffer,
+                &mut transfer.completed,
+            )
+            .unwrap_err();
+            let err = super::mark_word_transfer(Error::Retried {
+                attempts: 2,
+                source: Box::new(super::io_error(
+                    err,
+                    "nusb_bulk_read",
+                    Duration::from_secs(1),
+                    transfer,
+                )),
+            });
+            let Error::Retried { source, .. } = err else {
+                panic!("unexpected error: {err}");
+            };
+            let Error::Usb {
+                transfer: Some(transfer),
+                ..
+            } = *source
+            else {
+                panic!("unexpected error: {source}");
+            };
+            assert_eq!(transfer.completed, received);
+            assert_eq!(transfer.word_aligned, Some(aligned));
+        }
+    }
+
+    #[test]
+    fn failed_writes_count_the_bytes_the_writer_took() {
+        use crate::error::Error;
+        use std::io::{self, Write};
+
+        /// Takes `left` bytes in short writes, then fails; flushing fails
+        /// when `flush_fails` is set.
+        struct Dropout {
+            left: usize,
+            flush_fails: bool,
+        }
+        impl Write for Dropout {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.left == 0 {
+                    return Err(io::ErrorKind::ConnectionReset.into());
+                }
+                let len = buf.len().min(self.left).min(5);
+                self.left -= len;
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                if self.flush_fails {
+                    return Err(io::ErrorKind::ConnectionReset.into());
+                }
+                Ok(())
+            }
+        }
+
+        let parts: [&[u8]; 2] = [&[0; 6], &[0; 10]];
+        for (left, flush_fails, context, completed) in [
+            (9, false, "nusb_bulk_write", 9),
+            (16, true, "nusb_bulk_flush", 16),
+        ] {
+            let mut transfer = crate::UsbTransfer {
+                endpoint: Endpoint::FifoWrite,
+                direction: crate::TransferDirection::Out,
+                requested: 16,
+                completed: 0,
+                word_aligned: None,
+            };
+            let err = super::write_parts(
+                &mut Dropout { left, flush_fails },
+                &parts,
+                Duration::from_secs(1),
+                &mut transfer,
+                |writer| writer.flush(),
+            )
+            .unwrap_err();
+            let Error::Usb {
+                context: actual,
+                transfer: Some(transfer),
+                ..
+            } = &err
+            else {
+                panic!("unexpected error: {err}");
+            };
+            assert_eq!(*actual, context);
+            assert_eq!(transfer.completed, completed);
+            // Bytes the writer took may be on the wire, so neither failure
+            // is safe to replay.
+            assert!(!super::moved_nothing(&err));
+        }
+    }
+
+    #[test]
+    fn only_stalls_are_retried() {
+        use crate::error::{Error, ErrorKind, UsbErrorKind};
+        use nusb::transfer::TransferError;
+
+        let transfer_error = |err: TransferError| Error::Usb {
+            source: Box::new(std::io::Error::from(err)),
+            context: "nusb_bulk_read",
+            transfer: None,
+        };
+        let usb = UsbDevice::with_transport_config(TransportConfig {
+            stall_retries: 3,
+            ..TransportConfig::default()
+        })
+        .unwrap();
+
+        let mut calls = 0;
+        let err = usb
+            .retry_stalled(Endpoint::FifoRead, || {
+                calls += 1;
+                Err(transfer_error(TransferError::Disconnected))
+            })
+            .unwrap_err();
+        assert_eq!(calls, 1);
+        assert_eq!(err.kind(), ErrorKind::Usb(UsbErrorKind::NoDevice));
+
+        let retried = Error::Retried {
+            attempts: 2,
+            source: Box::new(transfer_error(TransferError::Stall)),
+        };
+        assert_eq!(retried.kind(), ErrorKind::Usb(UsbErrorKind::Pipe));
+        assert_eq!(retried.context(), Some("nusb_bulk_read"));
+        assert!(retried.to_string().ends_with("(after 2 attempts)"));
+    }
+
+    #[test]
+    fn stalls_after_partial_transfers_are_not_replayed() {
+        use crate::error::{Error, ErrorKind, UsbErrorKind};
+        use nusb::transfer::TransferError;
+
+        let stall_after = |completed| Error::Usb {
+            source: Box::new(std::io::Error::from(TransferError::Stall)),
+            context: "nusb_bulk_write",
+            transfer: Some(crate::UsbTransfer {
+                endpoint: Endpoint::FifoWrite,
+                direction: crate::TransferDirection::Out,
+                requested: 2048,
+                completed,
+                word_aligned: None,
+            }),
+        };
+        let usb = UsbDevice::with_transport_config(TransportConfig {
+            stall_retries: 3,
+            ..TransportConfig::default()
+        })
+        .unwrap();
+
+        let mut calls = 0;
+        let err = usb
+            .retry_stalled(Endpoint::FifoWrite, || {
+                calls += 1;
+                Err(stall_after(512))
+            })
+            .unwrap_err();
+        assert_eq!(calls, 1);
+        assert_eq!(err.kind(), ErrorKind::Usb(UsbErrorKind::Pipe));
+
+        // A stall before any data moved goes on to clear the halt, which
+        // needs an open device here.
+        let err = usb
+            .retry_stalled(Endpoint::FifoWrite, || Err(stall_after(0)))
+            .unwrap_err();
+        assert!(matches!(err, Error::DeviceNotOpen));
+    }
+
+    #[test]
+    fn partial_reads_carry_trailing_bytes() {
+        let mut usb = UsbDevice::with_transport_config(TransportConfig::default()).unwrap();
+        usb.read_carry[Endpoint::FifoRead.index()] = vec![0x34, 0x12, 0xcd, 0xab, 0x01];
+
+        // The carried bytes cover the request, so no transfer is attempted.
+        let mut words = [0u16; 2];
+        assert_eq!(
+            usb.read_words_partial(Endpoint::FifoRead, &mut words)
+                .unwrap(),
+            2
+        );
+        assert_eq!(words, [0x1234, 0xabcd]);
+        assert_eq!(usb.read_carry[Endpoint::FifoRead.index()], [0x01]);
+    }
+
+    #[test]
+    fn zero_length_packets_follow_whole_packet_writes() {
+        use super::needs_zero_length_packet;
+
+        assert!(!needs_zero_length_packet(63, 64));
+        assert!(needs_zero_length_packet(64, 64));
+        assert!(!needs_zero_length_packet(65, 64));
+        assert!(needs_zero_length_packet(128, 64));
+        assert!(!needs_zero_length_packet(0, 64));
+        // Max packet size unknown: never send one.
+        assert!(!needs_zero_length_packet(64, 0));
     }
 }
@@ -0,0 +1,205 @@
+use crate::config::Config;
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const CACHE_HEADER: &str = "vlfd-rs warm-start cache v1";
+const TABLE_WORDS: usize = 32;
+
+/// Device state persisted after a full initialization, keyed by serial.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheEntry {
+    pub(crate) serial: String,
+    pub(crate) smims_version: u16,
+    /// Encryption table after decoding.
+    pub(crate) table: [u16; TABLE_WORDS],
+    pub(crate) config: Config,
+}
+
+impl CacheEntry {
+    fn to_line(&self) -> String {
+        let mut line = format!("{} {:04x} ", self.serial, self.smims_version);
+        push_hex_words(&mut line, &self.table);
+        line.push(' ');
+        push_hex_words(&mut line, self.config.words());
+        line
+    }
+
+    /// Parses one cache line; any malformed field rejects the whole entry.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let serial = fields.next().filter(|serial| !serial.is_empty())?;
+        let smims_version = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let table = parse_hex_words::<TABLE_WORDS>(fields.next()?)?;
+        let config = parse_hex_words::<{ Config::WORD_COUNT }>(fields.next()?)?;
+        if fields.next().is_some() {
+            return None;
+        }
+        let config = Config::from_words(config);
+        // A snapshot whose config disagrees with its own version was
+        // written by a different firmware or corrupted in place.
+        if config.smims_version_raw() != smims_version {
+            return None;
+        }
+        Some(Self {
+            serial: serial.to_owned(),
+            smims_version,
+            table,
+            config,
+        })
+    }
+}
+
+/// Serials are used as whitespace-separated keys, so only boards with a
+/// plain serial can be cached.
+pub(crate) fn is_cacheable_serial(serial: &str) -> bool {
+    !serial.is_empty() && serial.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Returns the cached entry for `serial`. A missing, unreadable or
+/// corrupted cache is treated as a miss.
+pub(crate) fn load_entry(path: &Path, serial: &str) -> Option<CacheEntry> {
+    let contents = fs::read_to_string(path).ok()?;
+    parse_entries(&contents)
+        .into_iter()
+        .find(|entry| entry.serial == serial)
+}
+
+/// Inserts or replaces the entry for `entry.serial`, keeping other boards'
+/// entries and dropping any corrupted lines.
+pub(crate) fn store_entry(path: &Path, entry: &CacheEntry) -> io::Result<()> {
+    let mut entries = fs::read_to_string(path)
+        .map(|contents| parse_entries(&contents))
+        .unwrap_or_default();
+    entries.retain(|existing| existing.serial != entry.serial);
+    entries.push(entry.clone());
+    write_entries(path, &entries)
+}
+
+/// Drops the entry for `serial` after it failed validation.
+pub(crate) fn remove_entry(path: &Path, serial: &str) -> io::Result<()> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let mut entries = parse_entries(&contents);
+    entries.retain(|entry| entry.serial != serial);
+    write_entries(path, &entries)
+}
+
+fn parse_entries(contents: &str) -> Vec<CacheEntry> {
+    let mut lines = contents.lines();
+    if lines.next() != Some(CACHE_HEADER) {
+        return Vec::new();
+    }
+    lines.filter_map(CacheEntry::parse).collect()
+}
+
+/// Writes through a temporary file so a crash never leaves a half-written
+/// cache behind.
+fn write_entries(path: &Path, entries: &[CacheEntry]) -> io::Result<()> {
+    let mut contents = String::from(CACHE_HEADER);
+    contents.push('\n');
+    for entry in entries {
+        contents.push_str(&entry.to_line());
+        contents.push('\n');
+    }
+    let mut temp = PathBuf::from(path);
+    temp.as_mut_os_string().push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path)
+}
+
+fn push_hex_words(out: &mut String, words: &[u16]) {
+    for word in words {
+        let _ = write!(out, "{word:04x}");
+    }
+}
+
+fn parse_hex_words<const N: usize>(field: &str) -> Option<[u16; N]> {
+    if field.len() != N * 4 || !field.is_ascii() {
+        return None;
+    }
+    let mut words = [0u16; N];
+    for (word, chunk) in words.iter_mut().zip(field.as_bytes().chunks(4)) {
+        *word = u16::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CACHE_HEADER, CacheEntry, load_entry, remove_entry, store_entry};
+    use crate::config::Config;
+    use std::{fs, path::PathBuf};
+
+    fn entry(serial: &str, version: u16) -> CacheEntry {
+        let mut words = [0u16; Config::WORD_COUNT];
+        words[32] = version;
+        words[33] = 2048;
+        let mut table = [0u16; 32];
+        for (index, word) in table.iter_mut().enumerate() {
+            *word = (index as u16).wrapping_mul(0x1357);
+        }
+        CacheEntry {
+            serial: serial.to_owned(),
+            smims_version: version,
+            table,
+            config: Config::from_words(words),
+        }
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "vlfd-warm-start-{name}-{}.cache",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn entries_round_trip_per_serial() {
+        let path = cache_path("roundtrip");
+        store_entry(&path, &entry("A1", 0x0203)).unwrap();
+        store_entry(&path, &entry("B2", 0x0204)).unwrap();
+        store_entry(&path, &entry("A1", 0x0205)).unwrap();
+
+        assert_eq!(load_entry(&path, "A1"), Some(entry("A1", 0x0205)));
+        assert_eq!(load_entry(&path, "B2"), Some(entry("B2", 0x0204)));
+        assert_eq!(load_entry(&path, "C3"), None);
+
+        remove_entry(&path, "A1").unwrap();
+        assert_eq!(load_entry(&path, "A1"), None);
+        assert!(load_entry(&path, "B2").is_some());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupted_caches_are_treated_as_misses() {
+        let path = cache_path("corrupt");
+        let good = entry("A1", 0x0203).to_line();
+        let mut truncated = entry("B2", 0x0203).to_line();
+        truncated.truncate(truncated.len() - 3);
+        // Version field disagrees with the config snapshot.
+        let inconsistent = good.replacen("A1 0203", "C3 0204", 1);
+        fs::write(
+            &path,
+            format!("{CACHE_HEADER}\n{good}\n{truncated}\n{inconsistent}\ngarbage\n"),
+        )
+        .unwrap();
+
+        assert!(load_entry(&path, "A1").is_some());
+        assert_eq!(load_entry(&path, "B2"), None);
+        assert_eq!(load_entry(&path, "C3"), None);
+
+        fs::write(&path, format!("some other format\n{good}\n")).unwrap();
+        assert_eq!(load_entry(&path, "A1"), None);
+
+        // Storing over a corrupted file rewrites it cleanly.
+        store_entry(&path, &entry("B2", 0x0203)).unwrap();
+        assert!(load_entry(&path, "B2").is_some());
+        fs::remove_file(&path).unwrap();
+    }
+}
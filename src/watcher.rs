@@ -0,0 +1,132 @@
+use crate::error::{Error, Result};
+use crate::session::Board;
+use crate::usb::{
+    DeviceLocation, HotplugDeviceInfo, HotplugEventKind, HotplugOptions, HotplugRegistration,
+    TransportConfig, UsbDevice,
+};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WatchOptions {
+    /// Transport used for every opened board; its `device` selects which
+    /// boards are watched.
+    pub transport: TransportConfig,
+    /// Attempts to open and initialize an arrived board. Freshly enumerated
+    /// boards often need a few hundred milliseconds before the control
+    /// endpoints respond.
+    pub open_attempts: u32,
+    pub retry_delay: Duration,
+    /// Report boards already attached when the watcher starts.
+    pub enumerate: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            transport: TransportConfig::default(),
+            open_attempts: 5,
+            retry_delay: Duration::from_millis(200),
+            enumerate: true,
+        }
+    }
+}
+
+pub enum BoardEvent {
+    /// A board arrived and was opened and initialized.
+    Arrived {
+        device: HotplugDeviceInfo,
+        board: Box<Board>,
+    },
+    /// A board arrived but every open attempt failed; `error` is the last
+    /// failure. No `Left` event follows for it.
+    OpenFailed {
+        device: HotplugDeviceInfo,
+        error: Error,
+    },
+    /// A board previously delivered by `Arrived` was unplugged. `device`
+    /// has the same bus number and address as the arrival.
+    Left { device: HotplugDeviceInfo },
+}
+
+/// Watches for boards and hands each new one over already initialized.
+///
+/// Boards are opened on the watcher thread, so a slow arrival delays
+/// later events. Dropping the watcher stops it; boards already delivered
+/// stay open.
+pub struct BoardWatcher {
+    _registration: HotplugRegistration,
+}
+
+impl BoardWatcher {
+    pub fn new<F>(callback: F) -> Result<Self>
+    where
+        F: FnMut(BoardEvent) + Send + 'static,
+    {
+        Self::with_options(WatchOptions::default(), callback)
+    }
+
+    pub fn with_options<F>(options: WatchOptions, mut callback: F) -> Result<Self>
+    where
+        F: FnMut(BoardEvent) + Send + 'static,
+    {
+        let transport = options.transport;
+        let hotplug = HotplugOptions {
+            vendor_id: Some(transport.device.vendor_id),
+            product_id: Some(transport.device.product_id),
+            enumerate: options.enumerate,
+            ..HotplugOptions::default()
+        };
+        let mut delivered = Vec::<(u8, u8)>::new();
+        let registration = UsbDevice::with_transport_config(transport)?.register_hotplug_callback(
+            hotplug,
+            move |event| {
+                let identity = (event.device.bus_number, event.device.address);
+                match event.kind {
+                    HotplugEventKind::Arrived => match open_arrived(&options, &event.device) {
+                        Ok(board) => {
+                            delivered.push(identity);
+                            callback(BoardEvent::Arrived {
+                                device: event.device,
+                                board: Box::new(board),
+                            });
+                        }
+                        Err(error) => callback(BoardEvent::OpenFailed {
+                            device: event.device,
+                            error,
+                        }),
+                    },
+                    HotplugEventKind::Left => {
+                        if let Some(index) = delivered.iter().position(|seen| *seen == identity) {
+                            delivered.swap_remove(index);
+                            callback(BoardEvent::Left {
+                                device: event.device,
+                            });
+                        }
+                    }
+                }
+            },
+        )?;
+        Ok(Self {
+            _registration: registration,
+        })
+    }
+}
+
+fn open_arrived(options: &WatchOptions, device: &HotplugDeviceInfo) -> Result<Board> {
+    let location = DeviceLocation::Address {
+        bus: device.bus_number,
+        address: device.address,
+    };
+    let mut attempt = 1;
+    loop {
+        match Board::open_at_with_transport(options.transport, &location) {
+            Ok(board) => return Ok(board),
+            Err(err) if attempt >= options.open_attempts => return Err(err),
+            Err(_) => {
+                attempt += 1;
+                thread::sleep(options.retry_delay);
+            }
+        }
+    }
+}
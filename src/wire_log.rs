@@ -0,0 +1,203 @@
+//! Trace-level logging of raw endpoint traffic and of the encryption step,
+//! under the `vlfd_rs::wire` target.
+
+use crate::transport::PipelineEndpoint;
+use crate::usb::Endpoint;
+use nusb::transfer::{Buffer, Completion};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const TARGET: &str = "vlfd_rs::wire";
+const DEFAULT_HEXDUMP_BYTES: usize = 32;
+
+static HEXDUMP_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_HEXDUMP_BYTES);
+
+/// Sets how many leading bytes of each transfer are hexdumped (default 32).
+pub fn set_wire_log_hexdump_bytes(bytes: usize) {
+    HEXDUMP_BYTES.store(bytes, Ordering::Relaxed);
+}
+
+pub(crate) fn transfer(
+    direction: &'static str,
+    endpoint: Endpoint,
+    bytes: &[u8],
+    elapsed: Duration,
+    ok: bool,
+) {
+    if !log::log_enabled!(target: TARGET, log::Level::Trace) {
+        return;
+    }
+    let status = if ok { "" } else { " (failed)" };
+    log::trace!(
+        target: TARGET,
+        "{direction} {endpoint:?} {} bytes in {elapsed:?}{status}: {}",
+        bytes.len(),
+        HexDump::new(bytes),
+    );
+}
+
+/// Pipelined endpoint that logs each completion as [`transfer`] logs a
+/// blocking transfer, timed from its submission.
+pub(crate) struct LoggedPipeline {
+    inner: Box<dyn PipelineEndpoint>,
+    direction: &'static str,
+    endpoint: Endpoint,
+    /// Submission time of each pending transfer, oldest first.
+    submitted: VecDeque<Instant>,
+}
+
+impl LoggedPipeline {
+    pub(crate) fn wrap(
+        direction: &'static str,
+        endpoint: Endpoint,
+        inner: Box<dyn PipelineEndpoint>,
+    ) -> Box<dyn PipelineEndpoint> {
+        Box::new(Self {
+            inner,
+            direction,
+            endpoint,
+            submitted: VecDeque::new(),
+        })
+    }
+}
+
+impl PipelineEndpoint for LoggedPipeline {
+    fn max_packet_size(&self) -> usize {
+        self.inner.max_packet_size()
+    }
+
+    fn pending(&self) -> usize {
+        self.inner.pending()
+    }
+
+    fn allocate(&self, len: usize) -> Buffer {
+        self.inner.allocate(len)
+    }
+
+    fn submit(&mut self, buffer: Buffer) {
+        self.submitted.push_back(Instant::now());
+        self.inner.submit(buffer);
+    }
+
+    fn wait_next_complete(&mut self, timeout: Duration) -> Option<Completion> {
+        let completion = self.inner.wait_next_complete(timeout)?;
+        let elapsed = self
+            .submitted
+            .pop_front()
+            .map_or(Duration::ZERO, |submitted| submitted.elapsed());
+        let len = completion.actual_len.min(completion.buffer.len());
+        transfer(
+            self.direction,
+            self.endpoint,
+            &completion.buffer[..len],
+            elapsed,
+            completion.status.is_ok(),
+        );
+        Some(completion)
+    }
+
+    fn cancel_all(&mut self) {
+        // Cancelled transfers still complete, so their times stay queued.
+        self.inner.cancel_all();
+    }
+}
+
+/// Logs `words` around the XOR step; `stage` names the step and side, e.g.
+/// `"encrypt plain"`.
+pub(crate) fn crypto(stage: &'static str, words: &[u16]) {
+    if !log::log_enabled!(target: TARGET, log::Level::Trace) {
+        return;
+    }
+    let limit = HEXDUMP_BYTES.load(Ordering::Relaxed) / 2;
+    let shown = &words[..words.len().min(limit)];
+    let more = if shown.len() < words.len() { " .." } else { "" };
+    log::trace!(
+        target: TARGET,
+        "{stage} {} words: {}{more}",
+        words.len(),
+        shown
+            .iter()
+            .map(|word| format!("{word:04x}"))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+}
+
+struct HexDump<'a> {
+    bytes: &'a [u8],
+    limit: usize,
+}
+
+impl<'a> HexDump<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            limit: HEXDUMP_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = &self.bytes[..self.bytes.len().min(self.limit)];
+        for (index, byte) in shown.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        if shown.len() < self.bytes.len() {
+            f.write_str(" ..")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HexDump, LoggedPipeline};
+    use crate::{Board, Endpoint, IoConfig, Transport};
+
+    #[test]
+    fn hexdump_is_bounded() {
+        let bytes = [0x01, 0xab, 0xff, 0x10];
+        let dump = HexDump {
+            bytes: &bytes,
+            limit: 3,
+        };
+        assert_eq!(dump.to_string(), "01 ab ff ..");
+        let dump = HexDump {
+            bytes: &bytes,
+            limit: 8,
+        };
+        assert_eq!(dump.to_string(), "01 ab ff 10");
+    }
+
+    #[test]
+    fn logged_pipelines_pass_completions_through() {
+        let mut board = Board::open_mock().unwrap();
+        board.mock().set_pipelined(true);
+        let mut io = board.configure_io(&IoConfig::default()).unwrap();
+        let tx = (0..4096).collect::<Vec<u16>>();
+        let mut rx = vec![0; tx.len()];
+        io.transfer_pipelined(&tx, &mut rx).unwrap();
+        assert_eq!(rx, tx);
+        io.finish().unwrap();
+
+        let inner = board
+            .usb()
+            .pipeline_in_endpoint(Endpoint::FifoRead)
+            .unwrap()
+            .unwrap();
+        let mut logged = LoggedPipeline::wrap("IN", Endpoint::FifoRead, inner);
+        logged.submit(logged.allocate(8));
+        logged.cancel_all();
+        let completion = logged
+            .wait_next_complete(std::time::Duration::ZERO)
+            .unwrap();
+        assert!(completion.status.is_err());
+        assert_eq!(logged.pending(), 0);
+    }
+}